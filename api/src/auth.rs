@@ -15,6 +15,7 @@ lazy_static! {
     static ref BASIC_AUTH_REGEX: Regex = Regex::new(r"^Basic (?P<token>.*$)").unwrap();
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum BasicAuthError {
     Base64DecodeError(base64::DecodeError),
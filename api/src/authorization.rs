@@ -0,0 +1,305 @@
+use std::str::FromStr;
+
+use juniper::{graphql_value, FieldError, ScalarValue};
+use mongodb::bson::oid::ObjectId;
+use rocket::http::Status;
+
+use crate::{
+    context::Context,
+    models::{BaseModel, ModelError, Question, QuizTemplate, Role, User},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Permission {
+    /// Create, edit and delete one's own quiz templates and questions.
+    AuthorQuizzes,
+    /// List users and change their roles.
+    ManageUsers,
+    /// Start and answer attempts.
+    TakeQuizzes,
+    /// Read content owned by anyone.
+    ViewAllContent,
+}
+
+impl Role {
+    pub fn grants(&self, permission: Permission) -> bool {
+        match self {
+            Self::Admin => true,
+            Self::Author => permission == Permission::AuthorQuizzes,
+            Self::Learner => permission == Permission::TakeQuizzes,
+        }
+    }
+}
+
+pub fn has_permission(user: &User, permission: Permission) -> bool {
+    user.roles.iter().any(|role| role.grants(permission))
+}
+
+/// Why a request was refused. Converts into a GraphQL error whose `code` extension clients can
+/// branch on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuthorizationError {
+    Forbidden,
+    NotFound,
+    Unauthenticated,
+}
+
+impl AuthorizationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Forbidden => "FORBIDDEN",
+            Self::NotFound => "NOT_FOUND",
+            Self::Unauthenticated => "UNAUTHENTICATED",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            Self::Forbidden => Status::Forbidden,
+            Self::NotFound => Status::NotFound,
+            Self::Unauthenticated => Status::Unauthorized,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Forbidden => "You are not allowed to do this",
+            Self::NotFound => "Not found",
+            Self::Unauthenticated => "You must be signed in",
+        }
+    }
+}
+
+impl<S: ScalarValue> From<AuthorizationError> for FieldError<S> {
+    fn from(value: AuthorizationError) -> Self {
+        FieldError::new(value.message(), graphql_value!({ "code": (value.code()) }))
+    }
+}
+
+/// Why a read that needs authorization failed: it was refused or the database failed. Lets
+/// GraphQL resolvers and plain routes share a read while reporting errors their own way.
+#[derive(Debug)]
+pub enum ReadError {
+    Refused(AuthorizationError),
+    Model(ModelError),
+}
+
+impl From<AuthorizationError> for ReadError {
+    fn from(value: AuthorizationError) -> Self {
+        Self::Refused(value)
+    }
+}
+
+impl From<ModelError> for ReadError {
+    fn from(value: ModelError) -> Self {
+        Self::Model(value)
+    }
+}
+
+impl<S: ScalarValue> From<ReadError> for FieldError<S> {
+    fn from(value: ReadError) -> Self {
+        match value {
+            ReadError::Refused(e) => e.into(),
+            ReadError::Model(e) => e.into(),
+        }
+    }
+}
+
+pub fn require_user(context: &Context) -> Result<&User, AuthorizationError> {
+    context
+        .user
+        .as_ref()
+        .ok_or(AuthorizationError::Unauthenticated)
+}
+
+pub fn require_permission(
+    context: &Context,
+    permission: Permission,
+) -> Result<&User, AuthorizationError> {
+    let user = require_user(context)?;
+
+    if !has_permission(user, permission) {
+        return Err(AuthorizationError::Forbidden);
+    }
+
+    Ok(user)
+}
+
+/// Malformed ids cannot name anything, so they are not found rather than a database error.
+fn check_id(id: &str) -> Result<(), AuthorizationError> {
+    if ObjectId::from_str(id).is_err() {
+        return Err(AuthorizationError::NotFound);
+    }
+
+    Ok(())
+}
+
+/// Refuses edits to quiz templates owned by someone else.
+fn check_owner(user: &User, quiz_template: &QuizTemplate) -> Result<(), AuthorizationError> {
+    if quiz_template.user_id != user.id {
+        return Err(AuthorizationError::Forbidden);
+    }
+
+    Ok(())
+}
+
+/// Reads a quiz template that the signed in user owns and may edit.
+pub async fn require_quiz_template_owner(
+    context: &Context,
+    quiz_template_id: &str,
+) -> Result<QuizTemplate, ReadError> {
+    let user = require_permission(context, Permission::AuthorQuizzes)?;
+
+    check_id(quiz_template_id)?;
+
+    let quiz_template = context
+        .quiz_templates
+        .read_by_id(quiz_template_id)
+        .await?
+        .ok_or(AuthorizationError::NotFound)?;
+
+    check_owner(user, &quiz_template)?;
+
+    Ok(quiz_template)
+}
+
+/// Reads a quiz template and its questions in position order for export. Only your own
+/// templates can be exported unless you can view all content.
+pub(crate) async fn read_exportable(
+    context: &Context,
+    id: &str,
+) -> Result<(QuizTemplate, Vec<Question>), ReadError> {
+    let user = require_user(context)?;
+
+    check_id(id)?;
+
+    let quiz_template = context
+        .quiz_templates
+        .read_by_id(id)
+        .await?
+        .ok_or(AuthorizationError::NotFound)?;
+
+    if quiz_template.user_id != user.id && !has_permission(user, Permission::ViewAllContent) {
+        return Err(AuthorizationError::Forbidden)?;
+    }
+
+    let questions = context
+        .questions
+        .read_by_quiz_template_id(&quiz_template.id)
+        .await?;
+
+    Ok((quiz_template, questions))
+}
+
+/// Reads a question whose parent quiz template the signed in user owns and may edit.
+pub async fn require_question_owner(
+    context: &Context,
+    question_id: &str,
+) -> Result<Question, ReadError> {
+    require_permission(context, Permission::AuthorQuizzes)?;
+
+    check_id(question_id)?;
+
+    let question = context
+        .questions
+        .read_by_id(question_id)
+        .await?
+        .ok_or(AuthorizationError::NotFound)?;
+
+    require_quiz_template_owner(context, &question.quiz_template_id).await?;
+
+    Ok(question)
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{doc, from_document, oid::ObjectId};
+
+    use super::*;
+
+    fn user(roles: Vec<Role>) -> User {
+        User {
+            id: "66a000000000000000000000".to_string(),
+            roles,
+            username: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_has_permission() {
+        let admin = user(vec![Role::Admin]);
+
+        assert!(has_permission(&admin, Permission::ManageUsers));
+        assert!(has_permission(&admin, Permission::ViewAllContent));
+
+        let author = user(vec![Role::Author]);
+
+        assert!(has_permission(&author, Permission::AuthorQuizzes));
+        assert!(!has_permission(&author, Permission::TakeQuizzes));
+        assert!(!has_permission(&author, Permission::ManageUsers));
+
+        let learner = user(vec![Role::Learner]);
+
+        assert!(has_permission(&learner, Permission::TakeQuizzes));
+        assert!(!has_permission(&learner, Permission::AuthorQuizzes));
+
+        assert!(!has_permission(&user(vec![]), Permission::TakeQuizzes));
+    }
+
+    #[test]
+    fn test_grants() {
+        assert!(Role::Admin.grants(Permission::ManageUsers));
+        assert!(Role::Admin.grants(Permission::AuthorQuizzes));
+        assert!(Role::Author.grants(Permission::AuthorQuizzes));
+        assert!(!Role::Author.grants(Permission::ViewAllContent));
+        assert!(Role::Learner.grants(Permission::TakeQuizzes));
+        assert!(!Role::Learner.grants(Permission::ViewAllContent));
+
+        // Accounts stored before roles existed keep authoring.
+        let legacy_user = from_document::<User>(doc! {
+            "_id": ObjectId::new(),
+            "username": "test",
+        })
+        .unwrap();
+
+        assert!(has_permission(&legacy_user, Permission::AuthorQuizzes));
+        assert!(has_permission(&legacy_user, Permission::TakeQuizzes));
+        assert!(!has_permission(&legacy_user, Permission::ManageUsers));
+        assert!(!has_permission(&legacy_user, Permission::ViewAllContent));
+    }
+
+    #[test]
+    fn test_check_owner() {
+        let owner = user(vec![Role::Author]);
+        let quiz_template = QuizTemplate {
+            id: "66a000000000000000000001".to_string(),
+            name: "Capitals".to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: None,
+            scoring: Default::default(),
+            time_limit_seconds: None,
+            user_id: owner.id.clone(),
+        };
+
+        assert_eq!(Ok(()), check_owner(&owner, &quiz_template));
+
+        // Admins read everything but only edit their own templates.
+        let admin = User {
+            id: "66a000000000000000000002".to_string(),
+            ..user(vec![Role::Admin])
+        };
+
+        assert_eq!(
+            Err(AuthorizationError::Forbidden),
+            check_owner(&admin, &quiz_template)
+        );
+    }
+
+    #[test]
+    fn test_malformed_ids_are_not_found() {
+        assert_eq!(Err(AuthorizationError::NotFound), check_id("not-an-id"));
+        assert_eq!(Err(AuthorizationError::NotFound), check_id(""));
+        assert_eq!(Ok(()), check_id(&ObjectId::new().to_string()));
+    }
+}
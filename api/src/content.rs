@@ -0,0 +1,187 @@
+//! Rich text for question and answer fields.
+//!
+//! Fields are stored as their source and rendered to HTML when read. Markdown may contain
+//! `$inline$` and `$$display$$` math with balanced braces, which is left as TeX in
+//! `math-inline` and `math-display` elements for clients to typeset, for example with KaTeX.
+//! Raw HTML is refused when content is saved and every rendering is sanitised, so the HTML
+//! sent to clients never carries scripts, event handlers or `javascript:` links.
+
+use std::collections::{HashMap, HashSet};
+
+use ammonia::Builder;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, TagEnd};
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+
+/// Longest math expression accepted, in bytes.
+const MAX_MATH_LENGTH: usize = 4096;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFormat {
+    #[default]
+    Plain,
+    Markdown,
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_MATH | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES
+}
+
+fn check_math(tex: &str) -> Result<(), String> {
+    if tex.trim().is_empty() {
+        return Err("Math must not be empty".to_string());
+    }
+
+    if tex.len() > MAX_MATH_LENGTH {
+        return Err(format!("Math must be at most {} bytes", MAX_MATH_LENGTH));
+    }
+
+    Ok(())
+}
+
+impl ContentFormat {
+    /// Checks that content can be stored in this format.
+    pub fn validate(&self, source: &str) -> Result<(), String> {
+        if *self == Self::Plain {
+            return Ok(());
+        }
+
+        for event in Parser::new_ext(source, markdown_options()) {
+            match event {
+                Event::Html(_) | Event::InlineHtml(_) => {
+                    return Err("HTML is not allowed in Markdown content".to_string())
+                }
+                Event::InlineMath(tex) | Event::DisplayMath(tex) => check_math(&tex)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Safe HTML for content in this format.
+    pub fn render(&self, source: &str) -> String {
+        let unsafe_html = match self {
+            Self::Plain => escape(source).replace('\n', "<br>"),
+            Self::Markdown => {
+                let events = Parser::new_ext(source, markdown_options()).map(|event| match event {
+                    Event::InlineMath(tex) => Event::InlineHtml(CowStr::from(format!(
+                        "<span class=\"math-inline\">{}</span>",
+                        escape(&tex)
+                    ))),
+                    Event::DisplayMath(tex) => Event::InlineHtml(CowStr::from(format!(
+                        "<span class=\"math-display\">{}</span>",
+                        escape(&tex)
+                    ))),
+                    // Older content may predate validation. Show its HTML as text.
+                    Event::Html(text) | Event::InlineHtml(text) => Event::Text(text),
+                    event => event,
+                });
+
+                let mut rendered = String::new();
+                html::push_html(&mut rendered, events);
+
+                rendered
+            }
+        };
+
+        Builder::default()
+            .allowed_classes(HashMap::from([(
+                "span",
+                HashSet::from(["math-inline", "math-display"]),
+            )]))
+            .clean(&unsafe_html)
+            .to_string()
+    }
+
+    /// The words of content without its markup, for output that cannot show HTML. Math is
+    /// left as TeX.
+    pub fn plain_text(&self, source: &str) -> String {
+        let text = match self {
+            Self::Plain => source.to_string(),
+            Self::Markdown => {
+                let mut text = String::new();
+
+                for event in Parser::new_ext(source, markdown_options()) {
+                    match event {
+                        Event::Text(part)
+                        | Event::Code(part)
+                        | Event::InlineMath(part)
+                        | Event::DisplayMath(part)
+                        | Event::Html(part)
+                        | Event::InlineHtml(part) => text.push_str(&part),
+                        Event::SoftBreak
+                        | Event::HardBreak
+                        | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item) => {
+                            text.push(' ')
+                        }
+                        _ => {}
+                    }
+                }
+
+                text
+            }
+        };
+
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            "1 &lt; 2<br>&lt;b&gt;",
+            ContentFormat::Plain.render("1 < 2\n<b>")
+        );
+        assert_eq!(
+            "<p><strong>Bold</strong> and <span class=\"math-inline\">x^2 &lt; 1</span></p>\n",
+            ContentFormat::Markdown.render("**Bold** and $x^2 < 1$")
+        );
+        assert_eq!(
+            "<p><span class=\"math-display\">\\frac{1}{2}</span></p>\n",
+            ContentFormat::Markdown.render("$$\\frac{1}{2}$$")
+        );
+    }
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!("1 < 2 <b>", ContentFormat::Plain.plain_text("1 < 2\n<b>"));
+        assert_eq!(
+            "Title Bold and x^2 one two",
+            ContentFormat::Markdown.plain_text("# Title\n\n**Bold** and $x^2$\n\n- one\n- two")
+        );
+    }
+
+    #[test]
+    fn test_render_is_safe() {
+        let html = ContentFormat::Markdown
+            .render("[click](javascript:alert(1)) <script>alert(1)</script> <img src=x onerror=y>");
+
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(Ok(()), ContentFormat::Plain.validate("<script>"));
+        assert_eq!(
+            Ok(()),
+            ContentFormat::Markdown.validate("# Title\n\n$\\sqrt{2}$ and `<code>`")
+        );
+        assert_eq!(
+            Err("HTML is not allowed in Markdown content".to_string()),
+            ContentFormat::Markdown.validate("Hello <b>world</b>")
+        );
+        assert_eq!(
+            Err("Math must not be empty".to_string()),
+            ContentFormat::Markdown.validate("$$ $$")
+        );
+    }
+}
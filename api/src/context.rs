@@ -1,4 +1,5 @@
 use mongodb::Database;
+use redis::aio::MultiplexedConnection;
 use rocket::{
     http::Status,
     outcome::try_outcome,
@@ -7,14 +8,23 @@ use rocket::{
 };
 
 use crate::{
-    models::{BaseModel, Questions, QuizTemplates, User, Users},
+    game::Games,
+    loader::{FirstQuestionsByQuizTemplateId, Loader, QuizTemplatesById, UsersById},
+    models::{Attachments, Attempts, BaseModel, Questions, QuizTemplates, Reviews, User, Users},
     session::SessionStorage,
 };
 
 pub struct Context {
+    pub attachments: Attachments,
+    pub attempts: Attempts,
+    pub games: Games,
     pub questions: Questions,
+    pub first_questions_by_quiz_template_id: Loader<FirstQuestionsByQuizTemplateId>,
     pub quiz_templates: QuizTemplates,
+    pub quiz_templates_by_id: Loader<QuizTemplatesById>,
+    pub reviews: Reviews,
     pub users: Users,
+    pub users_by_id: Loader<UsersById>,
     pub user: Option<User>,
 }
 
@@ -27,7 +37,13 @@ impl<'r> FromRequest<'r> for Context {
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let db: &State<Database> = try_outcome!(request.guard::<&State<mongodb::Database>>().await);
 
-        let users = Users::new(&db);
+        let redis_client: &State<redis::Client> =
+            try_outcome!(request.guard::<&State<redis::Client>>().await);
+
+        let redis_connection: &State<MultiplexedConnection> =
+            try_outcome!(request.guard::<&State<MultiplexedConnection>>().await);
+
+        let users = Users::new(db);
 
         let mut session_storage: SessionStorage =
             try_outcome!(request.guard::<SessionStorage>().await);
@@ -54,10 +70,22 @@ impl<'r> FromRequest<'r> for Context {
         };
 
         Outcome::Success(Context {
-            questions: Questions::new(&db),
-            quiz_templates: QuizTemplates::new(&db),
+            attachments: Attachments::new(db),
+            attempts: Attempts::new(db),
+            games: Games::new(
+                redis_client.inner().clone(),
+                redis_connection.inner().clone(),
+            ),
+            questions: Questions::new(db),
+            first_questions_by_quiz_template_id: Loader::new(FirstQuestionsByQuizTemplateId(
+                Questions::new(db),
+            )),
+            quiz_templates: QuizTemplates::new(db),
+            quiz_templates_by_id: Loader::new(QuizTemplatesById(QuizTemplates::new(db))),
+            reviews: Reviews::new(db),
             user,
             users,
+            users_by_id: Loader::new(UsersById(Users::new(db))),
         })
     }
 }
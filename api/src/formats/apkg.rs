@@ -0,0 +1,519 @@
+//! Anki decks, for studying quiz templates offline.
+//!
+//! An `.apkg` is a zip holding `collection.anki2`, an SQLite database in Anki's schema 11, and a
+//! `media` file listing bundled media. Every quiz template becomes a deck and every question a
+//! Basic note with the question on the front and the answer on the back. Notes use the
+//! question id as their guid, and the note type and decks keep the same ids in every export, so
+//! importing a newer export updates notes instead of adding copies.
+
+use std::io::{Cursor, Write};
+
+use mongodb::bson::DateTime;
+use quick_xml::escape::escape;
+use rusqlite::{params, Connection, DatabaseName};
+use serde_json::{json, Value};
+use zip::{result::ZipError, write::SimpleFileOptions, ZipWriter};
+
+use crate::models::{Question, QuestionKind, QuizTemplate};
+
+const COLLECTION: &str = "collection.anki2";
+
+const DEFAULT_DECK_ID: i64 = 1;
+
+/// Most quiz templates one package may hold.
+pub const MAX_DECKS: usize = 50;
+
+/// Hashed into the id of the note type. Anki skips notes whose guid exists with another note
+/// type, so the id must not change between exports.
+const MODEL_NAME: &str = "quiz Basic";
+
+/// Separates the fields of a note.
+const FIELD_SEPARATOR: &str = "\x1f";
+
+const SCHEMA: &str = "
+CREATE TABLE col (
+    id integer primary key, crt integer not null, mod integer not null, scm integer not null,
+    ver integer not null, dty integer not null, usn integer not null, ls integer not null,
+    conf text not null, models text not null, decks text not null, dconf text not null,
+    tags text not null
+);
+CREATE TABLE notes (
+    id integer primary key, guid text not null, mid integer not null, mod integer not null,
+    usn integer not null, tags text not null, flds text not null, sfld integer not null,
+    csum integer not null, flags integer not null, data text not null
+);
+CREATE TABLE cards (
+    id integer primary key, nid integer not null, did integer not null, ord integer not null,
+    mod integer not null, usn integer not null, type integer not null, queue integer not null,
+    due integer not null, ivl integer not null, factor integer not null, reps integer not null,
+    lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null
+);
+CREATE TABLE revlog (
+    id integer primary key, cid integer not null, usn integer not null, ease integer not null,
+    ivl integer not null, lastIvl integer not null, factor integer not null,
+    time integer not null, type integer not null
+);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+CREATE INDEX ix_notes_usn on notes (usn);
+CREATE INDEX ix_cards_usn on cards (usn);
+CREATE INDEX ix_revlog_usn on revlog (usn);
+CREATE INDEX ix_cards_nid on cards (nid);
+CREATE INDEX ix_cards_sched on cards (did, queue, due);
+CREATE INDEX ix_revlog_cid on revlog (cid);
+CREATE INDEX ix_notes_csum on notes (csum);
+";
+
+#[derive(Debug)]
+pub enum ApkgError {
+    Sqlite(rusqlite::Error),
+    Zip(ZipError),
+}
+
+impl From<rusqlite::Error> for ApkgError {
+    fn from(value: rusqlite::Error) -> Self {
+        Self::Sqlite(value)
+    }
+}
+
+impl From<ZipError> for ApkgError {
+    fn from(value: ZipError) -> Self {
+        Self::Zip(value)
+    }
+}
+
+impl From<std::io::Error> for ApkgError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Zip(value.into())
+    }
+}
+
+impl std::fmt::Display for ApkgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sqlite(e) => write!(f, "Could not write Anki collection: {}", e),
+            Self::Zip(e) => write!(f, "Could not write Anki package: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ApkgError {}
+
+fn deck(id: i64, name: &str, modified: i64) -> Value {
+    json!({
+        "browserCollapsed": false,
+        "collapsed": false,
+        "conf": 1,
+        "desc": "",
+        "dyn": 0,
+        "extendNew": 10,
+        "extendRev": 50,
+        "id": id,
+        "lrnToday": [0, 0],
+        "mod": modified,
+        "name": name,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "timeToday": [0, 0],
+        "usn": -1,
+    })
+}
+
+fn field(name: &str, ord: usize) -> Value {
+    json!({
+        "font": "Arial",
+        "media": [],
+        "name": name,
+        "ord": ord,
+        "rtl": false,
+        "size": 20,
+        "sticky": false,
+    })
+}
+
+/// Anki's stock Basic note type: a front and a back field and one card showing them in turn.
+fn basic_model(id: i64, modified: i64) -> Value {
+    json!({
+        "css": ".card { font-family: arial; font-size: 20px; text-align: center; }",
+        "did": DEFAULT_DECK_ID,
+        "flds": [field("Front", 0), field("Back", 1)],
+        "id": id,
+        "latexPost": "\\end{document}",
+        "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\
+                     \\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\
+                     \\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+        "mod": modified,
+        "name": "Basic",
+        "req": [[0, "any", [0]]],
+        "sortf": 0,
+        "tags": [],
+        "tmpls": [{
+            "afmt": "{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}",
+            "bafmt": "",
+            "bqfmt": "",
+            "did": null,
+            "name": "Card 1",
+            "ord": 0,
+            "qfmt": "{{Front}}",
+        }],
+        "type": 0,
+        "usn": -1,
+        "vers": [],
+    })
+}
+
+fn deck_config() -> Value {
+    json!({
+        "1": {
+            "autoplay": true,
+            "dyn": false,
+            "id": 1,
+            "lapse": {
+                "delays": [10],
+                "leechAction": 0,
+                "leechFails": 8,
+                "minInt": 1,
+                "mult": 0,
+            },
+            "maxTaken": 60,
+            "mod": 0,
+            "name": "Default",
+            "new": {
+                "bury": true,
+                "delays": [1, 10],
+                "initialFactor": 2500,
+                "ints": [1, 4, 7],
+                "order": 1,
+                "perDay": 20,
+                "separate": true,
+            },
+            "replayq": true,
+            "rev": {
+                "bury": true,
+                "ease4": 1.3,
+                "fuzz": 0.05,
+                "ivlFct": 1,
+                "maxIvl": 36500,
+                "minSpace": 1,
+                "perDay": 100,
+            },
+            "timer": 0,
+            "usn": 0,
+        }
+    })
+}
+
+fn list(tag: &str, items: &[String]) -> String {
+    let items = items
+        .iter()
+        .map(|item| format!("<li>{}</li>", escape(item)))
+        .collect::<String>();
+
+    format!("<{0}>{1}</{0}>", tag, items)
+}
+
+/// The front of a question's card. Options are listed so that choice questions can be answered;
+/// ordering items are listed alphabetically so that the front does not give the order away.
+fn front(question: &Question) -> String {
+    let text = question.question_format.render(&question.question);
+
+    match &question.kind {
+        QuestionKind::MultipleChoice { options, .. }
+        | QuestionKind::MultipleSelect { options, .. } => {
+            format!("{}<br>{}", text, list("ol", options))
+        }
+        QuestionKind::Ordering { items } => {
+            let mut items = items.clone();
+            items.sort();
+
+            format!("{}<br>{}", text, list("ul", &items))
+        }
+        _ => text,
+    }
+}
+
+/// Anki's duplicate check value: the first 8 hex digits of the SHA-1 of the sort field.
+fn checksum(text: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(text).digest().bytes();
+
+    i64::from(u32::from_be_bytes([
+        digest[0], digest[1], digest[2], digest[3],
+    ]))
+}
+
+/// An id that is the same in every export, from the first 6 bytes of the SHA-1 of `text`. That
+/// keeps it in the range of the millisecond timestamps Anki uses for ids itself.
+fn stable_id(text: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(text).digest().bytes();
+
+    i64::from_be_bytes([
+        0, 0, digest[0], digest[1], digest[2], digest[3], digest[4], digest[5],
+    ])
+}
+
+/// Writes the collection database of the quiz templates.
+fn collection(
+    quiz_templates: &[(QuizTemplate, Vec<Question>)],
+    now: DateTime,
+) -> Result<Vec<u8>, rusqlite::Error> {
+    let millis = now.timestamp_millis();
+    let seconds = millis / 1000;
+
+    let model_id = stable_id(MODEL_NAME);
+    let deck_id = |index: usize| stable_id(&quiz_templates[index].0.id);
+
+    // Anki ids are creation times in milliseconds, so consecutive ones from now are unique.
+    let mut next_id = millis;
+
+    let mut decks = serde_json::Map::new();
+    decks.insert(
+        DEFAULT_DECK_ID.to_string(),
+        deck(DEFAULT_DECK_ID, "Default", seconds),
+    );
+
+    for (index, (quiz_template, _)) in quiz_templates.iter().enumerate() {
+        decks.insert(
+            deck_id(index).to_string(),
+            deck(deck_id(index), &quiz_template.name, seconds),
+        );
+    }
+
+    let conf = json!({
+        "activeDecks": [DEFAULT_DECK_ID],
+        "addToCur": true,
+        "collapseTime": 1200,
+        "curDeck": DEFAULT_DECK_ID,
+        "curModel": model_id.to_string(),
+        "dueCounts": true,
+        "estTimes": true,
+        "newSpread": 0,
+        "nextPos": 1,
+        "sortBackwards": false,
+        "sortType": "noteFld",
+        "timeLim": 0,
+    });
+
+    let connection = Connection::open_in_memory()?;
+    connection.execute_batch(SCHEMA)?;
+
+    connection.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![
+            seconds,
+            millis,
+            conf.to_string(),
+            json!({ model_id.to_string(): basic_model(model_id, seconds) }).to_string(),
+            Value::Object(decks).to_string(),
+            deck_config().to_string(),
+        ],
+    )?;
+
+    let mut position = 0;
+
+    for (index, (_, questions)) in quiz_templates.iter().enumerate() {
+        for question in questions {
+            let note_id = next_id;
+            next_id += 1;
+            position += 1;
+
+            let fields = [
+                front(question),
+                question.answer_format.render(&question.answer),
+            ];
+
+            // Anki sorts and checks for duplicates on the text of the first field.
+            let sort_field = question.question_format.plain_text(&question.question);
+
+            connection.execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, '', ?5, ?6, ?7, 0, '')",
+                params![
+                    note_id,
+                    question.id,
+                    model_id,
+                    seconds,
+                    fields.join(FIELD_SEPARATOR),
+                    sort_field,
+                    checksum(&sort_field),
+                ],
+            )?;
+
+            connection.execute(
+                "INSERT INTO cards VALUES (?1, ?2, ?3, 0, ?4, -1, 0, 0, ?5, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+                params![note_id, note_id, deck_id(index), seconds, position],
+            )?;
+        }
+    }
+
+    Ok(connection.serialize(DatabaseName::Main)?.to_vec())
+}
+
+/// An Anki package with one deck per quiz template.
+pub fn export(
+    quiz_templates: &[(QuizTemplate, Vec<Question>)],
+    now: DateTime,
+) -> Result<Vec<u8>, ApkgError> {
+    let collection = collection(quiz_templates, now)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let options = SimpleFileOptions::default();
+
+    zip.start_file(COLLECTION, options)?;
+    zip.write_all(&collection)?;
+
+    zip.start_file("media", options)?;
+    zip.write_all(b"{}")?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Scoring;
+    use std::io::Read;
+
+    use mongodb::bson::oid::ObjectId;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn quiz_template(
+        name: &str,
+        questions: &[(&str, &str, QuestionKind)],
+    ) -> (QuizTemplate, Vec<Question>) {
+        let quiz_template = QuizTemplate {
+            id: ObjectId::new().to_string(),
+            name: name.to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: None,
+            scoring: Scoring::default(),
+            time_limit_seconds: None,
+            user_id: ObjectId::new().to_string(),
+        };
+
+        let questions = questions
+            .iter()
+            .map(|(question, answer, kind)| {
+                Question::fixture(&quiz_template.id, question, answer, kind.clone())
+            })
+            .collect();
+
+        (quiz_template, questions)
+    }
+
+    #[test]
+    fn test_export() {
+        let quiz_templates = [
+            quiz_template(
+                "Capitals",
+                &[
+                    ("Capital of France?", "Paris", QuestionKind::default()),
+                    (
+                        "Capital of Germany?",
+                        "Berlin",
+                        QuestionKind::MultipleChoice {
+                            correct_option: 1,
+                            options: vec!["Bonn".to_string(), "Berlin".to_string()],
+                        },
+                    ),
+                ],
+            ),
+            quiz_template(
+                "Rivers",
+                &[("Longest river?", "Nile", QuestionKind::default())],
+            ),
+        ];
+
+        let package = export(&quiz_templates, DateTime::from_millis(1_700_000_000_000)).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(package)).unwrap();
+        let mut collection = vec![];
+        archive
+            .by_name(COLLECTION)
+            .unwrap()
+            .read_to_end(&mut collection)
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("{}.anki2", ObjectId::new()));
+        std::fs::write(&path, collection).unwrap();
+        let connection = Connection::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let decks: Value = serde_json::from_str(
+            &connection
+                .query_row("SELECT decks FROM col", [], |row| row.get::<_, String>(0))
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut names = decks
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|deck| deck["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        assert_eq!(vec!["Capitals", "Default", "Rivers"], names);
+
+        let notes = connection
+            .prepare("SELECT guid, flds FROM notes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    quiz_templates[0].1[0].id.clone(),
+                    "Capital of France?\x1fParis".to_string()
+                ),
+                (
+                    quiz_templates[0].1[1].id.clone(),
+                    "Capital of Germany?<br><ol><li>Bonn</li><li>Berlin</li></ol>\x1fBerlin"
+                        .to_string()
+                ),
+                (
+                    quiz_templates[1].1[0].id.clone(),
+                    "Longest river?\x1fNile".to_string()
+                ),
+            ],
+            notes
+        );
+
+        let card_decks = connection
+            .prepare("SELECT did FROM cards ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get::<_, i64>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(card_decks[0], card_decks[1]);
+        assert_ne!(card_decks[0], card_decks[2]);
+        assert!(decks.get(card_decks[2].to_string()).is_some());
+
+        // Ids that do not depend on the time of the export, so that reimports update notes.
+        assert_eq!(stable_id(&quiz_templates[1].0.id), card_decks[2]);
+
+        let model_ids = connection
+            .prepare("SELECT DISTINCT mid FROM notes")
+            .unwrap()
+            .query_map([], |row| row.get::<_, i64>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(vec![stable_id(MODEL_NAME)], model_ids);
+    }
+
+    #[test]
+    fn test_checksum() {
+        // The SHA-1 of "abc" is a9993e36...
+        assert_eq!(checksum("abc"), 0xa9993e36);
+    }
+}
@@ -0,0 +1,326 @@
+//! Portable JSON bundles of quiz templates and their questions.
+//!
+//! Bundles carry no ids or owners, so importing one always creates new templates for the
+//! caller. `version` is bumped whenever the shape changes in a way older readers cannot load.
+
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::content::ContentFormat;
+use crate::models::{
+    default_points, validate_points, Hint, PartialCredit, Question, QuestionKind, QuizTemplate,
+    Scoring,
+};
+
+pub const BUNDLE_VERSION: u32 = 1;
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn is_default_points(points: &f64) -> bool {
+    *points == default_points()
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BundleQuestion {
+    pub answer: String,
+    #[serde(default)]
+    pub answer_format: ContentFormat,
+    // Left out when empty so that bundles without them stay readable by older versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<Hint>,
+    #[serde(default)]
+    pub kind: QuestionKind,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub partial_credit: PartialCredit,
+    #[serde(default = "default_points", skip_serializing_if = "is_default_points")]
+    pub points: f64,
+    pub question: String,
+    #[serde(default)]
+    pub question_format: ContentFormat,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BundleQuizTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub question_time_limit_seconds: Option<u32>,
+    pub questions: Vec<BundleQuestion>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub scoring: Scoring,
+    #[serde(default)]
+    pub time_limit_seconds: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bundle {
+    pub quiz_templates: Vec<BundleQuizTemplate>,
+    pub version: u32,
+}
+
+impl Bundle {
+    pub fn new(quiz_templates: &[(QuizTemplate, Vec<Question>)]) -> Self {
+        Self {
+            quiz_templates: quiz_templates
+                .iter()
+                .map(|(quiz_template, questions)| BundleQuizTemplate {
+                    name: quiz_template.name.clone(),
+                    question_time_limit_seconds: quiz_template.question_time_limit_seconds,
+                    questions: questions
+                        .iter()
+                        .map(|question| BundleQuestion {
+                            answer: question.answer.clone(),
+                            answer_format: question.answer_format,
+                            explanation: question.explanation.clone(),
+                            hints: question.hints.clone(),
+                            kind: question.kind.clone(),
+                            partial_credit: question.partial_credit,
+                            points: question.points,
+                            question: question.question.clone(),
+                            question_format: question.question_format,
+                        })
+                        .collect(),
+                    scoring: quiz_template.scoring.clone(),
+                    time_limit_seconds: quiz_template.time_limit_seconds,
+                })
+                .collect(),
+            version: BUNDLE_VERSION,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Reads a bundle and checks every template and question in it, so that nothing is created
+    /// from a bundle with any problem.
+    pub fn from_json(json: &str) -> Result<Self, Vec<String>> {
+        let bundle = serde_json::from_str::<Self>(json).map_err(|e| vec![e.to_string()])?;
+
+        let errors = bundle.validate();
+
+        match errors.is_empty() {
+            true => Ok(bundle),
+            false => Err(errors),
+        }
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+
+        if self.version != BUNDLE_VERSION {
+            errors.push(format!(
+                "Unsupported bundle version {}, expected {}",
+                self.version, BUNDLE_VERSION
+            ));
+        }
+
+        if self.quiz_templates.is_empty() {
+            errors.push("Bundle has no quiz templates".to_string());
+        }
+
+        for (index, quiz_template) in self.quiz_templates.iter().enumerate() {
+            let location = format!("Quiz template {}", index + 1);
+
+            if quiz_template.name.trim().is_empty() {
+                errors.push(format!("{}: name must not be empty", location));
+            }
+
+            if quiz_template.question_time_limit_seconds == Some(0)
+                || quiz_template.time_limit_seconds == Some(0)
+            {
+                errors.push(format!("{}: time limits must be positive", location));
+            }
+
+            if let Err(e) = quiz_template.scoring.validate() {
+                errors.push(format!("{}: {}", location, e));
+            }
+
+            for (index, question) in quiz_template.questions.iter().enumerate() {
+                let location = format!("{}, question {}", location, index + 1);
+
+                if let Err(e) = question.kind.validate() {
+                    errors.push(format!("{}: {}", location, e));
+                }
+
+                if let Err(e) = validate_points(question.points) {
+                    errors.push(format!("{}: {}", location, e));
+                }
+
+                if let Err(e) = Hint::validate_all(&question.hints) {
+                    errors.push(format!("{}: {}", location, e));
+                }
+
+                let explanation = question.explanation.as_deref().unwrap_or_default();
+
+                for (format, content) in [
+                    (question.question_format, question.question.as_str()),
+                    (question.answer_format, question.answer.as_str()),
+                    (question.answer_format, explanation),
+                ] {
+                    if let Err(e) = format.validate(content) {
+                        errors.push(format!("{}: {}", location, e));
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// The templates and questions of the bundle with fresh ids, owned by `user_id`.
+    pub fn to_models(&self, user_id: &str) -> Vec<(QuizTemplate, Vec<Question>)> {
+        self.quiz_templates
+            .iter()
+            .map(|quiz_template| {
+                let id = ObjectId::new().to_string();
+
+                let questions = quiz_template
+                    .questions
+                    .iter()
+                    .enumerate()
+                    .map(|(position, question)| Question {
+                        id: ObjectId::new().to_string(),
+                        answer: question.answer.clone(),
+                        answer_format: question.answer_format,
+                        attachments: vec![],
+                        explanation: question.explanation.clone(),
+                        hints: question.hints.clone(),
+                        kind: question.kind.clone(),
+                        partial_credit: question.partial_credit,
+                        points: question.points,
+                        position: position as u32,
+                        question: question.question.clone(),
+                        question_format: question.question_format,
+                        quiz_template_id: id.clone(),
+                    })
+                    .collect();
+
+                let quiz_template = QuizTemplate {
+                    id,
+                    name: quiz_template.name.clone(),
+                    order_version: 0,
+                    question_count: quiz_template.questions.len() as u32,
+                    question_time_limit_seconds: quiz_template.question_time_limit_seconds,
+                    scoring: quiz_template.scoring.clone(),
+                    time_limit_seconds: quiz_template.time_limit_seconds,
+                    user_id: user_id.to_string(),
+                };
+
+                (quiz_template, questions)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiz_template() -> (QuizTemplate, Vec<Question>) {
+        let quiz_template = QuizTemplate {
+            id: ObjectId::new().to_string(),
+            name: "Capitals".to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: None,
+            scoring: Scoring::default(),
+            time_limit_seconds: Some(600),
+            user_id: ObjectId::new().to_string(),
+        };
+
+        let questions = vec![
+            Question::fixture(
+                &quiz_template.id,
+                "Capital of France?",
+                "Paris",
+                QuestionKind::default(),
+            ),
+            Question::fixture(
+                &quiz_template.id,
+                "Capital of Germany?",
+                "Berlin",
+                QuestionKind::MultipleChoice {
+                    correct_option: 1,
+                    options: vec!["Bonn".to_string(), "Berlin".to_string()],
+                },
+            ),
+        ];
+
+        (quiz_template, questions)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (quiz_template, questions) = quiz_template();
+
+        let json = Bundle::new(&[(quiz_template.clone(), questions.clone())])
+            .to_json()
+            .unwrap();
+
+        let user_id = ObjectId::new().to_string();
+
+        let models = Bundle::from_json(&json).unwrap().to_models(&user_id);
+
+        let (imported, imported_questions) = &models[0];
+
+        assert_ne!(quiz_template.id, imported.id);
+        assert_eq!(user_id, imported.user_id);
+        assert_eq!(quiz_template.name, imported.name);
+        assert_eq!(Some(600), imported.time_limit_seconds);
+        assert_eq!(
+            questions
+                .iter()
+                .map(|question| (&question.question, &question.kind))
+                .collect::<Vec<_>>(),
+            imported_questions
+                .iter()
+                .map(|question| (&question.question, &question.kind))
+                .collect::<Vec<_>>()
+        );
+        assert!(imported_questions
+            .iter()
+            .all(|question| question.quiz_template_id == imported.id));
+    }
+
+    #[test]
+    fn test_from_json_reports_every_problem() {
+        let json = r#"{
+            "version": 1,
+            "quiz_templates": [
+                {
+                    "name": " ",
+                    "questions": [
+                        { "answer": "a", "question": "ok" },
+                        {
+                            "answer": "b",
+                            "question": "bad",
+                            "kind": { "type": "multiple_choice", "correct_option": 3, "options": ["a", "b"] }
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        assert_eq!(
+            Err(vec![
+                "Quiz template 1: name must not be empty".to_string(),
+                "Quiz template 1, question 2: Correct option is out of range".to_string(),
+            ]),
+            Bundle::from_json(json)
+        );
+
+        assert!(
+            Bundle::from_json(r#"{ "version": 2, "quiz_templates": [] }"#)
+                .unwrap_err()
+                .contains(&"Unsupported bundle version 2, expected 1".to_string())
+        );
+        assert!(Bundle::from_json("not json").is_err());
+    }
+}
@@ -0,0 +1,642 @@
+//! Questions of one quiz template as CSV, for editing in a spreadsheet.
+//!
+//! Every row is one question. `type` is the question kind in snake case, as in the database.
+//! List cells such as `options` hold one entry per line. `correct` holds the 1-based correct
+//! option of a multiple choice question, a comma separated list of them for multiple select,
+//! `true` or `false`, or the value of a numeric question. Rows with an empty `id` are new
+//! questions.
+
+use std::collections::{HashMap, HashSet};
+
+use ::csv::{ReaderBuilder, StringRecord, Writer};
+use mongodb::bson::oid::ObjectId;
+
+use super::ImportError;
+use crate::content::ContentFormat;
+use crate::matching::MatchMode;
+use crate::models::{PartialCredit, Question, QuestionKind};
+
+const HEADERS: [&str; 9] = [
+    "id",
+    "type",
+    "question",
+    "answer",
+    "options",
+    "correct",
+    "tolerance",
+    "matching",
+    "matching_value",
+];
+
+/// Columns every file must have. The rest default to empty.
+const REQUIRED_HEADERS: [&str; 3] = ["id", "question", "answer"];
+
+/// How applying a CSV file changes the questions of a quiz template.
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub created: Vec<Question>,
+    pub deleted: Vec<Question>,
+    pub unchanged: usize,
+    pub updated: Vec<Question>,
+}
+
+fn join_lines(values: &[String]) -> String {
+    values.join("\n")
+}
+
+fn split_lines(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn to_row(question: &Question) -> [String; 9] {
+    let mut row: [String; 9] = Default::default();
+
+    row[0] = question.id.clone();
+    row[2] = question.question.clone();
+    row[3] = question.answer.clone();
+
+    let type_name = match &question.kind {
+        QuestionKind::FreeText { matching } => {
+            let (mode, value) = match matching {
+                MatchMode::Exact => ("exact", String::new()),
+                MatchMode::CaseInsensitive => ("case_insensitive", String::new()),
+                MatchMode::Normalized => ("normalized", String::new()),
+                MatchMode::Alternatives { accepted } => ("alternatives", join_lines(accepted)),
+                MatchMode::Regex { pattern } => ("regex", pattern.clone()),
+                MatchMode::Fuzzy { max_distance } => ("fuzzy", max_distance.to_string()),
+            };
+
+            row[7] = mode.to_string();
+            row[8] = value;
+
+            "free_text"
+        }
+        QuestionKind::MultipleChoice {
+            correct_option,
+            options,
+        } => {
+            row[4] = join_lines(options);
+            row[5] = (correct_option + 1).to_string();
+
+            "multiple_choice"
+        }
+        QuestionKind::MultipleSelect {
+            correct_options,
+            options,
+        } => {
+            row[4] = join_lines(options);
+            row[5] = correct_options
+                .iter()
+                .map(|option| (option + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+
+            "multiple_select"
+        }
+        QuestionKind::TrueFalse { correct } => {
+            row[5] = correct.to_string();
+
+            "true_false"
+        }
+        QuestionKind::Numeric { tolerance, value } => {
+            row[5] = value.to_string();
+            row[6] = tolerance.to_string();
+
+            "numeric"
+        }
+        QuestionKind::Ordering { items } => {
+            row[4] = join_lines(items);
+
+            "ordering"
+        }
+    };
+
+    row[1] = type_name.to_string();
+
+    row
+}
+
+/// Writes the questions with a header row.
+pub fn export(questions: &[Question]) -> Result<String, ::csv::Error> {
+    let mut writer = Writer::from_writer(vec![]);
+
+    writer.write_record(HEADERS)?;
+
+    for question in questions {
+        writer.write_record(to_row(question))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| ::csv::Error::from(e.into_error()))?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// One question read from a row. `id` is empty for new questions.
+#[derive(Debug)]
+pub struct Row {
+    pub answer: String,
+    pub id: Option<String>,
+    pub kind: QuestionKind,
+    pub line: usize,
+    pub question: String,
+}
+
+fn parse_index(value: &str) -> Result<usize, String> {
+    value
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index > 0)
+        .map(|index| index - 1)
+        .ok_or_else(|| format!("Invalid option number: {}", value.trim()))
+}
+
+fn parse_number(value: &str, column: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid {}: {}", column, value.trim()))
+}
+
+fn parse_kind(cell: impl Fn(&str) -> String) -> Result<QuestionKind, String> {
+    let correct = cell("correct");
+
+    let kind = match cell("type").trim() {
+        "" | "free_text" => {
+            let value = cell("matching_value");
+
+            let matching = match cell("matching").trim() {
+                "" | "exact" => MatchMode::Exact,
+                "case_insensitive" => MatchMode::CaseInsensitive,
+                "normalized" => MatchMode::Normalized,
+                "alternatives" => MatchMode::Alternatives {
+                    accepted: split_lines(&value),
+                },
+                "regex" => MatchMode::Regex {
+                    pattern: value.trim().to_string(),
+                },
+                "fuzzy" => MatchMode::Fuzzy {
+                    max_distance: value
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid maximum distance: {}", value.trim()))?,
+                },
+                other => return Err(format!("Unknown matching mode: {}", other)),
+            };
+
+            QuestionKind::FreeText { matching }
+        }
+        "multiple_choice" => QuestionKind::MultipleChoice {
+            correct_option: parse_index(&correct)?,
+            options: split_lines(&cell("options")),
+        },
+        "multiple_select" => QuestionKind::MultipleSelect {
+            correct_options: correct
+                .split(',')
+                .filter(|value| !value.trim().is_empty())
+                .map(parse_index)
+                .collect::<Result<_, _>>()?,
+            options: split_lines(&cell("options")),
+        },
+        "true_false" => QuestionKind::TrueFalse {
+            correct: match correct.trim().to_lowercase().as_str() {
+                "true" => true,
+                "false" => false,
+                other => return Err(format!("Correct must be true or false, not {}", other)),
+            },
+        },
+        "numeric" => {
+            let tolerance = cell("tolerance");
+
+            QuestionKind::Numeric {
+                tolerance: match tolerance.trim().is_empty() {
+                    true => 0.0,
+                    false => parse_number(&tolerance, "tolerance")?,
+                },
+                value: parse_number(&correct, "value")?,
+            }
+        }
+        "ordering" => QuestionKind::Ordering {
+            items: split_lines(&cell("options")),
+        },
+        other => return Err(format!("Unknown question type: {}", other)),
+    };
+
+    kind.validate().map_err(|e| e.to_string())?;
+
+    Ok(kind)
+}
+
+fn parse_row(headers: &HashMap<String, usize>, record: &StringRecord) -> Result<Row, String> {
+    let cell = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|index| record.get(*index))
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let id = cell("id").trim().to_string();
+
+    if !id.is_empty() && ObjectId::parse_str(&id).is_err() {
+        return Err(format!("Invalid id: {}", id));
+    }
+
+    let question = cell("question").trim().to_string();
+
+    if question.is_empty() {
+        return Err("Question must not be empty".to_string());
+    }
+
+    Ok(Row {
+        answer: cell("answer").trim().to_string(),
+        id: (!id.is_empty()).then_some(id),
+        kind: parse_kind(cell)?,
+        line: record
+            .position()
+            .map_or(0, |position| position.line() as usize),
+        question,
+    })
+}
+
+/// Reads every row. Rows that cannot be read are reported against their line and left out.
+pub fn parse(contents: &str) -> Result<(Vec<Row>, Vec<ImportError>), ImportError> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| ImportError::new(1, e.to_string()))?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.trim().to_lowercase(), index))
+        .collect::<HashMap<_, _>>();
+
+    if let Some(missing) = REQUIRED_HEADERS
+        .iter()
+        .find(|header| !headers.contains_key(**header))
+    {
+        return Err(ImportError::new(1, format!("Missing column: {}", missing)));
+    }
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line() as usize);
+
+                errors.push(ImportError::new(line, e.to_string()));
+
+                continue;
+            }
+        };
+
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+
+        match parse_row(&headers, &record) {
+            Ok(row) => rows.push(row),
+            Err(message) => {
+                let line = record
+                    .position()
+                    .map_or(0, |position| position.line() as usize);
+
+                errors.push(ImportError::new(line, message));
+            }
+        }
+    }
+
+    Ok((rows, errors))
+}
+
+/// Checks the text of a row against the formats its question is stored in.
+fn validate_content(question: &Question) -> Result<(), String> {
+    question
+        .question_format
+        .validate(&question.question)
+        .map_err(|e| format!("Question: {}", e))?;
+
+    question
+        .answer_format
+        .validate(&question.answer)
+        .map_err(|e| format!("Answer: {}", e))
+}
+
+/// Matches rows to the existing questions of `quiz_template_id` by id. Every row is checked
+/// here, so that applying the diff cannot fail half way for anything but the database.
+pub fn diff(
+    quiz_template_id: &str,
+    existing: &[Question],
+    rows: Vec<Row>,
+    delete_missing: bool,
+) -> Result<Diff, Vec<ImportError>> {
+    let existing_by_id = existing
+        .iter()
+        .map(|question| (question.id.as_str(), question))
+        .collect::<HashMap<_, _>>();
+
+    let mut diff = Diff::default();
+    let mut errors = vec![];
+    let mut seen = HashSet::new();
+
+    for row in rows {
+        let question = Question {
+            id: row
+                .id
+                .clone()
+                .unwrap_or_else(|| ObjectId::new().to_string()),
+            answer: row.answer,
+            answer_format: ContentFormat::Plain,
+            attachments: vec![],
+            explanation: None,
+            hints: vec![],
+            kind: row.kind,
+            partial_credit: PartialCredit::default(),
+            points: 1.0,
+            position: 0,
+            question: row.question,
+            question_format: ContentFormat::Plain,
+            quiz_template_id: quiz_template_id.to_string(),
+        };
+
+        let Some(id) = row.id else {
+            match validate_content(&question) {
+                Ok(()) => diff.created.push(question),
+                Err(e) => errors.push(ImportError::new(row.line, e)),
+            }
+
+            continue;
+        };
+
+        if !seen.insert(id.clone()) {
+            errors.push(ImportError::new(row.line, format!("Duplicate id: {}", id)));
+
+            continue;
+        }
+
+        match existing_by_id.get(id.as_str()) {
+            None => errors.push(ImportError::new(
+                row.line,
+                format!("Question {} is not in this quiz template", id),
+            )),
+            Some(current)
+                if current.answer == question.answer
+                    && current.kind == question.kind
+                    && current.question == question.question =>
+            {
+                diff.unchanged += 1;
+            }
+            // The file has no format, hint, explanation or scoring columns, so updates keep the
+            // current ones.
+            Some(current) => {
+                let question = Question {
+                    answer_format: current.answer_format,
+                    explanation: current.explanation.clone(),
+                    hints: current.hints.clone(),
+                    partial_credit: current.partial_credit,
+                    points: current.points,
+                    position: current.position,
+                    question_format: current.question_format,
+                    ..question
+                };
+
+                match validate_content(&question) {
+                    Ok(()) => diff.updated.push(question),
+                    Err(e) => errors.push(ImportError::new(row.line, e)),
+                }
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // New questions go after the existing ones, in file order.
+    let next_position = existing
+        .iter()
+        .map(|question| question.position + 1)
+        .max()
+        .unwrap_or(0);
+
+    for (question, position) in diff.created.iter_mut().zip(next_position..) {
+        question.position = position;
+    }
+
+    if delete_missing {
+        diff.deleted = existing
+            .iter()
+            .filter(|question| !seen.contains(&question.id))
+            .cloned()
+            .collect();
+    }
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn questions(quiz_template_id: &str) -> Vec<Question> {
+        let question = |question: &str, answer: &str, kind: QuestionKind| {
+            Question::fixture(quiz_template_id, question, answer, kind)
+        };
+
+        vec![
+            question(
+                "Capital of France?",
+                "Paris",
+                QuestionKind::FreeText {
+                    matching: MatchMode::Alternatives {
+                        accepted: vec!["paris city".to_string()],
+                    },
+                },
+            ),
+            question(
+                "Pick the oceans",
+                "Atlantic, Pacific",
+                QuestionKind::MultipleSelect {
+                    correct_options: vec![0, 2],
+                    options: vec![
+                        "Atlantic".to_string(),
+                        "Alps".to_string(),
+                        "Pacific, the big one".to_string(),
+                    ],
+                },
+            ),
+            question(
+                "Pi?",
+                "3",
+                QuestionKind::Numeric {
+                    tolerance: 0.5,
+                    value: 3.0,
+                },
+            ),
+            question(
+                "Is water wet?",
+                "True",
+                QuestionKind::TrueFalse { correct: true },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let quiz_template_id = ObjectId::new().to_string();
+        let existing = questions(&quiz_template_id);
+
+        let csv = export(&existing).unwrap();
+
+        let (rows, errors) = parse(&csv).unwrap();
+
+        assert!(errors.is_empty());
+
+        let diff = diff(&quiz_template_id, &existing, rows, true).unwrap();
+
+        assert_eq!(4, diff.unchanged);
+        assert!(diff.created.is_empty() && diff.updated.is_empty() && diff.deleted.is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let quiz_template_id = ObjectId::new().to_string();
+        let existing = questions(&quiz_template_id);
+
+        let csv = format!(
+            "id,type,question,answer,options,correct\n\
+             {},,Capital of Italy?,Rome,,\n\
+             ,multiple_choice,Largest ocean?,Pacific,\"Atlantic\nPacific\",2\n\
+             {},true_false,Is water wet?,True,,true\n",
+            existing[0].id, existing[3].id
+        );
+
+        let (rows, errors) = parse(&csv).unwrap();
+
+        assert!(errors.is_empty());
+
+        let result = diff(&quiz_template_id, &existing, rows, true).unwrap();
+
+        assert_eq!(1, result.unchanged);
+        assert_eq!(
+            vec!["Largest ocean?"],
+            result
+                .created
+                .iter()
+                .map(|question| question.question.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            QuestionKind::MultipleChoice {
+                correct_option: 1,
+                options: vec!["Atlantic".to_string(), "Pacific".to_string()],
+            },
+            result.created[0].kind
+        );
+        assert_eq!(1, result.created[0].position);
+        assert_eq!(vec![existing[0].id.clone()], ids(&result.updated));
+        assert_eq!(
+            vec![existing[1].id.clone(), existing[2].id.clone()],
+            ids(&result.deleted)
+        );
+
+        let (rows, _) = parse(&csv).unwrap();
+
+        assert!(diff(&quiz_template_id, &existing, rows, false)
+            .unwrap()
+            .deleted
+            .is_empty());
+    }
+
+    #[test]
+    fn test_diff_checks_stored_formats() {
+        let quiz_template_id = ObjectId::new().to_string();
+        let mut existing = questions(&quiz_template_id);
+
+        existing[0].answer_format = ContentFormat::Markdown;
+
+        let csv = format!(
+            "id,question,answer
+             {},Capital of France?,<b>Paris</b>
+             {},Pi?,<b>3</b>
+",
+            existing[0].id, existing[2].id
+        );
+
+        let (rows, _) = parse(&csv).unwrap();
+
+        assert_eq!(
+            Err(vec![ImportError::new(
+                2,
+                "Answer: HTML is not allowed in Markdown content"
+            )]),
+            diff(&quiz_template_id, &existing, rows, false).map(|_| ())
+        );
+    }
+
+    fn ids(questions: &[Question]) -> Vec<String> {
+        questions
+            .iter()
+            .map(|question| question.id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_row_errors() {
+        let quiz_template_id = ObjectId::new().to_string();
+        let existing = questions(&quiz_template_id);
+        let stranger = ObjectId::new().to_string();
+
+        let csv = format!(
+            "id,type,question,answer,correct\n\
+             ,multiple_choice,No options,a,1\n\
+             nope,,Bad id,a,\n\
+             ,numeric,Bad number,a,abc\n\
+             ,,,a,\n\
+             {stranger},,Someone else's,a,\n\
+             {id},,Twice,a,\n\
+             {id},,Twice again,a,\n",
+            id = existing[0].id
+        );
+
+        let (rows, errors) = parse(&csv).unwrap();
+
+        assert_eq!(
+            vec![
+                ImportError::new(2, "Multiple choice questions need at least two options"),
+                ImportError::new(3, "Invalid id: nope"),
+                ImportError::new(4, "Invalid value: abc"),
+                ImportError::new(5, "Question must not be empty"),
+            ],
+            errors
+        );
+
+        assert_eq!(
+            Err(vec![
+                ImportError::new(
+                    6,
+                    format!("Question {} is not in this quiz template", stranger)
+                ),
+                ImportError::new(8, format!("Duplicate id: {}", existing[0].id)),
+            ]),
+            diff(&quiz_template_id, &existing, rows, false).map(|_| ())
+        );
+
+        assert_eq!(
+            Err(ImportError::new(1, "Missing column: answer")),
+            parse("id,question\n").map(|_| ())
+        );
+    }
+}
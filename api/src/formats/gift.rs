@@ -0,0 +1,646 @@
+//! Moodle GIFT question files.
+//!
+//! Short answer, multiple choice, true/false, numeric and matching questions are read, along
+//! with `//` comments, `::titles::` and `$CATEGORY` lines. The first category names the quiz
+//! template. Matching questions have no kind of their own, so each pair becomes a free-text
+//! question. Questions are graded right or wrong, so short answer and numeric answers are only
+//! accepted at full credit, while any choice with positive credit counts as correct.
+
+use mongodb::bson::oid::ObjectId;
+
+use super::{Import, ImportError};
+use crate::content::ContentFormat;
+use crate::matching::MatchMode;
+use crate::models::{PartialCredit, Question, QuestionKind};
+
+/// Characters that must be escaped with a backslash to appear literally.
+const SPECIAL_CHARACTERS: &[char] = &['~', '=', '#', '{', '}', ':'];
+
+const FORMATS: &[&str] = &["[html]", "[markdown]", "[moodle]", "[plain]"];
+
+const CATEGORY_PREFIX: &str = "$CATEGORY:";
+
+/// A question as written in the file, starting on `line`.
+struct Block {
+    line: usize,
+    text: String,
+}
+
+/// One `=` or `~` entry of an answer block.
+struct Answer {
+    marker: char,
+    text: String,
+    /// Percentage from a `%50%` prefix.
+    weight: Option<f64>,
+}
+
+impl Answer {
+    fn is_full_credit(&self) -> bool {
+        self.weight.is_none_or(|weight| weight >= 100.0)
+    }
+
+    fn is_correct(&self) -> bool {
+        match self.weight {
+            Some(weight) => weight > 0.0,
+            None => self.marker == '=',
+        }
+    }
+}
+
+/// The unescaped occurrences of any of `targets` with their byte offsets.
+fn find_unescaped(text: &str, targets: &[char]) -> Vec<(usize, char)> {
+    let mut found = vec![];
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if targets.contains(&c) {
+            found.push((index, c));
+        }
+    }
+
+    found
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some(&next)) if next == '\\' || SPECIAL_CHARACTERS.contains(&next) => {
+                unescaped.push(next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped.trim().to_string()
+}
+
+fn strip_feedback(text: &str) -> &str {
+    match find_unescaped(text, &['#']).first() {
+        Some((index, _)) => &text[..*index],
+        None => text,
+    }
+}
+
+fn strip_format(text: &str) -> &str {
+    let text = text.trim_start();
+
+    FORMATS
+        .iter()
+        .find_map(|format| text.strip_prefix(format))
+        .unwrap_or(text)
+}
+
+/// Splits the file into questions, which are separated by blank lines. Also returns the first
+/// category.
+fn blocks(input: &str) -> (Option<String>, Vec<Block>) {
+    let mut category = None;
+    let mut blocks = vec![];
+    let mut current: Option<Block> = None;
+
+    for (index, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("//") {
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            blocks.extend(current.take());
+
+            continue;
+        }
+
+        if let Some(path) = trimmed.strip_prefix(CATEGORY_PREFIX) {
+            blocks.extend(current.take());
+
+            if category.is_none() {
+                category = path
+                    .trim()
+                    .rsplit('/')
+                    .next()
+                    .filter(|name| !name.is_empty() && !name.starts_with('$'))
+                    .map(str::to_string);
+            }
+
+            continue;
+        }
+
+        let block = current.get_or_insert_with(|| Block {
+            line: index + 1,
+            text: String::new(),
+        });
+
+        block.text.push_str(line);
+        block.text.push('\n');
+    }
+
+    blocks.extend(current);
+
+    (category, blocks)
+}
+
+fn parse_answers(body: &str) -> Result<Vec<Answer>, String> {
+    let markers = find_unescaped(body, &['=', '~']);
+
+    match markers.first() {
+        None => return Err("Answer block has no answers".to_string()),
+        Some((index, _)) if !body[..*index].trim().is_empty() => {
+            return Err("Answers must start with = or ~".to_string());
+        }
+        _ => {}
+    }
+
+    markers
+        .iter()
+        .enumerate()
+        .map(|(position, (index, marker))| {
+            let end = markers
+                .get(position + 1)
+                .map_or(body.len(), |(next, _)| *next);
+
+            let text = strip_feedback(&body[index + 1..end]).trim();
+
+            let (weight, text) = match text.strip_prefix('%') {
+                Some(rest) => {
+                    let (weight, text) = rest
+                        .split_once('%')
+                        .ok_or_else(|| format!("Invalid answer weight: {}", text))?;
+
+                    let weight = weight
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("Invalid answer weight: {}", weight))?;
+
+                    (Some(weight), text)
+                }
+                None => (None, text),
+            };
+
+            Ok(Answer {
+                marker: *marker,
+                text: text.to_string(),
+                weight,
+            })
+        })
+        .collect()
+}
+
+fn parse_number(text: &str) -> Result<(f64, f64), String> {
+    let text = unescape(strip_feedback(text));
+
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid number: {}", value.trim()))
+    };
+
+    if let Some((min, max)) = text.split_once("..") {
+        let (min, max) = (parse(min)?, parse(max)?);
+
+        return Ok(((min + max) / 2.0, (max - min).abs() / 2.0));
+    }
+
+    match text.split_once(':') {
+        Some((value, tolerance)) => Ok((parse(value)?, parse(tolerance)?)),
+        None => Ok((parse(&text)?, 0.0)),
+    }
+}
+
+fn numeric(body: &str) -> Result<(String, QuestionKind), String> {
+    let (value, tolerance) = if find_unescaped(body, &['=']).is_empty() {
+        parse_number(body)?
+    } else {
+        let answers = parse_answers(body)?;
+
+        let answer = answers
+            .iter()
+            .find(|answer| answer.is_full_credit())
+            .ok_or("Numeric question has no fully correct answer")?;
+
+        parse_number(&answer.text)?
+    };
+
+    Ok((
+        value.to_string(),
+        QuestionKind::Numeric { tolerance, value },
+    ))
+}
+
+fn short_answer(answers: &[Answer]) -> Result<(String, QuestionKind), String> {
+    let mut accepted = answers
+        .iter()
+        .filter(|answer| answer.is_full_credit())
+        .map(|answer| unescape(&answer.text))
+        .filter(|text| !text.is_empty());
+
+    let answer = accepted
+        .next()
+        .ok_or("Short answer question has no fully correct answer")?;
+
+    let accepted = accepted.collect::<Vec<_>>();
+
+    let matching = match accepted.is_empty() {
+        true => MatchMode::Normalized,
+        false => MatchMode::Alternatives { accepted },
+    };
+
+    Ok((answer, QuestionKind::FreeText { matching }))
+}
+
+fn choices(answers: &[Answer]) -> Result<(String, QuestionKind), String> {
+    let options = answers
+        .iter()
+        .map(|answer| unescape(&answer.text))
+        .collect::<Vec<_>>();
+
+    let correct = answers
+        .iter()
+        .enumerate()
+        .filter(|(_, answer)| answer.is_correct())
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    let single =
+        answers.iter().filter(|answer| answer.marker == '=').count() == 1 && correct.len() == 1;
+
+    let answer = correct
+        .iter()
+        .map(|index| options[*index].clone())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match (single, correct.is_empty()) {
+        (_, true) => Err("Multiple choice question has no correct answer".to_string()),
+        (true, false) => Ok((
+            answer,
+            QuestionKind::MultipleChoice {
+                correct_option: correct[0],
+                options,
+            },
+        )),
+        (false, false) => Ok((
+            answer,
+            QuestionKind::MultipleSelect {
+                correct_options: correct,
+                options,
+            },
+        )),
+    }
+}
+
+fn matching(
+    question: &str,
+    answers: &[Answer],
+) -> Result<Vec<(String, String, QuestionKind)>, String> {
+    let pairs = answers
+        .iter()
+        .map(|answer| {
+            let (left, right) = answer
+                .text
+                .split_once("->")
+                .ok_or("Every matching answer needs a ->")?;
+
+            Ok((unescape(left), unescape(right)))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Pairs with nothing on the left are distractors, which free-text questions have no use for.
+    Ok(pairs
+        .into_iter()
+        .filter(|(left, _)| !left.is_empty())
+        .map(|(left, right)| {
+            (
+                format!("{}\n{}", question, left),
+                right,
+                QuestionKind::FreeText {
+                    matching: MatchMode::Normalized,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Reads one question block into the question text, answer and kind of each question it makes.
+fn parse_block(text: &str) -> Result<Vec<(String, String, QuestionKind)>, String> {
+    let mut text = text.trim();
+    let mut title = String::new();
+
+    if let Some(rest) = text.strip_prefix("::") {
+        let colons = find_unescaped(rest, &[':']);
+
+        let end = colons
+            .windows(2)
+            .find(|pair| pair[1].0 == pair[0].0 + 1)
+            .map(|pair| pair[0].0)
+            .ok_or("Title is not closed with ::")?;
+
+        title = unescape(&rest[..end]);
+        text = &rest[end + 2..];
+    }
+
+    let braces = find_unescaped(text, &['{', '}']);
+
+    let open = braces
+        .iter()
+        .find(|(_, c)| *c == '{')
+        .map(|(index, _)| *index)
+        .ok_or("Question has no answer block")?;
+
+    let close = braces
+        .iter()
+        .find(|(index, c)| *c == '}' && *index > open)
+        .map(|(index, _)| *index)
+        .ok_or("Answer block is not closed")?;
+
+    let before = unescape(strip_format(&text[..open]));
+    let after = unescape(&text[close + 1..]);
+
+    let question = match (before.is_empty(), after.is_empty()) {
+        (_, true) => before,
+        (true, false) => format!("_____ {}", after),
+        (false, false) => format!("{} _____ {}", before, after),
+    };
+
+    let question = match question.is_empty() {
+        true => title,
+        false => question,
+    };
+
+    if question.is_empty() {
+        return Err("Question text is empty".to_string());
+    }
+
+    let body = text[open + 1..close].trim();
+
+    if body.is_empty() {
+        return Err("Essay questions are not supported".to_string());
+    }
+
+    let single = |(answer, kind): (String, QuestionKind)| {
+        kind.validate().map_err(|e| e.to_string())?;
+
+        Ok(vec![(question.clone(), answer, kind)])
+    };
+
+    if let Some(numeric_body) = body.strip_prefix('#') {
+        return single(numeric(numeric_body)?);
+    }
+
+    match unescape(strip_feedback(body)).to_uppercase().as_str() {
+        "T" | "TRUE" => {
+            return single((
+                "True".to_string(),
+                QuestionKind::TrueFalse { correct: true },
+            ));
+        }
+        "F" | "FALSE" => {
+            return single((
+                "False".to_string(),
+                QuestionKind::TrueFalse { correct: false },
+            ));
+        }
+        _ => {}
+    }
+
+    let answers = parse_answers(body)?;
+
+    if answers.iter().all(|answer| answer.marker == '=') {
+        if answers.iter().all(|answer| answer.text.contains("->")) {
+            return matching(&question, &answers);
+        }
+
+        return single(short_answer(&answers)?);
+    }
+
+    single(choices(&answers)?)
+}
+
+/// Reads every question in a GIFT file. Questions that cannot be read are reported against the
+/// line they start on and left out.
+pub fn parse(input: &str) -> Import {
+    let (name, blocks) = blocks(input);
+
+    let mut import = Import {
+        name,
+        ..Default::default()
+    };
+
+    for block in blocks {
+        match parse_block(&block.text) {
+            Ok(questions) => {
+                import
+                    .questions
+                    .extend(
+                        questions
+                            .into_iter()
+                            .map(|(question, answer, kind)| Question {
+                                id: ObjectId::new().to_string(),
+                                answer,
+                                answer_format: ContentFormat::Plain,
+                                attachments: vec![],
+                                explanation: None,
+                                hints: vec![],
+                                kind,
+                                partial_credit: PartialCredit::default(),
+                                points: 1.0,
+                                position: 0,
+                                question,
+                                question_format: ContentFormat::Plain,
+                                quiz_template_id: String::new(),
+                            }),
+                    )
+            }
+            Err(message) => import.errors.push(ImportError::new(block.line, message)),
+        }
+    }
+
+    import
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"// Geography questions
+$CATEGORY: $course$/top/Geography
+
+::Capital::What is the capital of France? {=Paris =paris city =%50%Lyon}
+
+Which of these is a river? {
+    =Danube # Correct
+    ~Alps
+    ~Sahara
+}
+
+Pick the oceans. {~%50%Atlantic ~%50%Pacific ~%-100%Everest}
+
+The Nile is in Africa. {T}
+
+The Moon is made of cheese.{FALSE#Sadly not}
+
+How many continents are there? {#7}
+
+What is g to one place? {#9.8:0.05}
+
+A year between 1990 and 2000. {#1990..2000}
+
+Match the countries to their capitals. {
+    =Italy -> Rome
+    =Spain -> Madrid
+    = -> Lisbon
+}
+
+Write an essay about glaciers. {}
+
+This has \{escaped\} braces and a\: colon {=yes}
+"#;
+
+    #[test]
+    fn test_parse() {
+        let import = parse(SAMPLE);
+
+        assert_eq!(Some("Geography".to_string()), import.name);
+
+        let questions = import
+            .questions
+            .iter()
+            .map(|question| {
+                (
+                    question.question.as_str(),
+                    question.answer.as_str(),
+                    &question.kind,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (
+                    "What is the capital of France?",
+                    "Paris",
+                    &QuestionKind::FreeText {
+                        matching: MatchMode::Alternatives {
+                            accepted: vec!["paris city".to_string()],
+                        },
+                    },
+                ),
+                (
+                    "Which of these is a river?",
+                    "Danube",
+                    &QuestionKind::MultipleChoice {
+                        correct_option: 0,
+                        options: vec![
+                            "Danube".to_string(),
+                            "Alps".to_string(),
+                            "Sahara".to_string(),
+                        ],
+                    },
+                ),
+                (
+                    "Pick the oceans.",
+                    "Atlantic, Pacific",
+                    &QuestionKind::MultipleSelect {
+                        correct_options: vec![0, 1],
+                        options: vec![
+                            "Atlantic".to_string(),
+                            "Pacific".to_string(),
+                            "Everest".to_string(),
+                        ],
+                    },
+                ),
+                (
+                    "The Nile is in Africa.",
+                    "True",
+                    &QuestionKind::TrueFalse { correct: true },
+                ),
+                (
+                    "The Moon is made of cheese.",
+                    "False",
+                    &QuestionKind::TrueFalse { correct: false },
+                ),
+                (
+                    "How many continents are there?",
+                    "7",
+                    &QuestionKind::Numeric {
+                        tolerance: 0.0,
+                        value: 7.0,
+                    },
+                ),
+                (
+                    "What is g to one place?",
+                    "9.8",
+                    &QuestionKind::Numeric {
+                        tolerance: 0.05,
+                        value: 9.8,
+                    },
+                ),
+                (
+                    "A year between 1990 and 2000.",
+                    "1995",
+                    &QuestionKind::Numeric {
+                        tolerance: 5.0,
+                        value: 1995.0,
+                    },
+                ),
+                (
+                    "Match the countries to their capitals.\nItaly",
+                    "Rome",
+                    &QuestionKind::FreeText {
+                        matching: MatchMode::Normalized,
+                    },
+                ),
+                (
+                    "Match the countries to their capitals.\nSpain",
+                    "Madrid",
+                    &QuestionKind::FreeText {
+                        matching: MatchMode::Normalized,
+                    },
+                ),
+                (
+                    "This has {escaped} braces and a: colon",
+                    "yes",
+                    &QuestionKind::FreeText {
+                        matching: MatchMode::Normalized,
+                    },
+                ),
+            ],
+            questions
+        );
+
+        assert_eq!(
+            vec![ImportError::new(30, "Essay questions are not supported")],
+            import.errors
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let import = parse(
+            "Missing answers\n\nUnclosed {=a\n\nNo correct {~a ~b}\n\nBad number {#abc}\n\nFine {=ok}",
+        );
+
+        assert_eq!(1, import.questions.len());
+        assert_eq!(
+            vec![
+                ImportError::new(1, "Question has no answer block"),
+                ImportError::new(3, "Answer block is not closed"),
+                ImportError::new(5, "Multiple choice question has no correct answer"),
+                ImportError::new(7, "Invalid number: abc"),
+            ],
+            import.errors
+        );
+    }
+}
@@ -0,0 +1,46 @@
+pub mod apkg;
+pub mod bundle;
+pub mod csv;
+pub mod gift;
+pub mod pdf;
+pub mod print;
+pub mod qti;
+
+use crate::models::Question;
+
+/// Something in an imported file that could not be turned into a question.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportError {
+    /// File of a package the problem is in, when the import reads more than one.
+    pub file: Option<String>,
+    /// 1-based line the problem starts on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ImportError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            file: None,
+            line,
+            message: message.into(),
+        }
+    }
+
+    pub fn in_file(file: impl Into<String>, line: usize, message: impl Into<String>) -> Self {
+        Self {
+            file: Some(file.into()),
+            ..Self::new(line, message)
+        }
+    }
+}
+
+/// The questions read from a file. They have no quiz template yet; it is set when the import is
+/// saved.
+#[derive(Debug, Default)]
+pub struct Import {
+    pub errors: Vec<ImportError>,
+    /// Suggested quiz template name, when the file carries one.
+    pub name: Option<String>,
+    pub questions: Vec<Question>,
+}
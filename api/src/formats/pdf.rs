@@ -0,0 +1,430 @@
+//! Printouts as PDF, written directly so that no fonts or external tools are needed.
+//!
+//! Text uses the standard Helvetica fonts every PDF reader has, in WinAnsi encoding, so
+//! characters outside Latin-1 and common punctuation print as `?`. Pages are A4.
+
+use super::print::{Block, Rich};
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 56.0;
+
+const TEXT_SIZE: f64 = 11.0;
+const HEADING_SIZE: f64 = 13.0;
+const TITLE_SIZE: f64 = 16.0;
+
+const INDENT: f64 = 18.0;
+const ANSWER_LINE_HEIGHT: f64 = 22.0;
+
+/// Helvetica widths of the printable ASCII characters, in thousandths of the font size.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+        }
+    }
+
+    fn width(&self, text: &str, size: f64) -> f64 {
+        let width = text
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => HELVETICA_WIDTHS[c as usize - 32],
+                _ => 556,
+            })
+            .map(f64::from)
+            .sum::<f64>()
+            * size
+            / 1000.0;
+
+        match self {
+            // Bold glyphs are slightly wider. Overestimating only wraps a little early.
+            Self::Regular => width,
+            Self::Bold => width * 1.1,
+        }
+    }
+}
+
+/// The WinAnsi byte of a character, or `?` when it has none.
+fn win_ansi(c: char) -> u8 {
+    match c {
+        '€' => 0x80,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+        _ => b'?',
+    }
+}
+
+/// A PDF string literal.
+fn literal(text: &str) -> Vec<u8> {
+    let mut bytes = vec![b'('];
+
+    for byte in text.chars().map(win_ansi) {
+        if matches!(byte, b'(' | b')' | b'\\') {
+            bytes.push(b'\\');
+        }
+
+        bytes.push(byte);
+    }
+
+    bytes.push(b')');
+
+    bytes
+}
+
+/// Greedy word wrap. Words longer than a line are split between characters.
+fn wrap(text: &str, font: Font, size: f64, width: f64) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = match line.is_empty() {
+            true => word.to_string(),
+            false => format!("{} {}", line, word),
+        };
+
+        if font.width(&candidate, size) <= width {
+            line = candidate;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        for c in word.chars() {
+            if !line.is_empty() && font.width(&format!("{}{}", line, c), size) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            line.push(c);
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+struct Layout {
+    pages: Vec<Vec<u8>>,
+    /// Distance of the next line's top from the bottom of the page.
+    y: f64,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![vec![]],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn at_top(&self) -> bool {
+        self.y >= PAGE_HEIGHT - MARGIN
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(vec![]);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// Starts a new page unless `height` fits on this one.
+    fn reserve(&mut self, height: f64) {
+        if self.y - height < MARGIN && !self.at_top() {
+            self.new_page();
+        }
+    }
+
+    fn content(&mut self) -> &mut Vec<u8> {
+        self.pages.last_mut().expect("Layout always has a page")
+    }
+
+    fn text(&mut self, text: &str, font: Font, size: f64, x: f64) {
+        let leading = size * 1.3;
+
+        for line in wrap(text, font, size, PAGE_WIDTH - MARGIN - x) {
+            self.reserve(leading);
+            self.y -= leading;
+
+            let baseline = self.y + size * 0.25;
+            let content = self.content();
+
+            content.extend(
+                format!(
+                    "BT /{} {} Tf {:.2} {:.2} Td ",
+                    font.name(),
+                    size,
+                    x,
+                    baseline
+                )
+                .bytes(),
+            );
+            content.extend(literal(&line));
+            content.extend(b" Tj ET\n");
+        }
+    }
+
+    fn answer_line(&mut self) {
+        self.reserve(ANSWER_LINE_HEIGHT);
+        self.y -= ANSWER_LINE_HEIGHT;
+
+        let y = self.y;
+
+        self.content().extend(
+            format!(
+                "0.5 w {:.2} {:.2} m {:.2} {:.2} l S\n",
+                MARGIN + INDENT,
+                y,
+                PAGE_WIDTH - MARGIN,
+                y
+            )
+            .bytes(),
+        );
+    }
+
+    fn gap(&mut self, height: f64) {
+        if !self.at_top() {
+            self.y -= height;
+        }
+    }
+}
+
+fn item_height(text: &str, details: &[Rich], answer_lines: usize) -> f64 {
+    let leading = TEXT_SIZE * 1.3;
+    let width = PAGE_WIDTH - 2.0 * MARGIN - INDENT;
+
+    let lines = wrap(text, Font::Regular, TEXT_SIZE, width).len()
+        + details
+            .iter()
+            .map(|detail| wrap(&detail.text, Font::Regular, TEXT_SIZE, width).len())
+            .sum::<usize>();
+
+    lines as f64 * leading + answer_lines as f64 * ANSWER_LINE_HEIGHT
+}
+
+/// Lays the blocks out on pages and writes the document.
+pub fn render(blocks: &[Block]) -> Vec<u8> {
+    let mut layout = Layout::new();
+
+    for block in blocks {
+        match block {
+            Block::Title(text) => {
+                layout.text(text, Font::Bold, TITLE_SIZE, MARGIN);
+                layout.gap(TEXT_SIZE);
+            }
+            Block::Heading(text) => {
+                layout.gap(TEXT_SIZE);
+                layout.text(text, Font::Bold, HEADING_SIZE, MARGIN);
+                layout.gap(TEXT_SIZE / 2.0);
+            }
+            Block::Text(text) => {
+                layout.text(text, Font::Regular, TEXT_SIZE, MARGIN);
+                layout.gap(TEXT_SIZE);
+            }
+            Block::Item {
+                answer_lines,
+                details,
+                number,
+                text,
+            } => {
+                // Keep short items on one page.
+                layout.reserve(
+                    item_height(&text.text, details, *answer_lines).min(PAGE_HEIGHT / 3.0),
+                );
+
+                let number = format!("{}.", number);
+
+                let y = layout.y;
+                layout.text(&number, Font::Bold, TEXT_SIZE, MARGIN);
+                layout.y = y;
+                layout.text(&text.text, Font::Regular, TEXT_SIZE, MARGIN + INDENT);
+
+                for detail in details {
+                    layout.text(
+                        &detail.text,
+                        Font::Regular,
+                        TEXT_SIZE,
+                        MARGIN + INDENT * 2.0,
+                    );
+                }
+
+                for _ in 0..*answer_lines {
+                    layout.answer_line();
+                }
+
+                layout.gap(TEXT_SIZE);
+            }
+            Block::PageBreak => layout.new_page(),
+        }
+    }
+
+    document(&layout.pages)
+}
+
+/// Writes the objects of a document with the page content streams given.
+fn document(pages: &[Vec<u8>]) -> Vec<u8> {
+    // Objects 1 to 4 are the catalog, page tree and fonts. Each page then takes two objects:
+    // the page and its content stream.
+    let page_id = |index: usize| 5 + 2 * index;
+
+    let kids = (0..pages.len())
+        .map(|index| format!("{} 0 R", page_id(index)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let font = |name: &str| {
+        format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            name
+        )
+        .into_bytes()
+    };
+
+    let mut objects = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()).into_bytes(),
+        font("Helvetica"),
+        font("Helvetica-Bold"),
+    ];
+
+    for (index, content) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id(index) + 1
+            )
+            .into_bytes(),
+        );
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+
+        pdf.extend(format!("{} 0 obj\n", index + 1).bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .bytes(),
+    );
+
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let lines = wrap(
+            "The quick brown fox jumps over the lazy dog",
+            Font::Regular,
+            TEXT_SIZE,
+            100.0,
+        );
+
+        assert!(lines.len() > 1);
+        assert!(lines
+            .iter()
+            .all(|line| Font::Regular.width(line, TEXT_SIZE) <= 100.0));
+        assert_eq!(
+            "The quick brown fox jumps over the lazy dog",
+            lines.join(" ")
+        );
+
+        let long = "x".repeat(200);
+
+        assert_eq!(long, wrap(&long, Font::Regular, TEXT_SIZE, 100.0).concat());
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(
+            b"(a \\(b\\) \\\\ \xe9 ? \x96)".to_vec(),
+            literal("a (b) \\ é ✓ –")
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let blocks = vec![
+            Block::Title("Quiz".to_string()),
+            Block::Item {
+                answer_lines: 60,
+                details: vec![],
+                number: 1,
+                text: Rich::plain("Write an essay"),
+            },
+            Block::PageBreak,
+            Block::Text("Seed 1".to_string()),
+        ];
+
+        let pdf = render(&blocks);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        // The answer lines overflow onto a second page before the page break starts a third.
+        assert!(text.contains("/Count 3"));
+
+        let xref = text[text.rfind("startxref\n").unwrap() + 10..]
+            .lines()
+            .next()
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        assert!(text[xref..].starts_with("xref"));
+    }
+}
@@ -0,0 +1,449 @@
+//! Quiz templates laid out for printing, with answer keys.
+//!
+//! A printout is a list of [`Block`]s rendered to a self-contained HTML document or, through
+//! [`super::pdf`], to a PDF. Variants are papers drawn with consecutive seeds, so a paper and
+//! its key printed from the same seed and options always match.
+
+use quick_xml::escape::escape;
+
+use crate::content::ContentFormat;
+use crate::matching::MatchMode;
+use crate::models::{ModelError, Paper, PaperOptions, Question, QuestionKind, QuizTemplate};
+
+pub const MAX_VARIANTS: usize = 26;
+
+/// Text of a printout as plain text for PDFs and as sanitised HTML for the HTML document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rich {
+    pub html: String,
+    pub text: String,
+}
+
+impl Rich {
+    pub fn plain(text: impl Into<String>) -> Self {
+        let text = text.into();
+
+        Self {
+            html: escape(&text).into_owned(),
+            text,
+        }
+    }
+
+    /// Question or answer content, rendered with its format.
+    fn content(format: ContentFormat, source: &str) -> Self {
+        Self {
+            html: format.render(source),
+            text: format.plain_text(source),
+        }
+    }
+
+    fn with_suffix(mut self, suffix: &str) -> Self {
+        self.html.push_str(&escape(suffix));
+        self.text.push_str(suffix);
+
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Title(String),
+    Heading(String),
+    Text(String),
+    /// A numbered question or answer, with indented `details` below it and ruled lines to
+    /// write on.
+    Item {
+        answer_lines: usize,
+        details: Vec<Rich>,
+        number: usize,
+        text: Rich,
+    },
+    PageBreak,
+}
+
+/// One version of a printed paper, labelled with a capital letter.
+pub struct Variant {
+    pub label: char,
+    pub paper: Paper,
+}
+
+/// Draws `count` variants. Variant `n` uses `seed + n`.
+pub fn variants(
+    pool: &[Question],
+    options: &PaperOptions,
+    seed: i64,
+    count: usize,
+) -> Result<Vec<Variant>, ModelError> {
+    if count == 0 || count > MAX_VARIANTS {
+        Err(format!("Variants must be between 1 and {}", MAX_VARIANTS))?;
+    }
+
+    ('A'..='Z')
+        .take(count)
+        .enumerate()
+        .map(|(index, label)| {
+            Ok(Variant {
+                label,
+                paper: Paper::draw(pool, options.clone(), seed.wrapping_add(index as i64))?,
+            })
+        })
+        .collect()
+}
+
+fn option_label(index: usize) -> String {
+    format!("{})", (b'a' + (index % 26) as u8) as char)
+}
+
+fn variant_title(quiz_template: &QuizTemplate, variant: &Variant, count: usize) -> String {
+    match count {
+        1 => quiz_template.name.clone(),
+        _ => format!("{} (variant {})", quiz_template.name, variant.label),
+    }
+}
+
+fn question_item(number: usize, question: &Question, order: &[usize]) -> Block {
+    let (suffix, details, answer_lines) = match &question.kind {
+        QuestionKind::FreeText { .. } => ("", vec![], 2),
+        QuestionKind::MultipleChoice { options, .. } => (
+            "",
+            order
+                .iter()
+                .enumerate()
+                .map(|(position, option)| {
+                    Rich::plain(format!("{} {}", option_label(position), options[*option]))
+                })
+                .collect(),
+            0,
+        ),
+        QuestionKind::MultipleSelect { options, .. } => (
+            " (select all that apply)",
+            order
+                .iter()
+                .enumerate()
+                .map(|(position, option)| {
+                    Rich::plain(format!("{} {}", option_label(position), options[*option]))
+                })
+                .collect(),
+            0,
+        ),
+        QuestionKind::TrueFalse { .. } => ("", vec![Rich::plain("True / False")], 0),
+        QuestionKind::Numeric { .. } => ("", vec![], 1),
+        QuestionKind::Ordering { items } => (
+            " (number the items in order)",
+            order
+                .iter()
+                .map(|item| Rich::plain(format!("____ {}", items[*item])))
+                .collect(),
+            0,
+        ),
+    };
+
+    Block::Item {
+        answer_lines,
+        details,
+        number,
+        text: Rich::content(question.question_format, &question.question).with_suffix(suffix),
+    }
+}
+
+/// The papers of every variant, each starting on a new page. The seed is printed at the end so
+/// that the matching key can be requested.
+pub fn paper(
+    quiz_template: &QuizTemplate,
+    pool: &[Question],
+    variants: &[Variant],
+    seed: i64,
+) -> Vec<Block> {
+    let mut blocks = vec![];
+
+    for (index, variant) in variants.iter().enumerate() {
+        if index > 0 {
+            blocks.push(Block::PageBreak);
+        }
+
+        blocks.push(Block::Title(variant_title(
+            quiz_template,
+            variant,
+            variants.len(),
+        )));
+        blocks.push(Block::Text(
+            "Name: ______________________________    Date: ______________".to_string(),
+        ));
+
+        for (position, question) in variant.paper.questions(pool).into_iter().enumerate() {
+            blocks.push(question_item(
+                position + 1,
+                question,
+                &variant.paper.option_order(position, question),
+            ));
+        }
+    }
+
+    blocks.push(Block::Text(format!("Seed {}", seed)));
+
+    blocks
+}
+
+fn key_answer(question: &Question, order: &[usize]) -> Rich {
+    let labelled = |options: &[String], correct: &[usize]| {
+        order
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| correct.contains(option))
+            .map(|(position, option)| format!("{} {}", option_label(position), options[*option]))
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    let answer = Rich::content(question.answer_format, &question.answer);
+
+    let text = match &question.kind {
+        QuestionKind::FreeText {
+            matching: MatchMode::Alternatives { accepted },
+        } => return answer.with_suffix(&format!(" (also accept: {})", accepted.join(", "))),
+        QuestionKind::FreeText { .. } => return answer,
+        QuestionKind::MultipleChoice {
+            correct_option,
+            options,
+        } => labelled(options, &[*correct_option]),
+        QuestionKind::MultipleSelect {
+            correct_options,
+            options,
+        } => labelled(options, correct_options),
+        QuestionKind::TrueFalse { correct } => match correct {
+            true => "True".to_string(),
+            false => "False".to_string(),
+        },
+        QuestionKind::Numeric { tolerance, value } if *tolerance > 0.0 => {
+            format!("{} ± {}", value, tolerance)
+        }
+        QuestionKind::Numeric { value, .. } => value.to_string(),
+        QuestionKind::Ordering { items } => items.join(", "),
+    };
+
+    Rich::plain(text)
+}
+
+/// The answers of every variant, numbered as on the papers.
+pub fn answer_key(
+    quiz_template: &QuizTemplate,
+    pool: &[Question],
+    variants: &[Variant],
+    seed: i64,
+) -> Vec<Block> {
+    let mut blocks = vec![Block::Title(format!("{}: answer key", quiz_template.name))];
+
+    for variant in variants {
+        if variants.len() > 1 {
+            blocks.push(Block::Heading(format!("Variant {}", variant.label)));
+        }
+
+        for (position, question) in variant.paper.questions(pool).into_iter().enumerate() {
+            blocks.push(Block::Item {
+                answer_lines: 0,
+                details: vec![key_answer(
+                    question,
+                    &variant.paper.option_order(position, question),
+                )],
+                number: position + 1,
+                text: Rich::content(question.question_format, &question.question),
+            });
+        }
+    }
+
+    blocks.push(Block::Text(format!("Seed {}", seed)));
+
+    blocks
+}
+
+const STYLE: &str = "
+body { font-family: Helvetica, Arial, sans-serif; font-size: 11pt; margin: 2cm; }
+h1 { font-size: 16pt; }
+h2 { font-size: 13pt; }
+.item { break-inside: avoid; margin: 0 0 1em; }
+.item .number { float: left; }
+.item .text { margin-left: 1.5em; }
+.item p { margin: 0 0 0.3em; }
+.item ul { list-style: none; margin: 0; padding-left: 3em; }
+.line { border-bottom: 1px solid #000; height: 1.8em; margin-left: 1.5em; }
+.page-break { break-after: page; }
+@page { size: A4; margin: 0; }
+";
+
+/// A standalone HTML document with its own styles and no external resources.
+pub fn html(title: &str, blocks: &[Block]) -> String {
+    let body = blocks
+        .iter()
+        .map(|block| match block {
+            Block::Title(text) => format!("<h1>{}</h1>\n", escape(text)),
+            Block::Heading(text) => format!("<h2>{}</h2>\n", escape(text)),
+            Block::Text(text) => format!("<p>{}</p>\n", escape(text)),
+            Block::Item {
+                answer_lines,
+                details,
+                number,
+                text,
+            } => {
+                let details = match details.is_empty() {
+                    true => String::new(),
+                    false => format!(
+                        "<ul>{}</ul>",
+                        details
+                            .iter()
+                            .map(|detail| format!("<li>{}</li>", detail.html))
+                            .collect::<String>()
+                    ),
+                };
+
+                format!(
+                    "<div class=\"item\"><strong class=\"number\">{}.</strong>\
+                     <div class=\"text\">{}</div>{}{}</div>\n",
+                    number,
+                    text.html,
+                    details,
+                    "<div class=\"line\"></div>".repeat(*answer_lines)
+                )
+            }
+            Block::PageBreak => "<div class=\"page-break\"></div>\n".to_string(),
+        })
+        .collect::<String>();
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::content::ContentFormat;
+    use crate::models::Scoring;
+    use mongodb::bson::oid::ObjectId;
+
+    use super::*;
+
+    fn quiz_template() -> (QuizTemplate, Vec<Question>) {
+        let quiz_template = QuizTemplate {
+            id: ObjectId::new().to_string(),
+            name: "Capitals".to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: None,
+            scoring: Scoring::default(),
+            time_limit_seconds: None,
+            user_id: ObjectId::new().to_string(),
+        };
+
+        let questions = (0..6)
+            .map(|index| {
+                Question::fixture(
+                    &quiz_template.id,
+                    &format!("Question {index}?"),
+                    &format!("Option {index}-0"),
+                    QuestionKind::MultipleChoice {
+                        correct_option: 0,
+                        options: (0..4)
+                            .map(|option| format!("Option {index}-{option}"))
+                            .collect(),
+                    },
+                )
+            })
+            .collect();
+
+        (quiz_template, questions)
+    }
+
+    fn options() -> PaperOptions {
+        PaperOptions {
+            question_count: Some(4),
+            shuffle_options: true,
+            shuffle_questions: true,
+        }
+    }
+
+    #[test]
+    fn test_variants_are_reproducible() {
+        let (_, pool) = quiz_template();
+
+        let first = variants(&pool, &options(), 7, 3).unwrap();
+        let second = variants(&pool, &options(), 7, 3).unwrap();
+
+        assert_eq!(
+            vec!['A', 'B', 'C'],
+            first
+                .iter()
+                .map(|variant| variant.label)
+                .collect::<Vec<_>>()
+        );
+        assert!(first.iter().zip(&second).all(|(a, b)| a.paper == b.paper));
+        assert_ne!(first[0].paper.seed, first[1].paper.seed);
+        assert!(variants(&pool, &options(), 7, 27).is_err());
+    }
+
+    #[test]
+    fn test_key_matches_paper() {
+        let (quiz_template, pool) = quiz_template();
+
+        let variants = variants(&pool, &options(), 42, 2).unwrap();
+
+        let paper = paper(&quiz_template, &pool, &variants, 42);
+        let key = answer_key(&quiz_template, &pool, &variants, 42);
+
+        let items = |blocks: &[Block]| {
+            blocks
+                .iter()
+                .filter_map(|block| match block {
+                    Block::Item { details, .. } => Some(details.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Every key entry names the label the correct option was printed with.
+        for (options, answer) in items(&paper).iter().zip(items(&key)) {
+            assert!(options.contains(&answer[0]));
+            assert!(answer[0].text.ends_with("-0"));
+        }
+
+        assert_eq!(8, items(&key).len());
+        assert!(paper.contains(&Block::PageBreak));
+        assert!(paper.contains(&Block::Title("Capitals (variant B)".to_string())));
+        assert_eq!(Some(&Block::Text("Seed 42".to_string())), paper.last());
+        assert_eq!(paper.last(), key.last());
+    }
+
+    #[test]
+    fn test_content_is_rendered() {
+        let (_, mut pool) = quiz_template();
+
+        pool[0].question = "**Bold** question?".to_string();
+        pool[0].question_format = ContentFormat::Markdown;
+
+        let Block::Item { text, .. } = question_item(1, &pool[0], &[0, 1, 2, 3]) else {
+            unreachable!("questions print as items");
+        };
+
+        assert_eq!("<p><strong>Bold</strong> question?</p>\n", text.html);
+        assert_eq!("Bold question?", text.text);
+    }
+
+    #[test]
+    fn test_html_escapes() {
+        let html = html(
+            "A & B",
+            &[Block::Item {
+                answer_lines: 2,
+                details: vec![],
+                number: 1,
+                text: Rich::plain("Is 1 < 2?"),
+            }],
+        );
+
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<div class=\"text\">Is 1 &lt; 2?</div>"));
+        assert_eq!(2, html.matches("class=\"line\"").count());
+    }
+}
@@ -0,0 +1,1009 @@
+//! IMS QTI 2.1 content packages, for exchanging quizzes with learning management systems.
+//!
+//! A package is a zip holding `imsmanifest.xml` and one assessmentItem per question. Free-text
+//! questions become text entry interactions, choice and true/false questions choice
+//! interactions, numeric questions float text entries and ordering questions order
+//! interactions. QTI cannot express every free-text matching mode: exact matching is kept,
+//! alternatives become a case-insensitive mapping and the other modes are read back as
+//! case-insensitive. Items with any other interaction are reported rather than imported.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use mongodb::bson::oid::ObjectId;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use zip::write::SimpleFileOptions;
+use zip::{result::ZipResult, ZipArchive, ZipWriter};
+
+use super::{Import, ImportError};
+use crate::content::ContentFormat;
+use crate::matching::MatchMode;
+use crate::models::{PartialCredit, Question, QuestionKind, QuizTemplate};
+
+const MANIFEST: &str = "imsmanifest.xml";
+
+const ITEM_RESOURCE_TYPE: &str = "imsqti_item_xmlv2p1";
+
+const RESPONSE: &str = "RESPONSE";
+
+const MATCH_CORRECT: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/match_correct";
+
+const MAP_RESPONSE: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/map_response";
+
+/// Largest uncompressed file read from a package, in bytes.
+const MAX_FILE_LENGTH: u64 = 1 << 20;
+
+/// Interactions an item may use to be imported.
+const SUPPORTED_INTERACTIONS: [&str; 3] = [
+    "choiceInteraction",
+    "orderInteraction",
+    "textEntryInteraction",
+];
+
+/// Identifier of a question's item. QTI identifiers may not start with a digit.
+fn item_identifier(question: &Question) -> String {
+    format!("Q{}", question.id)
+}
+
+fn choice_identifier(index: usize) -> String {
+    format!("choice_{}", index + 1)
+}
+
+fn simple_choices(options: &[String]) -> String {
+    options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            format!(
+                "      <simpleChoice identifier=\"{}\">{}</simpleChoice>\n",
+                choice_identifier(index),
+                escape(option)
+            )
+        })
+        .collect()
+}
+
+fn values<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|value| format!("<value>{}</value>", escape(&value.to_string())))
+        .collect()
+}
+
+fn response_declaration(cardinality: &str, base_type: &str, correct: &str, extra: &str) -> String {
+    format!(
+        "  <responseDeclaration identifier=\"{}\" cardinality=\"{}\" baseType=\"{}\">\n    \
+         <correctResponse>{}</correctResponse>\n{}  </responseDeclaration>\n",
+        RESPONSE, cardinality, base_type, correct, extra
+    )
+}
+
+fn choice_item(question: &Question, options: &[String], correct: &[usize]) -> (String, String) {
+    let cardinality = match question.kind {
+        QuestionKind::MultipleSelect { .. } => "multiple",
+        _ => "single",
+    };
+
+    let max_choices = match cardinality {
+        "multiple" => 0,
+        _ => 1,
+    };
+
+    (
+        response_declaration(
+            cardinality,
+            "identifier",
+            &values(correct.iter().map(|index| choice_identifier(*index))),
+            "",
+        ),
+        format!(
+            "    <choiceInteraction responseIdentifier=\"{}\" shuffle=\"false\" \
+             maxChoices=\"{}\">\n      <prompt>{}</prompt>\n{}    </choiceInteraction>\n",
+            RESPONSE,
+            max_choices,
+            escape(&question.question),
+            simple_choices(options)
+        ),
+    )
+}
+
+fn text_entry(question: &Question) -> String {
+    format!(
+        "    <p>{}</p>\n    <p><textEntryInteraction responseIdentifier=\"{}\"/></p>\n",
+        escape(&question.question),
+        RESPONSE
+    )
+}
+
+/// Scores a float response against the correct value within `tolerance` either way.
+fn tolerance_processing(tolerance: f64) -> String {
+    format!(
+        "  <responseProcessing>\n    <responseCondition>\n      <responseIf>\n        \
+         <equal toleranceMode=\"absolute\" tolerance=\"{0} {0}\">\
+         <variable identifier=\"{1}\"/><correct identifier=\"{1}\"/></equal>\n        \
+         <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">1</baseValue>\
+         </setOutcomeValue>\n      </responseIf>\n      <responseElse>\n        \
+         <setOutcomeValue identifier=\"SCORE\"><baseValue baseType=\"float\">0</baseValue>\
+         </setOutcomeValue>\n      </responseElse>\n    </responseCondition>\n  \
+         </responseProcessing>\n",
+        tolerance, RESPONSE
+    )
+}
+
+fn template_processing(template: &str) -> String {
+    format!("  <responseProcessing template=\"{}\"/>\n", template)
+}
+
+/// The assessmentItem of one question.
+fn item(question: &Question) -> String {
+    let (declaration, body, processing) = match &question.kind {
+        QuestionKind::FreeText { matching } => {
+            let accepted = match matching {
+                MatchMode::Exact => None,
+                MatchMode::Alternatives { accepted } => Some(accepted.as_slice()),
+                _ => Some(&[][..]),
+            };
+
+            match accepted {
+                None => (
+                    response_declaration("single", "string", &values([&question.answer]), ""),
+                    text_entry(question),
+                    template_processing(MATCH_CORRECT),
+                ),
+                Some(accepted) => {
+                    let entries = std::iter::once(&question.answer)
+                        .chain(accepted)
+                        .map(|value| {
+                            format!(
+                                "      <mapEntry mapKey=\"{}\" mappedValue=\"1\" \
+                                 caseSensitive=\"false\"/>\n",
+                                escape(value)
+                            )
+                        })
+                        .collect::<String>();
+
+                    (
+                        response_declaration(
+                            "single",
+                            "string",
+                            &values([&question.answer]),
+                            &format!(
+                                "    <mapping defaultValue=\"0\">\n{}    </mapping>\n",
+                                entries
+                            ),
+                        ),
+                        text_entry(question),
+                        template_processing(MAP_RESPONSE),
+                    )
+                }
+            }
+        }
+        QuestionKind::MultipleChoice {
+            correct_option,
+            options,
+        } => {
+            let (declaration, body) = choice_item(question, options, &[*correct_option]);
+
+            (declaration, body, template_processing(MATCH_CORRECT))
+        }
+        QuestionKind::MultipleSelect {
+            correct_options,
+            options,
+        } => {
+            let (declaration, body) = choice_item(question, options, correct_options);
+
+            (declaration, body, template_processing(MATCH_CORRECT))
+        }
+        QuestionKind::TrueFalse { correct } => {
+            let options = ["True".to_string(), "False".to_string()];
+
+            let (declaration, body) =
+                choice_item(question, &options, &[if *correct { 0 } else { 1 }]);
+
+            (declaration, body, template_processing(MATCH_CORRECT))
+        }
+        QuestionKind::Numeric { tolerance, value } => (
+            response_declaration("single", "float", &values([value]), ""),
+            text_entry(question),
+            tolerance_processing(*tolerance),
+        ),
+        QuestionKind::Ordering { items } => (
+            response_declaration(
+                "ordered",
+                "identifier",
+                &values((0..items.len()).map(choice_identifier)),
+                "",
+            ),
+            format!(
+                "    <orderInteraction responseIdentifier=\"{}\" shuffle=\"true\">\n      \
+                 <prompt>{}</prompt>\n{}    </orderInteraction>\n",
+                RESPONSE,
+                escape(&question.question),
+                simple_choices(items)
+            ),
+            template_processing(MATCH_CORRECT),
+        ),
+    };
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <assessmentItem xmlns=\"http://www.imsglobal.org/xsd/imsqti_v2p1\" \
+         identifier=\"{}\" title=\"{}\" adaptive=\"false\" timeDependent=\"false\">\n\
+         {}  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\"/>\n  \
+         <itemBody>\n{}  </itemBody>\n{}</assessmentItem>\n",
+        item_identifier(question),
+        escape(&question.question),
+        declaration,
+        body,
+        processing
+    )
+}
+
+fn item_path(question: &Question) -> String {
+    format!("items/{}.xml", item_identifier(question))
+}
+
+fn manifest(quiz_template: &QuizTemplate, questions: &[Question]) -> String {
+    let resources = questions
+        .iter()
+        .map(|question| {
+            format!(
+                "    <resource identifier=\"{}\" type=\"{}\" href=\"{2}\">\n      \
+                 <file href=\"{2}\"/>\n    </resource>\n",
+                item_identifier(question),
+                ITEM_RESOURCE_TYPE,
+                item_path(question)
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <manifest xmlns=\"http://www.imsglobal.org/xsd/imscp_v1p1\" \
+         xmlns:imsmd=\"http://ltsc.ieee.org/xsd/LOM\" identifier=\"M{}\">\n  \
+         <metadata>\n    <schema>QTIv2.1 Package</schema>\n    \
+         <schemaversion>1.0.0</schemaversion>\n    <imsmd:lom><imsmd:general><imsmd:title>\
+         <imsmd:string>{}</imsmd:string></imsmd:title></imsmd:general></imsmd:lom>\n  \
+         </metadata>\n  <organizations/>\n  <resources>\n{}  </resources>\n</manifest>\n",
+        quiz_template.id,
+        escape(&quiz_template.name),
+        resources
+    )
+}
+
+/// A zipped QTI 2.1 package of a quiz template and its questions.
+pub fn export(quiz_template: &QuizTemplate, questions: &[Question]) -> ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let options = SimpleFileOptions::default();
+
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(manifest(quiz_template, questions).as_bytes())?;
+
+    for question in questions {
+        zip.start_file(item_path(question), options)?;
+        zip.write_all(item(question).as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// Just enough of an XML document to read packages. Names have their namespace prefix removed.
+#[derive(Debug)]
+struct Element {
+    attributes: HashMap<String, String>,
+    children: Vec<Node>,
+    /// 1-based line the start tag ends on.
+    line: usize,
+    name: String,
+}
+
+impl Element {
+    fn new(start: &BytesStart, line: usize) -> Result<Self, String> {
+        let mut attributes = HashMap::new();
+
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|e| e.to_string())?;
+
+            attributes.insert(
+                String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+                attribute
+                    .unescape_value()
+                    .map_err(|e| e.to_string())?
+                    .into_owned(),
+            );
+        }
+
+        Ok(Self {
+            attributes,
+            children: vec![],
+            line,
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Every element below this one, in document order.
+    fn descendants(&self) -> Vec<&Element> {
+        self.elements()
+            .flat_map(|element| std::iter::once(element).chain(element.descendants()))
+            .collect()
+    }
+
+    fn descendant(&self, name: &str) -> Option<&Element> {
+        self.descendants()
+            .into_iter()
+            .find(|element| element.name == name)
+    }
+
+    fn collect_text(&self, text: &mut String, skip_interactions: bool) {
+        for node in &self.children {
+            match node {
+                Node::Text(value) => {
+                    text.push(' ');
+                    text.push_str(value);
+                }
+                Node::Element(element) if skip_interactions && is_interaction(&element.name) => {
+                    if let Some(prompt) = element.child("prompt") {
+                        prompt.collect_text(text, false);
+                    }
+                }
+                Node::Element(element) => element.collect_text(text, skip_interactions),
+            }
+        }
+    }
+
+    /// Text content with runs of whitespace collapsed.
+    fn text(&self) -> String {
+        let mut text = String::new();
+
+        self.collect_text(&mut text, false);
+
+        collapse_whitespace(&text)
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_interaction(name: &str) -> bool {
+    name.ends_with("Interaction")
+}
+
+fn line_at(xml: &str, position: u64) -> usize {
+    let position = (position as usize).min(xml.len());
+
+    xml.as_bytes()[..position]
+        .iter()
+        .filter(|byte| **byte == b'\n')
+        .count()
+        + 1
+}
+
+/// Reads the root element of a document, or the line of the first problem in it.
+fn parse_xml(xml: &str) -> Result<Element, ImportError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut stack: Vec<Element> = vec![];
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| ImportError::new(line_at(xml, reader.error_position()), e.to_string()))?;
+
+        let line = line_at(xml, reader.buffer_position());
+        let invalid = |message: String| ImportError::new(line, message);
+
+        let node = match event {
+            Event::Start(start) => {
+                stack.push(Element::new(&start, line).map_err(invalid)?);
+                continue;
+            }
+            Event::Empty(start) => Node::Element(Element::new(&start, line).map_err(invalid)?),
+            Event::End(_) => match stack.pop() {
+                Some(element) => Node::Element(element),
+                None => return Err(invalid("Unexpected end tag".to_string())),
+            },
+            Event::Text(text) => Node::Text(
+                text.unescape()
+                    .map_err(|e| invalid(e.to_string()))?
+                    .into_owned(),
+            ),
+            Event::CData(data) => Node::Text(String::from_utf8_lossy(&data).into_owned()),
+            Event::Eof => return Err(invalid("Document has no root element".to_string())),
+            _ => continue,
+        };
+
+        match (stack.last_mut(), node) {
+            (Some(parent), node) => parent.children.push(node),
+            (None, Node::Element(root)) => return Ok(root),
+            (None, Node::Text(_)) => {
+                return Err(invalid("Text outside the root element".to_string()))
+            }
+        }
+    }
+}
+
+fn read_file<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<String, String> {
+    let file = archive
+        .by_name(path)
+        .map_err(|_| format!("Package has no {}", path))?;
+
+    if file.size() > MAX_FILE_LENGTH {
+        return Err(format!("{} is larger than {} bytes", path, MAX_FILE_LENGTH));
+    }
+
+    let mut contents = String::new();
+
+    file.take(MAX_FILE_LENGTH)
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("Could not read {}: {}", path, e))?;
+
+    Ok(contents)
+}
+
+/// Correct response values, in order.
+fn correct_values(declaration: &Element) -> Vec<String> {
+    declaration
+        .child("correctResponse")
+        .map(|correct| {
+            correct
+                .elements()
+                .filter(|element| element.name == "value")
+                .map(Element::text)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Choice identifiers and texts of a choice or order interaction.
+fn choices(interaction: &Element) -> Vec<(String, String)> {
+    interaction
+        .descendants()
+        .into_iter()
+        .filter(|element| element.name == "simpleChoice")
+        .map(|choice| {
+            (
+                choice
+                    .attribute("identifier")
+                    .unwrap_or_default()
+                    .to_string(),
+                choice.text(),
+            )
+        })
+        .collect()
+}
+
+fn choice_index(choices: &[(String, String)], identifier: &str) -> Result<usize, String> {
+    choices
+        .iter()
+        .position(|(choice, _)| choice == identifier)
+        .ok_or_else(|| format!("Correct response {} is not a choice", identifier))
+}
+
+fn read_choice(
+    interaction: &Element,
+    declaration: &Element,
+) -> Result<(String, QuestionKind), String> {
+    let choices = choices(interaction);
+
+    let correct = correct_values(declaration)
+        .iter()
+        .map(|identifier| choice_index(&choices, identifier))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let texts = choices
+        .iter()
+        .map(|(_, text)| text.clone())
+        .collect::<Vec<_>>();
+
+    let answer = correct
+        .iter()
+        .map(|index| texts[*index].clone())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if declaration.attribute("cardinality") == Some("multiple") {
+        return Ok((
+            answer,
+            QuestionKind::MultipleSelect {
+                correct_options: correct,
+                options: texts,
+            },
+        ));
+    }
+
+    let correct_option = match correct[..] {
+        [correct_option] => correct_option,
+        _ => return Err("Single choice items need exactly one correct response".to_string()),
+    };
+
+    let lowercase = texts
+        .iter()
+        .map(|text| text.to_lowercase())
+        .collect::<Vec<_>>();
+
+    if lowercase == ["true", "false"] || lowercase == ["false", "true"] {
+        let correct = lowercase[correct_option] == "true";
+
+        return Ok((
+            if correct { "True" } else { "False" }.to_string(),
+            QuestionKind::TrueFalse { correct },
+        ));
+    }
+
+    Ok((
+        answer,
+        QuestionKind::MultipleChoice {
+            correct_option,
+            options: texts,
+        },
+    ))
+}
+
+fn read_order(
+    interaction: &Element,
+    declaration: &Element,
+) -> Result<(String, QuestionKind), String> {
+    let choices = choices(interaction);
+
+    let items = correct_values(declaration)
+        .iter()
+        .map(|identifier| Ok(choices[choice_index(&choices, identifier)?].1.clone()))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if items.len() != choices.len() {
+        return Err("Correct order must include every choice once".to_string());
+    }
+
+    Ok((items.join(", "), QuestionKind::Ordering { items }))
+}
+
+fn read_text_entry(
+    item: &Element,
+    declaration: &Element,
+) -> Result<(String, QuestionKind), String> {
+    let answer = correct_values(declaration).into_iter().next();
+
+    if let Some("float" | "integer") = declaration.attribute("baseType") {
+        let answer = answer.ok_or("Numeric item has no correct response")?;
+
+        let value = answer
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid number: {}", answer))?;
+
+        let tolerance = item
+            .child("responseProcessing")
+            .and_then(|processing| processing.descendant("equal"))
+            .filter(|equal| equal.attribute("toleranceMode") == Some("absolute"))
+            .and_then(|equal| equal.attribute("tolerance"))
+            .and_then(|tolerance| tolerance.split_whitespace().next())
+            .map(|tolerance| {
+                tolerance
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid tolerance: {}", tolerance))
+            })
+            .transpose()?
+            .unwrap_or(0.0);
+
+        return Ok((
+            value.to_string(),
+            QuestionKind::Numeric { tolerance, value },
+        ));
+    }
+
+    let accepted = declaration.child("mapping").map(|mapping| {
+        mapping
+            .elements()
+            .filter(|entry| entry.name == "mapEntry")
+            .filter(|entry| {
+                entry
+                    .attribute("mappedValue")
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .is_some_and(|value| value > 0.0)
+            })
+            .filter_map(|entry| entry.attribute("mapKey"))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    });
+
+    let answer = answer
+        .or_else(|| {
+            accepted
+                .as_ref()
+                .and_then(|accepted| accepted.first().cloned())
+        })
+        .ok_or("Text entry item has no correct response")?;
+
+    let matching = match accepted {
+        None => MatchMode::Exact,
+        Some(accepted) => {
+            let accepted = accepted
+                .into_iter()
+                .filter(|value| *value != answer)
+                .collect::<Vec<_>>();
+
+            match accepted.is_empty() {
+                true => MatchMode::CaseInsensitive,
+                false => MatchMode::Alternatives { accepted },
+            }
+        }
+    };
+
+    Ok((answer, QuestionKind::FreeText { matching }))
+}
+
+/// Reads one assessmentItem into a question.
+fn read_item(xml: &str) -> Result<Question, ImportError> {
+    let item = parse_xml(xml)?;
+
+    if item.name != "assessmentItem" {
+        return Err(ImportError::new(item.line, "Not an assessmentItem"));
+    }
+
+    let body = item
+        .child("itemBody")
+        .ok_or_else(|| ImportError::new(item.line, "Item has no itemBody"))?;
+
+    let interactions = body
+        .descendants()
+        .into_iter()
+        .filter(|element| is_interaction(&element.name))
+        .collect::<Vec<_>>();
+
+    let interaction = match interactions[..] {
+        [] => return Err(ImportError::new(body.line, "Item has no interaction")),
+        [interaction] => interaction,
+        [_, second, ..] => {
+            return Err(ImportError::new(
+                second.line,
+                "Items with more than one interaction are not supported",
+            ))
+        }
+    };
+
+    let invalid = |message: String| ImportError::new(interaction.line, message);
+
+    if !SUPPORTED_INTERACTIONS.contains(&interaction.name.as_str()) {
+        return Err(invalid(format!(
+            "Unsupported interaction: {}",
+            interaction.name
+        )));
+    }
+
+    let response = interaction
+        .attribute("responseIdentifier")
+        .unwrap_or_default();
+
+    let declaration = item
+        .elements()
+        .find(|element| {
+            element.name == "responseDeclaration"
+                && element.attribute("identifier") == Some(response)
+        })
+        .ok_or_else(|| invalid(format!("No responseDeclaration for {}", response)))?;
+
+    let (answer, kind) = match interaction.name.as_str() {
+        "choiceInteraction" => read_choice(interaction, declaration),
+        "orderInteraction" => read_order(interaction, declaration),
+        _ => read_text_entry(&item, declaration),
+    }
+    .map_err(invalid)?;
+
+    kind.validate().map_err(|e| invalid(e.to_string()))?;
+
+    let mut question = String::new();
+    body.collect_text(&mut question, true);
+
+    let question = collapse_whitespace(&question);
+
+    if question.is_empty() {
+        return Err(ImportError::new(body.line, "Question text is empty"));
+    }
+
+    Ok(Question {
+        id: ObjectId::new().to_string(),
+        answer,
+        answer_format: ContentFormat::Plain,
+        attachments: vec![],
+        explanation: None,
+        hints: vec![],
+        kind,
+        partial_credit: PartialCredit::default(),
+        points: 1.0,
+        position: 0,
+        question,
+        question_format: ContentFormat::Plain,
+        quiz_template_id: String::new(),
+    })
+}
+
+/// Reads the items listed in the manifest of a zipped package. Items that cannot be read are
+/// reported against their file and left out. Fails only when the package itself is unreadable.
+pub fn parse(package: &[u8]) -> Result<Import, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(package)).map_err(|e| format!("Invalid zip file: {}", e))?;
+
+    let manifest = parse_xml(&read_file(&mut archive, MANIFEST)?)
+        .map_err(|e| format!("{}, line {}: {}", MANIFEST, e.line, e.message))?;
+
+    let mut import = Import {
+        name: manifest
+            .child("metadata")
+            .and_then(|metadata| metadata.descendant("title"))
+            .map(Element::text)
+            .filter(|name| !name.is_empty()),
+        ..Default::default()
+    };
+
+    let resources = manifest
+        .child("resources")
+        .map(|resources| {
+            resources
+                .elements()
+                .filter(|resource| resource.name == "resource")
+                .filter(|resource| {
+                    resource
+                        .attribute("type")
+                        .is_some_and(|kind| kind.starts_with("imsqti_item_xmlv2p"))
+                })
+                .filter_map(|resource| resource.attribute("href"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for href in resources {
+        let question = read_file(&mut archive, href)
+            .map_err(|message| ImportError::new(1, message))
+            .and_then(|xml| read_item(&xml));
+
+        match question {
+            Ok(question) => import.questions.push(question),
+            Err(error) => import
+                .errors
+                .push(ImportError::in_file(href, error.line, error.message)),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Scoring;
+
+    fn quiz_template() -> (QuizTemplate, Vec<Question>) {
+        let quiz_template = QuizTemplate {
+            id: ObjectId::new().to_string(),
+            name: "Science & maths".to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: None,
+            scoring: Scoring::default(),
+            time_limit_seconds: None,
+            user_id: ObjectId::new().to_string(),
+        };
+
+        let question = |question: &str, answer: &str, kind: QuestionKind| {
+            Question::fixture(&quiz_template.id, question, answer, kind)
+        };
+
+        let questions = vec![
+            question(
+                "Chemical symbol of <gold>?",
+                "Au",
+                QuestionKind::FreeText {
+                    matching: MatchMode::Exact,
+                },
+            ),
+            question(
+                "Largest planet?",
+                "Jupiter",
+                QuestionKind::FreeText {
+                    matching: MatchMode::Alternatives {
+                        accepted: vec!["Planet Jupiter".to_string()],
+                    },
+                },
+            ),
+            question(
+                "Noble gas?",
+                "Neon",
+                QuestionKind::MultipleChoice {
+                    correct_option: 1,
+                    options: vec!["Oxygen".to_string(), "Neon".to_string()],
+                },
+            ),
+            question(
+                "Prime numbers?",
+                "2, 3",
+                QuestionKind::MultipleSelect {
+                    correct_options: vec![0, 1],
+                    options: vec!["2".to_string(), "3".to_string(), "4".to_string()],
+                },
+            ),
+            question(
+                "Water boils at 100 °C at sea level.",
+                "True",
+                QuestionKind::TrueFalse { correct: true },
+            ),
+            question(
+                "Acceleration due to gravity?",
+                "9.8",
+                QuestionKind::Numeric {
+                    tolerance: 0.1,
+                    value: 9.8,
+                },
+            ),
+            question(
+                "Order by size",
+                "Atom, Cell, Planet",
+                QuestionKind::Ordering {
+                    items: vec!["Atom".to_string(), "Cell".to_string(), "Planet".to_string()],
+                },
+            ),
+        ];
+
+        (quiz_template, questions)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (quiz_template, questions) = quiz_template();
+
+        let package = export(&quiz_template, &questions).unwrap();
+
+        let import = parse(&package).unwrap();
+
+        assert_eq!(Vec::<ImportError>::new(), import.errors);
+        assert_eq!(Some(quiz_template.name), import.name);
+        assert_eq!(
+            questions
+                .iter()
+                .map(|question| (&question.question, &question.answer, &question.kind))
+                .collect::<Vec<_>>(),
+            import
+                .questions
+                .iter()
+                .map(|question| (&question.question, &question.answer, &question.kind))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lossy_matching_is_case_insensitive() {
+        let (quiz_template, mut questions) = quiz_template();
+
+        questions.truncate(1);
+        questions[0].kind = QuestionKind::FreeText {
+            matching: MatchMode::Fuzzy { max_distance: 1 },
+        };
+
+        let import = parse(&export(&quiz_template, &questions).unwrap()).unwrap();
+
+        assert_eq!(
+            QuestionKind::FreeText {
+                matching: MatchMode::CaseInsensitive
+            },
+            import.questions[0].kind
+        );
+    }
+
+    fn package(items: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let options = SimpleFileOptions::default();
+
+        let resources = items
+            .iter()
+            .map(|(href, _)| {
+                format!(
+                    "<resource identifier=\"R\" type=\"imsqti_item_xmlv2p1\" href=\"{}\"/>",
+                    href
+                )
+            })
+            .collect::<String>();
+
+        zip.start_file(MANIFEST, options).unwrap();
+        write!(
+            zip,
+            "<manifest><resources>{}</resources></manifest>",
+            resources
+        )
+        .unwrap();
+
+        for (href, xml) in items {
+            zip.start_file(*href, options).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_reports_unsupported_items() {
+        let matching = r#"<assessmentItem>
+  <responseDeclaration identifier="RESPONSE" cardinality="multiple" baseType="directedPair"/>
+  <itemBody>
+    <matchInteraction responseIdentifier="RESPONSE">
+      <prompt>Match the capitals</prompt>
+    </matchInteraction>
+  </itemBody>
+</assessmentItem>"#;
+
+        let choice = r#"<assessmentItem>
+  <responseDeclaration identifier="RESPONSE" cardinality="single" baseType="identifier">
+    <correctResponse><value>B</value></correctResponse>
+  </responseDeclaration>
+  <itemBody>
+    <p>Pick <b>one</b></p>
+    <choiceInteraction responseIdentifier="RESPONSE" maxChoices="1">
+      <simpleChoice identifier="A">First</simpleChoice>
+      <simpleChoice identifier="B">Second</simpleChoice>
+    </choiceInteraction>
+  </itemBody>
+</assessmentItem>"#;
+
+        let import = parse(&package(&[
+            ("match.xml", matching),
+            ("choice.xml", choice),
+            ("empty.xml", ""),
+            (
+                "broken.xml",
+                "<assessmentItem>\n<itemBody>\n</assessmentItem>",
+            ),
+        ]))
+        .unwrap();
+
+        assert_eq!(None, import.name);
+        assert_eq!(1, import.questions.len());
+        assert_eq!("Pick one", import.questions[0].question);
+        assert_eq!("Second", import.questions[0].answer);
+        assert_eq!(
+            ImportError::in_file("match.xml", 4, "Unsupported interaction: matchInteraction"),
+            import.errors[0]
+        );
+        assert_eq!(Some("empty.xml"), import.errors[1].file.as_deref());
+        assert_eq!(Some("broken.xml"), import.errors[2].file.as_deref());
+        assert_eq!(3, import.errors.len());
+    }
+
+    #[test]
+    fn test_invalid_package() {
+        assert!(parse(b"not a zip").is_err());
+
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("item.xml", SimpleFileOptions::default())
+            .unwrap();
+
+        assert_eq!(
+            Err(format!("Package has no {}", MANIFEST)),
+            parse(&zip.finish().unwrap().into_inner()).map(|import| import.questions.len())
+        );
+    }
+}
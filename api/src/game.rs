@@ -0,0 +1,702 @@
+use std::{collections::HashMap, fmt::Display};
+
+use mongodb::bson::DateTime;
+use rand::{seq::IteratorRandom, thread_rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use redis::{aio::MultiplexedConnection, AsyncCommands, RedisError};
+use rocket::futures::{future::ready, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{new_option_key, OptionIds, Question, QuestionKind, Response};
+
+const CODE_CHOICES: &str = "ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+const CODE_LENGTH: usize = 6;
+
+/// Games are dropped from Redis once nobody has touched them for this long.
+const GAME_TTL: u64 = 60 * 60 * 6;
+
+/// Points for a correct answer given the moment the question opened. Half of it decays over
+/// the countdown.
+const MAX_POINTS: u32 = 1000;
+
+pub const DEFAULT_SECONDS_PER_QUESTION: u32 = 20;
+
+pub const MAX_SECONDS_PER_QUESTION: u32 = 300;
+
+#[derive(Debug)]
+pub enum GameError {
+    Build,
+    Invalid(&'static str),
+    Json(serde_json::Error),
+    NotFound,
+    Rand(rand::Error),
+    Redis(RedisError),
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Build => write!(f, "Unable to create game"),
+            Self::Invalid(message) => write!(f, "{}", message),
+            Self::Json(error) => error.fmt(f),
+            Self::NotFound => write!(f, "Game not found"),
+            Self::Rand(error) => error.fmt(f),
+            Self::Redis(error) => error.fmt(f),
+        }
+    }
+}
+
+impl From<rand::Error> for GameError {
+    fn from(value: rand::Error) -> Self {
+        Self::Rand(value)
+    }
+}
+
+impl From<RedisError> for GameError {
+    fn from(value: RedisError) -> Self {
+        Self::Redis(value)
+    }
+}
+
+impl From<serde_json::Error> for GameError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+/// Times are milliseconds since the Unix epoch.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "phase")]
+pub enum GamePhase {
+    Lobby,
+    Question {
+        deadline: i64,
+        index: usize,
+        started_at: i64,
+    },
+    Reveal {
+        index: usize,
+    },
+    Finished,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Game {
+    pub code: String,
+    pub host_id: String,
+    /// Keys the [`OptionIds`] players see. Never published.
+    #[serde(default)]
+    pub option_key: String,
+    pub phase: GamePhase,
+    pub question_ids: Vec<String>,
+    pub quiz_template_id: String,
+    pub seconds_per_question: u32,
+}
+
+impl Game {
+    /// The ids players see for the options of a question.
+    pub fn option_ids(&self, question: &Question) -> OptionIds {
+        OptionIds::new(
+            &self.option_key,
+            &question.id,
+            question.kind.options().len(),
+        )
+    }
+
+    /// Opens the question after the current one. Answers must be revealed before moving on.
+    pub fn next_question(&mut self, now: i64) -> Result<usize, GameError> {
+        let index = match self.phase {
+            GamePhase::Lobby => 0,
+            GamePhase::Reveal { index } => index + 1,
+            GamePhase::Question { .. } => {
+                return Err(GameError::Invalid("Reveal the answer first"))
+            }
+            GamePhase::Finished => return Err(GameError::Invalid("The game has finished")),
+        };
+
+        if index >= self.question_ids.len() {
+            return Err(GameError::Invalid("There are no more questions"));
+        }
+
+        self.phase = GamePhase::Question {
+            deadline: now + i64::from(self.seconds_per_question) * 1000,
+            index,
+            started_at: now,
+        };
+
+        Ok(index)
+    }
+
+    /// Closes the open question so its answers can be revealed.
+    pub fn reveal(&mut self) -> Result<usize, GameError> {
+        let GamePhase::Question { index, .. } = self.phase else {
+            return Err(GameError::Invalid("No question is open"));
+        };
+
+        self.phase = GamePhase::Reveal { index };
+
+        Ok(index)
+    }
+
+    /// The index of the question open for answers at `now`.
+    pub fn open_question(&self, now: i64) -> Result<usize, GameError> {
+        match self.phase {
+            GamePhase::Question {
+                deadline, index, ..
+            } if now <= deadline => Ok(index),
+            GamePhase::Question { .. } => Err(GameError::Invalid("Time is up")),
+            _ => Err(GameError::Invalid("No question is open")),
+        }
+    }
+
+    /// Points for answering the open question correctly at `answered_at`.
+    pub fn points(&self, answered_at: i64) -> u32 {
+        let GamePhase::Question {
+            deadline,
+            started_at,
+            ..
+        } = self.phase
+        else {
+            return 0;
+        };
+
+        let duration = (deadline - started_at).max(1);
+        let remaining = (deadline - answered_at).clamp(0, duration);
+
+        let bonus = u64::from(MAX_POINTS / 2) * remaining as u64 / duration as u64;
+
+        MAX_POINTS / 2 + bonus as u32
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Player {
+    pub score: u32,
+    pub user_id: String,
+    pub username: String,
+}
+
+/// Highest score first, ties broken by username.
+pub fn leaderboard(mut players: Vec<Player>) -> Vec<Player> {
+    players.sort_by(|a, b| b.score.cmp(&a.score).then(a.username.cmp(&b.username)));
+
+    players
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlayerAnswer {
+    pub answered_at: i64,
+    pub response: Response,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveQuestionKind {
+    FreeText,
+    MultipleChoice,
+    MultipleSelect,
+    TrueFalse,
+    Numeric,
+    Ordering,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LiveOption {
+    pub id: usize,
+    pub text: String,
+}
+
+/// A question as shown to players: nothing that gives the answer away. Responses refer to
+/// options by their opaque ids, and ordering items are listed in id order.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LiveQuestion {
+    pub count: usize,
+    pub deadline: i64,
+    pub index: usize,
+    pub kind: LiveQuestionKind,
+    pub options: Vec<LiveOption>,
+    pub question: String,
+    /// The question rendered to sanitised HTML.
+    #[serde(default)]
+    pub question_html: String,
+    pub question_id: String,
+}
+
+impl LiveQuestion {
+    pub fn new(
+        question: &Question,
+        ids: &OptionIds,
+        index: usize,
+        count: usize,
+        deadline: i64,
+    ) -> Self {
+        let order = match &question.kind {
+            QuestionKind::Ordering { .. } => ids.shuffled(),
+            kind => (0..kind.options().len()).collect(),
+        };
+
+        let options = order
+            .into_iter()
+            .map(|option| LiveOption {
+                id: ids.id(option),
+                text: question.kind.options()[option].clone(),
+            })
+            .collect();
+
+        let kind = match &question.kind {
+            QuestionKind::FreeText { .. } => LiveQuestionKind::FreeText,
+            QuestionKind::MultipleChoice { .. } => LiveQuestionKind::MultipleChoice,
+            QuestionKind::MultipleSelect { .. } => LiveQuestionKind::MultipleSelect,
+            QuestionKind::TrueFalse { .. } => LiveQuestionKind::TrueFalse,
+            QuestionKind::Numeric { .. } => LiveQuestionKind::Numeric,
+            QuestionKind::Ordering { .. } => LiveQuestionKind::Ordering,
+        };
+
+        Self {
+            count,
+            deadline,
+            index,
+            kind,
+            options,
+            question: question.question.clone(),
+            question_html: question.question_format.render(&question.question),
+            question_id: question.id.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PlayerResult {
+    pub correct: bool,
+    pub points: u32,
+    pub user_id: String,
+}
+
+/// Published to everyone in a game as it progresses.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+pub enum GameEvent {
+    PlayerJoined {
+        player: Player,
+    },
+    QuestionStarted {
+        question: LiveQuestion,
+    },
+    AnswerRevealed {
+        correct_response: Response,
+        index: usize,
+        question_id: String,
+        results: Vec<PlayerResult>,
+    },
+    LeaderboardUpdated {
+        players: Vec<Player>,
+    },
+    GameEnded {
+        players: Vec<Player>,
+    },
+}
+
+fn game_key(code: &str) -> String {
+    format!("game:{}", code)
+}
+
+fn players_key(code: &str) -> String {
+    format!("game:{}:players", code)
+}
+
+fn answers_key(code: &str, index: usize) -> String {
+    format!("game:{}:answers:{}", code, index)
+}
+
+fn events_channel(code: &str) -> String {
+    format!("game:{}:events", code)
+}
+
+/// Replaces the game in `KEYS[1]` with `ARGV[2]` only while its phase still has every field of
+/// `ARGV[1]` and no others. Returns 1 when it was replaced.
+const SAVE_IF_PHASE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if not current then
+    return 0
+end
+local phase = cjson.decode(current).phase
+local expected = cjson.decode(ARGV[1])
+for key, value in pairs(expected) do
+    if phase[key] ~= value then
+        return 0
+    end
+end
+for key in pairs(phase) do
+    if expected[key] == nil then
+        return 0
+    end
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+return 1
+"#;
+
+/// Live game state in Redis. The game itself is only written by its host; players and answers
+/// live in hashes of their own so that concurrent joins and answers never overwrite each other.
+pub struct Games {
+    client: redis::Client,
+    connection: MultiplexedConnection,
+}
+
+impl Games {
+    pub fn new(client: redis::Client, connection: MultiplexedConnection) -> Self {
+        Self { client, connection }
+    }
+
+    /// Multiplexed connections are cheap handles onto one shared socket.
+    fn connection(&self) -> MultiplexedConnection {
+        self.connection.clone()
+    }
+
+    pub async fn create(
+        &self,
+        host_id: &str,
+        quiz_template_id: &str,
+        question_ids: Vec<String>,
+        seconds_per_question: u32,
+    ) -> Result<Game, GameError> {
+        let mut rng = ChaCha20Rng::from_rng(thread_rng())?;
+
+        for _ in 0..100 {
+            let code = (0..CODE_LENGTH)
+                .map(|_| CODE_CHOICES.chars().choose(&mut rng))
+                .collect::<Option<String>>()
+                .ok_or(GameError::Build)?;
+
+            let game = Game {
+                code: code.clone(),
+                host_id: host_id.to_string(),
+                option_key: new_option_key(),
+                phase: GamePhase::Lobby,
+                question_ids: question_ids.clone(),
+                quiz_template_id: quiz_template_id.to_string(),
+                seconds_per_question,
+            };
+
+            let created: bool = redis::cmd("SET")
+                .arg(game_key(&code))
+                .arg(serde_json::to_string(&game)?)
+                .arg("NX")
+                .arg("EX")
+                .arg(GAME_TTL)
+                .query_async::<_, Option<String>>(&mut self.connection())
+                .await?
+                .is_some();
+
+            if created {
+                return Ok(game);
+            }
+        }
+
+        eprintln!("Cannot find free game code");
+
+        Err(GameError::Build)
+    }
+
+    pub async fn read(&self, code: &str) -> Result<Game, GameError> {
+        let value: Option<String> = self.connection().get(game_key(code)).await?;
+
+        let value = value.ok_or(GameError::NotFound)?;
+
+        Ok(serde_json::from_str(&value)?)
+    }
+
+    pub async fn save(&self, game: &Game) -> Result<(), GameError> {
+        let _: () = self
+            .connection()
+            .set_ex(game_key(&game.code), serde_json::to_string(game)?, GAME_TTL)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Saves `game` unless its phase was changed since it was `expected`, so that two requests
+    /// racing each other cannot both move the game on. Returns whether it was saved.
+    pub async fn save_if_phase(
+        &self,
+        game: &Game,
+        expected: &GamePhase,
+    ) -> Result<bool, GameError> {
+        let saved: bool = redis::Script::new(SAVE_IF_PHASE_SCRIPT)
+            .key(game_key(&game.code))
+            .arg(serde_json::to_string(expected)?)
+            .arg(serde_json::to_string(game)?)
+            .arg(GAME_TTL)
+            .invoke_async(&mut self.connection())
+            .await?;
+
+        Ok(saved)
+    }
+
+    pub async fn players(&self, code: &str) -> Result<Vec<Player>, GameError> {
+        let values: HashMap<String, String> = self.connection().hgetall(players_key(code)).await?;
+
+        values
+            .values()
+            .map(|value| Ok(serde_json::from_str(value)?))
+            .collect()
+    }
+
+    pub async fn player(&self, code: &str, user_id: &str) -> Result<Option<Player>, GameError> {
+        let value: Option<String> = self.connection().hget(players_key(code), user_id).await?;
+
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+
+    /// Adds `player` unless they already joined. Returns whether they were added.
+    pub async fn add_player(&self, code: &str, player: &Player) -> Result<bool, GameError> {
+        let added: bool = self
+            .connection()
+            .hset_nx(
+                players_key(code),
+                &player.user_id,
+                serde_json::to_string(player)?,
+            )
+            .await?;
+
+        let _: () = self
+            .connection()
+            .expire(players_key(code), GAME_TTL as i64)
+            .await?;
+
+        Ok(added)
+    }
+
+    pub async fn save_players(&self, code: &str, players: &[Player]) -> Result<(), GameError> {
+        let values = players
+            .iter()
+            .map(|player| Ok((player.user_id.clone(), serde_json::to_string(player)?)))
+            .collect::<Result<Vec<_>, GameError>>()?;
+
+        if !values.is_empty() {
+            let _: () = self
+                .connection()
+                .hset_multiple(players_key(code), &values)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the first answer of a player to a question. Returns whether it was recorded.
+    pub async fn add_answer(
+        &self,
+        code: &str,
+        index: usize,
+        user_id: &str,
+        answer: &PlayerAnswer,
+    ) -> Result<bool, GameError> {
+        let key = answers_key(code, index);
+
+        let added: bool = self
+            .connection()
+            .hset_nx(&key, user_id, serde_json::to_string(answer)?)
+            .await?;
+
+        let _: () = self.connection().expire(&key, GAME_TTL as i64).await?;
+
+        Ok(added)
+    }
+
+    pub async fn answers(
+        &self,
+        code: &str,
+        index: usize,
+    ) -> Result<HashMap<String, PlayerAnswer>, GameError> {
+        let values: HashMap<String, String> =
+            self.connection().hgetall(answers_key(code, index)).await?;
+
+        values
+            .into_iter()
+            .map(|(user_id, value)| Ok((user_id, serde_json::from_str(&value)?)))
+            .collect()
+    }
+
+    pub async fn publish(&self, code: &str, event: &GameEvent) -> Result<(), GameError> {
+        let _: () = self
+            .connection()
+            .publish(events_channel(code), serde_json::to_string(event)?)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Events of a game from now on. The stream ends after [`GameEvent::GameEnded`].
+    pub async fn subscribe(
+        &self,
+        code: &str,
+    ) -> Result<impl Stream<Item = GameEvent> + Send + 'static, GameError> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+
+        pubsub.subscribe(events_channel(code)).await?;
+
+        let events = pubsub
+            .into_on_message()
+            .filter_map(|message| {
+                let event = message
+                    .get_payload::<String>()
+                    .ok()
+                    .and_then(|payload| serde_json::from_str::<GameEvent>(&payload).ok());
+
+                ready(event)
+            })
+            .scan(false, |ended, event| {
+                if *ended {
+                    return ready(None);
+                }
+
+                *ended = matches!(event, GameEvent::GameEnded { .. });
+
+                ready(Some(event))
+            });
+
+        Ok(events)
+    }
+}
+
+pub fn now() -> i64 {
+    DateTime::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(question_count: usize) -> Game {
+        Game {
+            code: "ABC234".to_string(),
+            host_id: "host".to_string(),
+            option_key: "key".to_string(),
+            phase: GamePhase::Lobby,
+            question_ids: (0..question_count).map(|i| i.to_string()).collect(),
+            quiz_template_id: "template".to_string(),
+            seconds_per_question: 10,
+        }
+    }
+
+    #[test]
+    fn test_next_question() {
+        let mut game = game(2);
+
+        assert_eq!(0, game.next_question(0).unwrap());
+        assert!(game.next_question(0).is_err());
+
+        game.phase = GamePhase::Reveal { index: 0 };
+
+        assert_eq!(1, game.next_question(0).unwrap());
+
+        game.phase = GamePhase::Reveal { index: 1 };
+
+        assert!(game.next_question(0).is_err());
+    }
+
+    #[test]
+    fn test_reveal_only_once() {
+        let mut game = game(2);
+
+        assert!(game.reveal().is_err());
+
+        game.next_question(0).unwrap();
+
+        assert_eq!(0, game.reveal().unwrap());
+        assert_eq!(GamePhase::Reveal { index: 0 }, game.phase);
+        assert!(game.reveal().is_err());
+    }
+
+    #[test]
+    fn test_open_question_and_points() {
+        let mut game = game(1);
+
+        assert!(game.open_question(0).is_err());
+
+        game.next_question(1_000).unwrap();
+
+        assert_eq!(0, game.open_question(11_000).unwrap());
+        assert!(game.open_question(11_001).is_err());
+
+        assert_eq!(MAX_POINTS, game.points(1_000));
+        assert_eq!(750, game.points(6_000));
+        assert_eq!(MAX_POINTS / 2, game.points(11_000));
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let player = |username: &str, score| Player {
+            score,
+            user_id: username.to_string(),
+            username: username.to_string(),
+        };
+
+        let players = leaderboard(vec![player("b", 10), player("c", 20), player("a", 10)]);
+
+        assert_eq!(
+            vec!["c", "a", "b"],
+            players
+                .iter()
+                .map(|player| player.username.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_live_question_hides_the_order() {
+        let items = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+
+        let question = Question {
+            id: "question".to_string(),
+            ..Question::fixture(
+                "template",
+                "Sort",
+                "",
+                QuestionKind::Ordering {
+                    items: items.clone(),
+                },
+            )
+        };
+
+        let game = game(1);
+        let ids = game.option_ids(&question);
+
+        let live = LiveQuestion::new(&question, &ids, 0, 1, 0);
+
+        assert_eq!(
+            (0..5).collect::<Vec<_>>(),
+            live.options
+                .iter()
+                .map(|option| option.id)
+                .collect::<Vec<_>>()
+        );
+
+        for option in &live.options {
+            assert_eq!(items[ids.index(option.id).unwrap()], option.text);
+        }
+
+        let correct = ids.to_ids(&question.correct_response());
+
+        assert_eq!(
+            question.correct_response(),
+            ids.to_indexes(&correct).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_event_round_trip() {
+        let event = GameEvent::AnswerRevealed {
+            correct_response: Response::Choice(1),
+            index: 0,
+            question_id: "question".to_string(),
+            results: vec![],
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+
+        assert_eq!(event, serde_json::from_str(&json).unwrap());
+    }
+}
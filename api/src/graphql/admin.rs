@@ -0,0 +1,57 @@
+use juniper::{graphql_object, FieldResult, ID};
+
+use super::{Role, User};
+
+use crate::authorization::{require_user, AuthorizationError};
+use crate::context::Context;
+use crate::models::{self, BaseModel};
+
+/// Only reachable through `Mutation.admin`, which checks the `MANAGE_USERS` permission.
+pub struct AdminMutation;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl AdminMutation {
+    async fn set_roles(
+        &self,
+        context: &Context,
+        user_id: ID,
+        roles: Vec<Role>,
+    ) -> FieldResult<User> {
+        let current_user = require_user(context)?;
+
+        let mut user = context
+            .users
+            .read_by_id(&user_id.to_string())
+            .await?
+            .ok_or(AuthorizationError::NotFound)?;
+
+        let mut roles = roles.into_iter().map(Role::to_model).collect::<Vec<_>>();
+
+        roles.sort_by_key(models::Role::as_str);
+        roles.dedup();
+
+        if user.id == current_user.id && !roles.contains(&models::Role::Admin) {
+            return Err("You cannot remove your own admin role")?;
+        }
+
+        user.roles = roles;
+
+        context.users.update_by_id(&user.id, &user).await?;
+
+        Ok(User::from_model(&user))
+    }
+}
+
+/// Only reachable through `Query.admin`, which checks the `MANAGE_USERS` permission.
+pub struct AdminQuery;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl AdminQuery {
+    async fn users(&self, context: &Context) -> FieldResult<Vec<User>> {
+        let models = context.users.read_all().await?;
+
+        Ok(models.iter().map(User::from_model).collect())
+    }
+}
@@ -0,0 +1,675 @@
+use juniper::{graphql_object, FieldResult, GraphQLEnum, GraphQLInputObject, GraphQLObject, ID};
+use mongodb::bson::DateTime;
+
+use super::{
+    question::{to_index, Question},
+    quiz_template::QuizTemplate,
+};
+
+use crate::authorization::{
+    has_permission, require_permission, require_user, AuthorizationError, Permission,
+};
+use crate::context::Context;
+use crate::models::{self, BaseModel, ModelError};
+use crate::sweep::submit_if_expired;
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum AttemptStatus {
+    InProgress,
+    Submitted,
+}
+
+impl AttemptStatus {
+    fn from_model(model: &models::AttemptStatus) -> Self {
+        match model {
+            models::AttemptStatus::InProgress => Self::InProgress,
+            models::AttemptStatus::Submitted => Self::Submitted,
+        }
+    }
+}
+
+/// Exactly one field is set, matching the kind of the question answered. Choices and orderings
+/// are option ids.
+#[derive(GraphQLObject)]
+pub struct Response {
+    boolean: Option<bool>,
+    choice: Option<i32>,
+    choices: Option<Vec<i32>>,
+    number: Option<f64>,
+    order: Option<Vec<i32>>,
+    text: Option<String>,
+}
+
+impl Response {
+    pub fn from_model(model: &models::Response) -> Self {
+        let mut response = Self {
+            boolean: None,
+            choice: None,
+            choices: None,
+            number: None,
+            order: None,
+            text: None,
+        };
+
+        match model {
+            models::Response::Boolean(boolean) => response.boolean = Some(*boolean),
+            models::Response::Choice(choice) => response.choice = Some(*choice as i32),
+            models::Response::Choices(choices) => {
+                response.choices = Some(choices.iter().map(|c| *c as i32).collect())
+            }
+            models::Response::Number(number) => response.number = Some(*number),
+            models::Response::Order(order) => {
+                response.order = Some(order.iter().map(|o| *o as i32).collect())
+            }
+            models::Response::Text(text) => response.text = Some(text.clone()),
+        }
+
+        response
+    }
+}
+
+/// Set exactly one field, matching the kind of the question being answered. Choices and
+/// orderings are option ids.
+#[derive(GraphQLInputObject)]
+pub struct ResponseInput {
+    boolean: Option<bool>,
+    choice: Option<i32>,
+    choices: Option<Vec<i32>>,
+    number: Option<f64>,
+    order: Option<Vec<i32>>,
+    text: Option<String>,
+}
+
+impl ResponseInput {
+    pub fn to_model(&self) -> Result<models::Response, ModelError> {
+        let to_indexes = |values: &Vec<i32>| {
+            values
+                .iter()
+                .map(|value| to_index(*value))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let mut responses = vec![];
+
+        if let Some(boolean) = self.boolean {
+            responses.push(models::Response::Boolean(boolean));
+        }
+
+        if let Some(choice) = self.choice {
+            responses.push(models::Response::Choice(to_index(choice)?));
+        }
+
+        if let Some(choices) = &self.choices {
+            responses.push(models::Response::Choices(to_indexes(choices)?));
+        }
+
+        if let Some(number) = self.number {
+            responses.push(models::Response::Number(number));
+        }
+
+        if let Some(order) = &self.order {
+            responses.push(models::Response::Order(to_indexes(order)?));
+        }
+
+        if let Some(text) = &self.text {
+            responses.push(models::Response::Text(text.clone()));
+        }
+
+        match responses.len() {
+            1 => Ok(responses.remove(0)),
+            _ => Err("Exactly one response field must be set")?,
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct AttemptAnswer {
+    question_id: ID,
+    response: Response,
+}
+
+#[derive(GraphQLObject)]
+pub struct QuestionResult {
+    /// How close the response came to an accepted answer, from 0 to 1.
+    confidence: f64,
+    correct: bool,
+    /// Share of the question's points earned before hint penalties, from 0 to 1.
+    credit: f64,
+    hints_revealed: i32,
+    /// The question's points.
+    max_score: f64,
+    /// Share of the question's points deducted for the hints revealed.
+    penalty: f64,
+    question_id: ID,
+    response: Option<Response>,
+    score: f64,
+}
+
+impl QuestionResult {
+    fn from_model(
+        attempt: &Attempt,
+        questions: &[models::Question],
+        model: &models::QuestionResult,
+    ) -> Self {
+        Self {
+            confidence: model.confidence,
+            correct: model.correct,
+            credit: model.credit,
+            hints_revealed: model.hints_revealed as i32,
+            max_score: model.max_score,
+            penalty: model.penalty,
+            question_id: model.question_id.clone().into(),
+            response: model
+                .response
+                .as_ref()
+                .map(|response| attempt.shown_response(questions, &model.question_id, response)),
+            score: model.score,
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct AttemptResult {
+    max_score: f64,
+    /// Whether the score reached the template's pass mark. Empty without a pass mark.
+    passed: Option<bool>,
+    /// The score as a percentage of `maxScore`.
+    percentage: f64,
+    question_results: Vec<QuestionResult>,
+    score: f64,
+}
+
+impl AttemptResult {
+    fn from_model(
+        attempt: &Attempt,
+        questions: &[models::Question],
+        model: &models::AttemptResult,
+    ) -> Self {
+        Self {
+            max_score: model.max_score,
+            passed: model.passed,
+            percentage: model.percentage,
+            question_results: model
+                .question_results
+                .iter()
+                .map(|result| QuestionResult::from_model(attempt, questions, result))
+                .collect(),
+            score: model.score,
+        }
+    }
+}
+
+/// An option of a multiple choice, multiple select or ordering question in the order it is
+/// shown. Responses refer to options by `id`, which says nothing about the correct answer.
+#[derive(GraphQLObject)]
+pub struct PaperOption {
+    id: i32,
+    text: String,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct AttemptQuestion {
+    /// When answers to this question stop being accepted. Empty while untimed or unopened.
+    deadline_at: Option<DateTime>,
+    /// How many hints the question has in all.
+    hint_count: i32,
+    /// The hints revealed so far, in order.
+    hints: Vec<String>,
+    id: ID,
+    /// What revealing the next hint would cost, as a share of the question's points.
+    next_hint_penalty: Option<f64>,
+    opened: bool,
+    options: Vec<PaperOption>,
+    /// Hidden until the question is opened when the quiz limits time per question.
+    question: Option<Question>,
+    /// Seconds left by the server clock, so clients need not trust their own.
+    remaining_seconds: Option<i32>,
+}
+
+impl AttemptQuestion {
+    fn from_model(attempt: &models::Attempt, position: usize, model: &models::Question) -> Self {
+        let opened = attempt.opened_at(&model.id).is_some();
+        let visible = opened || attempt.question_time_limit_seconds.is_none();
+
+        let texts = model.kind.options();
+        let ids = option_ids(attempt, model);
+
+        let order = match (&model.kind, &attempt.paper) {
+            (models::QuestionKind::Ordering { .. }, _) => ids.shuffled(),
+            (_, Some(paper)) => paper.option_order(position, model),
+            (_, None) => (0..texts.len()).collect(),
+        };
+
+        let deadline_at = attempt.question_deadline(&model.id);
+        let hints_revealed = attempt.hints_revealed(&model.id);
+
+        Self {
+            deadline_at,
+            hint_count: model.hints.len() as i32,
+            hints: model
+                .hints
+                .iter()
+                .take(hints_revealed)
+                .map(|hint| hint.text.clone())
+                .collect(),
+            id: model.id.clone().into(),
+            next_hint_penalty: model.hints.get(hints_revealed).map(|hint| hint.penalty),
+            opened,
+            options: match visible {
+                true => order
+                    .into_iter()
+                    .map(|index| PaperOption {
+                        id: ids.id(index) as i32,
+                        text: texts[index].clone(),
+                    })
+                    .collect(),
+                false => vec![],
+            },
+            question: visible.then(|| Question::for_attempt(attempt, model)),
+            remaining_seconds: deadline_at.map(remaining_seconds),
+        }
+    }
+}
+
+fn remaining_seconds(deadline: DateTime) -> i32 {
+    let millis = deadline.timestamp_millis() - DateTime::now().timestamp_millis();
+
+    (millis.max(0) / 1000) as i32
+}
+
+/// How to draw the questions of an attempt. Leaving every field empty gives every question in
+/// template order.
+#[derive(GraphQLInputObject)]
+pub struct PaperInput {
+    /// Questions drawn at random from the template. Every question when empty.
+    question_count: Option<i32>,
+    shuffle_options: Option<bool>,
+    shuffle_questions: Option<bool>,
+}
+
+impl PaperInput {
+    fn to_model(&self) -> Result<models::PaperOptions, ModelError> {
+        Ok(models::PaperOptions {
+            question_count: self.question_count.map(to_index).transpose()?,
+            shuffle_options: self.shuffle_options.unwrap_or(false),
+            shuffle_questions: self.shuffle_questions.unwrap_or(false),
+        })
+    }
+}
+
+pub struct Attempt {
+    model: models::Attempt,
+}
+
+impl Attempt {
+    fn from_model(model: &models::Attempt) -> Self {
+        Self {
+            model: model.clone(),
+        }
+    }
+
+    /// A stored response as the learner sees it, with option ids in place of indexes.
+    fn shown_response(
+        &self,
+        questions: &[models::Question],
+        question_id: &str,
+        response: &models::Response,
+    ) -> Response {
+        let count = questions
+            .iter()
+            .find(|question| question.id == question_id)
+            .map_or(0, |question| question.kind.options().len());
+
+        let ids = models::OptionIds::new(&self.model.option_key, question_id, count);
+
+        Response::from_model(&ids.to_ids(response))
+    }
+}
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl Attempt {
+    /// When the attempt runs out of time and is submitted automatically.
+    fn deadline_at(&self) -> Option<DateTime> {
+        self.model.deadline_at
+    }
+
+    async fn answers(&self, context: &Context) -> FieldResult<Vec<AttemptAnswer>> {
+        let questions = read_template_questions(context, &self.model.quiz_template_id).await?;
+
+        Ok(self
+            .model
+            .answers
+            .iter()
+            .map(|answer| AttemptAnswer {
+                question_id: answer.question_id.clone().into(),
+                response: self.shown_response(&questions, &answer.question_id, &answer.response),
+            })
+            .collect())
+    }
+
+    fn id(&self) -> ID {
+        self.model.id.clone().into()
+    }
+
+    /// The questions given in this attempt, in the order they are shown.
+    async fn questions(&self, context: &Context) -> FieldResult<Vec<AttemptQuestion>> {
+        let questions = read_template_questions(context, &self.model.quiz_template_id).await?;
+
+        Ok(self
+            .model
+            .questions(&questions)
+            .into_iter()
+            .enumerate()
+            .map(|(position, question)| {
+                AttemptQuestion::from_model(&self.model, position, question)
+            })
+            .collect())
+    }
+
+    async fn quiz_template(&self, context: &Context) -> FieldResult<Option<QuizTemplate>> {
+        let model = context
+            .quiz_templates
+            .read_by_id(&self.model.quiz_template_id)
+            .await?;
+
+        Ok(model.as_ref().map(QuizTemplate::from_model))
+    }
+
+    /// Seconds left on the whole attempt by the server clock.
+    fn remaining_seconds(&self) -> Option<i32> {
+        match self.model.status {
+            models::AttemptStatus::InProgress => self.model.deadline_at.map(remaining_seconds),
+            models::AttemptStatus::Submitted => None,
+        }
+    }
+
+    async fn result(&self, context: &Context) -> FieldResult<Option<AttemptResult>> {
+        let Some(result) = &self.model.result else {
+            return Ok(None);
+        };
+
+        let questions = read_template_questions(context, &self.model.quiz_template_id).await?;
+
+        Ok(Some(AttemptResult::from_model(self, &questions, result)))
+    }
+
+    /// Seed the questions were drawn with. Together with the template it rebuilds the paper.
+    fn seed(&self) -> Option<String> {
+        self.model
+            .paper
+            .as_ref()
+            .map(|paper| paper.seed.to_string())
+    }
+
+    fn started_at(&self) -> DateTime {
+        self.model.started_at
+    }
+
+    fn status(&self) -> AttemptStatus {
+        AttemptStatus::from_model(&self.model.status)
+    }
+
+    fn submitted_at(&self) -> Option<DateTime> {
+        self.model.submitted_at
+    }
+}
+
+/// Submits the attempts that ran out of time since they were last read.
+async fn submit_expired(context: &Context, attempts: &mut [models::Attempt]) -> FieldResult<()> {
+    for attempt in attempts {
+        submit_if_expired(&context.attempts, &context.questions, attempt).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads an attempt owned by the signed in user that can still be answered.
+async fn read_open_attempt(context: &Context, id: &ID) -> FieldResult<models::Attempt> {
+    let user = require_permission(context, Permission::TakeQuizzes)?;
+
+    let mut attempt = context
+        .attempts
+        .read_by_id(&id.to_string())
+        .await?
+        .filter(|attempt| attempt.user_id == user.id)
+        .ok_or(AuthorizationError::NotFound)?;
+
+    if submit_if_expired(&context.attempts, &context.questions, &mut attempt).await? {
+        return Err("Time is up and the attempt has been submitted")?;
+    }
+
+    if attempt.status != models::AttemptStatus::InProgress {
+        return Err("Attempt has already been submitted")?;
+    }
+
+    Ok(attempt)
+}
+
+/// Writes back an attempt read with [`read_open_attempt`], unless it was submitted meanwhile.
+async fn save_open_attempt(context: &Context, attempt: &models::Attempt) -> FieldResult<()> {
+    if !context.attempts.update_in_progress(attempt).await? {
+        return Err("Attempt has already been submitted")?;
+    }
+
+    Ok(())
+}
+
+/// Reads a question given in the attempt.
+async fn read_attempt_question(
+    context: &Context,
+    attempt: &models::Attempt,
+    question_id: &ID,
+) -> FieldResult<models::Question> {
+    let question = context
+        .questions
+        .read_by_id(&question_id.to_string())
+        .await?
+        .filter(|question| {
+            question.quiz_template_id == attempt.quiz_template_id
+                && attempt
+                    .paper
+                    .as_ref()
+                    .is_none_or(|paper| paper.question_ids.contains(&question.id))
+        })
+        .ok_or("Question is not part of this attempt")?;
+
+    Ok(question)
+}
+
+/// The ids the learner sees for the options of a question.
+fn option_ids(attempt: &models::Attempt, question: &models::Question) -> models::OptionIds {
+    models::OptionIds::new(
+        &attempt.option_key,
+        &question.id,
+        question.kind.options().len(),
+    )
+}
+
+/// Where a question appears on the attempt's paper, which decides how its options are shuffled.
+fn position_in_paper(attempt: &models::Attempt, question: &models::Question) -> usize {
+    match &attempt.paper {
+        Some(paper) => paper
+            .question_ids
+            .iter()
+            .position(|id| id == &question.id)
+            .unwrap_or_default(),
+        None => 0,
+    }
+}
+
+async fn read_template_questions(
+    context: &Context,
+    quiz_template_id: &str,
+) -> FieldResult<Vec<models::Question>> {
+    let questions = context
+        .questions
+        .read_by_quiz_template_id(quiz_template_id)
+        .await?;
+
+    Ok(questions)
+}
+
+pub struct AttemptMutation;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl AttemptMutation {
+    /// Answers a question, replacing an earlier answer unless the question's explanation has
+    /// been shown.
+    async fn answer(
+        &self,
+        context: &Context,
+        id: ID,
+        question_id: ID,
+        response: ResponseInput,
+    ) -> FieldResult<Attempt> {
+        let response = response.to_model()?;
+
+        let mut attempt = read_open_attempt(context, &id).await?;
+
+        let question = read_attempt_question(context, &attempt, &question_id).await?;
+
+        let response = option_ids(&attempt, &question).to_indexes(&response)?;
+
+        attempt.check_time(&question.id, DateTime::now())?;
+        attempt.check_answer_change(&question)?;
+
+        attempt.record_answer(&question.id, response);
+
+        save_open_attempt(context, &attempt).await?;
+
+        Ok(Attempt::from_model(&attempt))
+    }
+
+    /// Shows a question and, when the quiz limits time per question, starts its clock.
+    async fn open_question(
+        &self,
+        context: &Context,
+        id: ID,
+        question_id: ID,
+    ) -> FieldResult<AttemptQuestion> {
+        let mut attempt = read_open_attempt(context, &id).await?;
+
+        let question = read_attempt_question(context, &attempt, &question_id).await?;
+
+        let position = position_in_paper(&attempt, &question);
+
+        if attempt.opened_at(&question.id).is_none() {
+            attempt.open_question(&question.id, DateTime::now());
+
+            save_open_attempt(context, &attempt).await?;
+        }
+
+        Ok(AttemptQuestion::from_model(&attempt, position, &question))
+    }
+
+    /// Reveals the next hint of a question. Its penalty is deducted when the attempt is graded.
+    async fn reveal_hint(
+        &self,
+        context: &Context,
+        id: ID,
+        question_id: ID,
+    ) -> FieldResult<AttemptQuestion> {
+        let mut attempt = read_open_attempt(context, &id).await?;
+
+        let question = read_attempt_question(context, &attempt, &question_id).await?;
+
+        let now = DateTime::now();
+
+        attempt.check_time(&question.id, now)?;
+        attempt.reveal_hint(&question, now)?;
+
+        save_open_attempt(context, &attempt).await?;
+
+        Ok(AttemptQuestion::from_model(
+            &attempt,
+            position_in_paper(&attempt, &question),
+            &question,
+        ))
+    }
+
+    /// Starts an attempt. Without a `paper` every question is given in template order.
+    async fn start(
+        &self,
+        context: &Context,
+        quiz_template_id: ID,
+        paper: Option<PaperInput>,
+    ) -> FieldResult<Attempt> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let quiz_template = context
+            .quiz_templates
+            .read_by_id(&quiz_template_id.to_string())
+            .await?
+            .ok_or(AuthorizationError::NotFound)?;
+
+        let mut attempt = models::Attempt::new(&quiz_template, &user.id);
+
+        if let Some(paper) = paper {
+            let questions = read_template_questions(context, &quiz_template.id).await?;
+
+            attempt.paper = Some(models::Paper::generate(&questions, paper.to_model()?)?);
+        }
+
+        let model = context.attempts.create(&attempt).await?;
+
+        Ok(Attempt::from_model(&model))
+    }
+
+    async fn submit(&self, context: &Context, id: ID) -> FieldResult<Attempt> {
+        let mut attempt = read_open_attempt(context, &id).await?;
+
+        let questions = read_template_questions(context, &attempt.quiz_template_id).await?;
+
+        attempt.submit(&questions);
+
+        save_open_attempt(context, &attempt).await?;
+
+        Ok(Attempt::from_model(&attempt))
+    }
+}
+
+pub struct AttemptQuery;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl AttemptQuery {
+    async fn by_id(&self, context: &Context, id: ID) -> FieldResult<Option<Attempt>> {
+        let user = require_user(context)?;
+
+        let mut models = context
+            .attempts
+            .read_by_id(&id.to_string())
+            .await?
+            .filter(|attempt| {
+                attempt.user_id == user.id || has_permission(user, Permission::ViewAllContent)
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        submit_expired(context, &mut models).await?;
+
+        Ok(models.first().map(Attempt::from_model))
+    }
+
+    async fn mine(
+        &self,
+        context: &Context,
+        quiz_template_id: Option<ID>,
+    ) -> FieldResult<Vec<Attempt>> {
+        let user = require_user(context)?;
+
+        let quiz_template_id = quiz_template_id.map(|id| id.to_string());
+
+        let mut models = context
+            .attempts
+            .read_by_user_id(&user.id, quiz_template_id.as_deref())
+            .await?;
+
+        submit_expired(context, &mut models).await?;
+
+        Ok(models.iter().map(Attempt::from_model).collect())
+    }
+}
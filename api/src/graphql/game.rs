@@ -0,0 +1,556 @@
+use std::pin::Pin;
+
+use juniper::{graphql_object, FieldResult, GraphQLEnum, GraphQLObject, GraphQLUnion, ID};
+use mongodb::bson::{doc, DateTime};
+use rocket::futures::{Stream, StreamExt};
+
+use super::attempt::{Response, ResponseInput};
+
+use crate::authorization::{
+    require_permission, require_quiz_template_owner, require_user, AuthorizationError, Permission,
+};
+use crate::context::Context;
+use crate::game::{
+    self, leaderboard, now, GameError, PlayerAnswer, DEFAULT_SECONDS_PER_QUESTION,
+    MAX_SECONDS_PER_QUESTION,
+};
+use crate::models::{self, BaseModel};
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum GamePhase {
+    Lobby,
+    Question,
+    Reveal,
+    Finished,
+}
+
+#[derive(GraphQLObject)]
+pub struct LivePlayer {
+    score: i32,
+    user_id: ID,
+    username: String,
+}
+
+impl LivePlayer {
+    fn from_model(model: &game::Player) -> Self {
+        Self {
+            score: model.score as i32,
+            user_id: model.user_id.clone().into(),
+            username: model.username.clone(),
+        }
+    }
+
+    fn leaderboard(models: Vec<game::Player>) -> Vec<Self> {
+        leaderboard(models).iter().map(Self::from_model).collect()
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct Game {
+    code: String,
+    /// When answers to the open question stop being accepted.
+    deadline: Option<DateTime>,
+    phase: GamePhase,
+    /// Highest score first.
+    players: Vec<LivePlayer>,
+    question_count: i32,
+    question_index: Option<i32>,
+    seconds_per_question: i32,
+}
+
+impl Game {
+    fn from_model(model: &game::Game, players: Vec<game::Player>) -> Self {
+        let (phase, question_index, deadline) = match model.phase {
+            game::GamePhase::Lobby => (GamePhase::Lobby, None, None),
+            game::GamePhase::Question {
+                deadline, index, ..
+            } => (
+                GamePhase::Question,
+                Some(index as i32),
+                Some(DateTime::from_millis(deadline)),
+            ),
+            game::GamePhase::Reveal { index } => (GamePhase::Reveal, Some(index as i32), None),
+            game::GamePhase::Finished => (GamePhase::Finished, None, None),
+        };
+
+        Self {
+            code: model.code.clone(),
+            deadline,
+            phase,
+            players: LivePlayer::leaderboard(players),
+            question_count: model.question_ids.len() as i32,
+            question_index,
+            seconds_per_question: model.seconds_per_question as i32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum LiveQuestionKind {
+    FreeText,
+    MultipleChoice,
+    MultipleSelect,
+    TrueFalse,
+    Numeric,
+    Ordering,
+}
+
+impl LiveQuestionKind {
+    fn from_model(model: &game::LiveQuestionKind) -> Self {
+        match model {
+            game::LiveQuestionKind::FreeText => Self::FreeText,
+            game::LiveQuestionKind::MultipleChoice => Self::MultipleChoice,
+            game::LiveQuestionKind::MultipleSelect => Self::MultipleSelect,
+            game::LiveQuestionKind::TrueFalse => Self::TrueFalse,
+            game::LiveQuestionKind::Numeric => Self::Numeric,
+            game::LiveQuestionKind::Ordering => Self::Ordering,
+        }
+    }
+}
+
+/// `id` is what responses refer to. It says nothing about the correct answer.
+#[derive(GraphQLObject)]
+pub struct LiveOption {
+    id: i32,
+    text: String,
+}
+
+#[derive(GraphQLObject)]
+pub struct LiveQuestion {
+    count: i32,
+    deadline: DateTime,
+    index: i32,
+    kind: LiveQuestionKind,
+    options: Vec<LiveOption>,
+    question: String,
+    /// The question rendered to sanitised HTML.
+    question_html: String,
+    question_id: ID,
+}
+
+impl LiveQuestion {
+    fn from_model(model: &game::LiveQuestion) -> Self {
+        Self {
+            count: model.count as i32,
+            deadline: DateTime::from_millis(model.deadline),
+            index: model.index as i32,
+            kind: LiveQuestionKind::from_model(&model.kind),
+            options: model
+                .options
+                .iter()
+                .map(|option| LiveOption {
+                    id: option.id as i32,
+                    text: option.text.clone(),
+                })
+                .collect(),
+            question: model.question.clone(),
+            question_html: model.question_html.clone(),
+            question_id: model.question_id.clone().into(),
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct PlayerResult {
+    correct: bool,
+    points: i32,
+    user_id: ID,
+}
+
+#[derive(GraphQLObject)]
+pub struct PlayerJoined {
+    player: LivePlayer,
+}
+
+#[derive(GraphQLObject)]
+pub struct QuestionStarted {
+    question: LiveQuestion,
+}
+
+#[derive(GraphQLObject)]
+pub struct AnswerRevealed {
+    correct_response: Response,
+    index: i32,
+    question_id: ID,
+    results: Vec<PlayerResult>,
+}
+
+#[derive(GraphQLObject)]
+pub struct LeaderboardUpdated {
+    /// Highest score first.
+    players: Vec<LivePlayer>,
+}
+
+#[derive(GraphQLObject)]
+pub struct GameEnded {
+    /// Final standings, highest score first.
+    players: Vec<LivePlayer>,
+}
+
+#[derive(GraphQLUnion)]
+pub enum GameEvent {
+    PlayerJoined(PlayerJoined),
+    QuestionStarted(QuestionStarted),
+    AnswerRevealed(AnswerRevealed),
+    LeaderboardUpdated(LeaderboardUpdated),
+    GameEnded(GameEnded),
+}
+
+impl GameEvent {
+    fn from_model(model: game::GameEvent) -> Self {
+        match model {
+            game::GameEvent::PlayerJoined { player } => Self::PlayerJoined(PlayerJoined {
+                player: LivePlayer::from_model(&player),
+            }),
+            game::GameEvent::QuestionStarted { question } => {
+                Self::QuestionStarted(QuestionStarted {
+                    question: LiveQuestion::from_model(&question),
+                })
+            }
+            game::GameEvent::AnswerRevealed {
+                correct_response,
+                index,
+                question_id,
+                results,
+            } => Self::AnswerRevealed(AnswerRevealed {
+                correct_response: Response::from_model(&correct_response),
+                index: index as i32,
+                question_id: question_id.into(),
+                results: results
+                    .iter()
+                    .map(|result| PlayerResult {
+                        correct: result.correct,
+                        points: result.points as i32,
+                        user_id: result.user_id.clone().into(),
+                    })
+                    .collect(),
+            }),
+            game::GameEvent::LeaderboardUpdated { players } => {
+                Self::LeaderboardUpdated(LeaderboardUpdated {
+                    players: LivePlayer::leaderboard(players),
+                })
+            }
+            game::GameEvent::GameEnded { players } => Self::GameEnded(GameEnded {
+                players: LivePlayer::leaderboard(players),
+            }),
+        }
+    }
+}
+
+pub type GameEventStream = Pin<Box<dyn Stream<Item = GameEvent> + Send>>;
+
+/// Reads a game the signed in user is hosting.
+async fn read_hosted_game(context: &Context, code: &str) -> FieldResult<game::Game> {
+    let user = require_user(context)?;
+
+    let game = context.games.read(code).await?;
+
+    if game.host_id != user.id {
+        return Err(AuthorizationError::Forbidden)?;
+    }
+
+    Ok(game)
+}
+
+async fn read_question(context: &Context, id: &str) -> FieldResult<models::Question> {
+    let question = context
+        .questions
+        .read_by_id(id)
+        .await?
+        .ok_or("Question no longer exists")?;
+
+    Ok(question)
+}
+
+async fn to_graphql(context: &Context, game: &game::Game) -> FieldResult<Game> {
+    let players = context.games.players(&game.code).await?;
+
+    Ok(Game::from_model(game, players))
+}
+
+/// Streams the events of a game to its host and players.
+pub async fn subscribe(context: &Context, code: &str) -> FieldResult<GameEventStream> {
+    let user = require_user(context)?;
+
+    let game = context.games.read(code).await?;
+
+    if game.host_id != user.id && context.games.player(code, &user.id).await?.is_none() {
+        return Err(AuthorizationError::Forbidden)?;
+    }
+
+    let events = context.games.subscribe(code).await?;
+
+    Ok(Box::pin(events.map(GameEvent::from_model)))
+}
+
+pub struct GameMutation;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl GameMutation {
+    /// Answers the open question. Only the first answer of each player counts.
+    async fn answer(
+        &self,
+        context: &Context,
+        code: String,
+        response: ResponseInput,
+    ) -> FieldResult<bool> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let response = response.to_model()?;
+
+        let game = context.games.read(&code).await?;
+
+        let answered_at = now();
+
+        let index = game.open_question(answered_at)?;
+
+        if context.games.player(&code, &user.id).await?.is_none() {
+            return Err("Join the game first")?;
+        }
+
+        let question = read_question(context, &game.question_ids[index]).await?;
+
+        let response = game.option_ids(&question).to_indexes(&response)?;
+
+        let answer = PlayerAnswer {
+            answered_at,
+            response,
+        };
+
+        if !context
+            .games
+            .add_answer(&code, index, &user.id, &answer)
+            .await?
+        {
+            return Err("You have already answered")?;
+        }
+
+        Ok(true)
+    }
+
+    async fn end(&self, context: &Context, code: String) -> FieldResult<Game> {
+        let mut game = read_hosted_game(context, &code).await?;
+
+        game.phase = game::GamePhase::Finished;
+
+        context.games.save(&game).await?;
+
+        let players = context.games.players(&code).await?;
+
+        context
+            .games
+            .publish(
+                &code,
+                &game::GameEvent::GameEnded {
+                    players: leaderboard(players.clone()),
+                },
+            )
+            .await?;
+
+        Ok(Game::from_model(&game, players))
+    }
+
+    /// Starts a live game of a quiz template you own. Players join with the returned code.
+    async fn host(
+        &self,
+        context: &Context,
+        quiz_template_id: ID,
+        seconds_per_question: Option<i32>,
+    ) -> FieldResult<Game> {
+        let quiz_template = require_quiz_template_owner(context, &quiz_template_id).await?;
+
+        let seconds_per_question = match seconds_per_question {
+            None => DEFAULT_SECONDS_PER_QUESTION,
+            Some(seconds) => u32::try_from(seconds)
+                .ok()
+                .filter(|seconds| (1..=MAX_SECONDS_PER_QUESTION).contains(seconds))
+                .ok_or_else(|| {
+                    format!(
+                        "Seconds per question must be between 1 and {}",
+                        MAX_SECONDS_PER_QUESTION
+                    )
+                })?,
+        };
+
+        let question_ids = context
+            .questions
+            .read_by_quiz_template_id(&quiz_template.id)
+            .await?
+            .into_iter()
+            .map(|question| question.id)
+            .collect::<Vec<_>>();
+
+        if question_ids.is_empty() {
+            return Err("Quiz template has no questions")?;
+        }
+
+        let user = require_user(context)?;
+
+        let game = context
+            .games
+            .create(
+                &user.id,
+                &quiz_template.id,
+                question_ids,
+                seconds_per_question,
+            )
+            .await?;
+
+        Ok(Game::from_model(&game, vec![]))
+    }
+
+    async fn join(&self, context: &Context, code: String) -> FieldResult<Game> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let game = context.games.read(&code).await?;
+
+        if game.phase == game::GamePhase::Finished {
+            return Err(GameError::Invalid("The game has finished"))?;
+        }
+
+        let player = game::Player {
+            score: 0,
+            user_id: user.id.clone(),
+            username: user.username.clone(),
+        };
+
+        if context.games.add_player(&code, &player).await? {
+            context
+                .games
+                .publish(&code, &game::GameEvent::PlayerJoined { player })
+                .await?;
+        }
+
+        to_graphql(context, &game).await
+    }
+
+    /// Opens the next question and starts its countdown.
+    async fn next(&self, context: &Context, code: String) -> FieldResult<Game> {
+        let mut game = read_hosted_game(context, &code).await?;
+
+        let previous = game.phase.clone();
+
+        let index = game.next_question(now())?;
+
+        let question = read_question(context, &game.question_ids[index]).await?;
+
+        let game::GamePhase::Question { deadline, .. } = game.phase else {
+            unreachable!("next_question always opens a question");
+        };
+
+        if !context.games.save_if_phase(&game, &previous).await? {
+            return Err(GameError::Invalid(
+                "The next question has already been opened",
+            ))?;
+        }
+
+        let question = game::LiveQuestion::new(
+            &question,
+            &game.option_ids(&question),
+            index,
+            game.question_ids.len(),
+            deadline,
+        );
+
+        context
+            .games
+            .publish(&code, &game::GameEvent::QuestionStarted { question })
+            .await?;
+
+        to_graphql(context, &game).await
+    }
+
+    /// Closes the open question, scores the answers and shows the correct one.
+    async fn reveal(&self, context: &Context, code: String) -> FieldResult<Game> {
+        let mut game = read_hosted_game(context, &code).await?;
+
+        let open = game.clone();
+
+        let index = game.reveal()?;
+
+        // Only the request that closes the question scores it, so a retried reveal cannot
+        // score every player twice.
+        if !context.games.save_if_phase(&game, &open.phase).await? {
+            return Err(GameError::Invalid("The answer has already been revealed"))?;
+        }
+
+        let question = read_question(context, &game.question_ids[index]).await?;
+
+        let answers = context.games.answers(&code, index).await?;
+
+        let mut players = context.games.players(&code).await?;
+
+        let mut results = vec![];
+
+        for player in players.iter_mut() {
+            let Some(answer) = answers.get(&player.user_id) else {
+                continue;
+            };
+
+            let correct = question.check(&answer.response).matched;
+
+            let points = if correct {
+                open.points(answer.answered_at)
+            } else {
+                0
+            };
+
+            player.score += points;
+
+            results.push(game::PlayerResult {
+                correct,
+                points,
+                user_id: player.user_id.clone(),
+            });
+        }
+
+        context.games.save_players(&code, &players).await?;
+
+        context
+            .games
+            .publish(
+                &code,
+                &game::GameEvent::AnswerRevealed {
+                    correct_response: game
+                        .option_ids(&question)
+                        .to_ids(&question.correct_response()),
+                    index,
+                    question_id: question.id.clone(),
+                    results,
+                },
+            )
+            .await?;
+
+        context
+            .games
+            .publish(
+                &code,
+                &game::GameEvent::LeaderboardUpdated {
+                    players: leaderboard(players.clone()),
+                },
+            )
+            .await?;
+
+        Ok(Game::from_model(&game, players))
+    }
+}
+
+pub struct GameQuery;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl GameQuery {
+    /// A game you host or play in.
+    async fn by_code(&self, context: &Context, code: String) -> FieldResult<Game> {
+        let user = require_user(context)?;
+
+        let game = context.games.read(&code).await?;
+
+        if game.host_id != user.id && context.games.player(&code, &user.id).await?.is_none() {
+            return Err(AuthorizationError::NotFound)?;
+        }
+
+        to_graphql(context, &game).await
+    }
+}
@@ -0,0 +1,488 @@
+use std::{collections::HashMap, str::FromStr};
+
+use base64::prelude::*;
+use juniper::{graphql_object, graphql_value, FieldError, FieldResult, GraphQLObject, Value, ID};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use super::quiz_template::QuizTemplate;
+
+use crate::authorization::{require_permission, require_quiz_template_owner, Permission};
+use crate::context::Context;
+use crate::formats::{self, bundle::Bundle, Import};
+use crate::models::{self, BaseModel, ModelError};
+
+/// Largest file accepted, in bytes.
+const MAX_IMPORT_LENGTH: usize = 1 << 20;
+
+const DEFAULT_NAME: &str = "Imported quiz";
+
+#[derive(GraphQLObject)]
+pub struct ImportError {
+    /// File of a package the problem is in.
+    file: Option<String>,
+    line: i32,
+    message: String,
+}
+
+impl ImportError {
+    fn from_model(model: &formats::ImportError) -> Self {
+        Self {
+            file: model.file.clone(),
+            line: model.line as i32,
+            message: model.message.clone(),
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct ImportResult {
+    /// Parts of the file that could not be imported. The rest is imported regardless.
+    errors: Vec<ImportError>,
+    question_count: i32,
+    /// Empty when the file had no question that could be imported.
+    quiz_template: Option<QuizTemplate>,
+}
+
+async fn insert(
+    context: &Context,
+    quiz_template: &models::QuizTemplate,
+    questions: &[models::Question],
+) -> Result<(), ModelError> {
+    context.quiz_templates.create(quiz_template).await?;
+
+    for question in questions {
+        context.questions.create(question).await?;
+    }
+
+    Ok(())
+}
+
+async fn remove(context: &Context, quiz_template_id: &str) -> Result<(), ModelError> {
+    context
+        .questions
+        .delete_by_filter(doc! {
+            "quiz_template_id": ObjectId::from_str(quiz_template_id)?,
+        })
+        .await?;
+
+    context
+        .quiz_templates
+        .delete_by_id(quiz_template_id)
+        .await?;
+
+    Ok(())
+}
+
+/// Inserts quiz templates with their questions, all or nothing as far as it can. Transactions
+/// need a replica set, which deployments need not run, so a failed insert is compensated by
+/// deleting everything inserted before it. That clean up can fail as well, for instance when the
+/// database goes away mid import, in which case the error lists the ids of the quiz templates
+/// left behind under `leftBehind` so they can be removed by hand.
+pub async fn insert_all(
+    context: &Context,
+    quiz_templates: &[(models::QuizTemplate, Vec<models::Question>)],
+) -> FieldResult<()> {
+    for (index, (quiz_template, questions)) in quiz_templates.iter().enumerate() {
+        let Err(e) = insert(context, quiz_template, questions).await else {
+            continue;
+        };
+
+        let mut left_behind = vec![];
+
+        for (quiz_template, _) in &quiz_templates[..=index] {
+            if let Err(remove_error) = remove(context, &quiz_template.id).await {
+                eprintln!(
+                    "Error removing partly imported quiz template {}: {:?}",
+                    quiz_template.id, remove_error
+                );
+
+                left_behind.push(Value::scalar(quiz_template.id.clone()));
+            }
+        }
+
+        if left_behind.is_empty() {
+            return Err(e)?;
+        }
+
+        return Err(FieldError::new(
+            format!("{}. Some quiz templates could not be cleaned up", e),
+            graphql_value!({ "leftBehind": (Value::list(left_behind)) }),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Saves the questions of an import under a new quiz template.
+async fn save(
+    context: &Context,
+    import: Import,
+    name: Option<String>,
+) -> FieldResult<ImportResult> {
+    let user = require_permission(context, Permission::AuthorQuizzes)?;
+
+    let errors = import.errors.iter().map(ImportError::from_model).collect();
+
+    if import.questions.is_empty() {
+        return Ok(ImportResult {
+            errors,
+            question_count: 0,
+            quiz_template: None,
+        });
+    }
+
+    let quiz_template = models::QuizTemplate {
+        id: ObjectId::new().to_string(),
+        name: name
+            .or(import.name)
+            .unwrap_or_else(|| DEFAULT_NAME.to_string()),
+        order_version: 0,
+        question_count: import.questions.len() as u32,
+        question_time_limit_seconds: None,
+        scoring: Default::default(),
+        time_limit_seconds: None,
+        user_id: user.id.clone(),
+    };
+
+    let questions = import
+        .questions
+        .iter()
+        .enumerate()
+        .map(|(position, question)| models::Question {
+            position: position as u32,
+            quiz_template_id: quiz_template.id.clone(),
+            ..question.clone()
+        })
+        .collect::<Vec<_>>();
+
+    insert_all(context, &[(quiz_template.clone(), questions)]).await?;
+
+    Ok(ImportResult {
+        errors,
+        question_count: import.questions.len() as i32,
+        quiz_template: Some(QuizTemplate::from_model(&quiz_template)),
+    })
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct BundleQuizTemplate {
+    name: String,
+    question_count: i32,
+    /// Empty on a dry run.
+    quiz_template: Option<QuizTemplate>,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct BundleImportResult {
+    /// Every problem found in the bundle. Nothing is created unless this is empty.
+    errors: Vec<String>,
+    /// What was created, or would be on a dry run.
+    quiz_templates: Vec<BundleQuizTemplate>,
+}
+
+/// Recreates the quiz templates of a JSON bundle under the signed in user.
+pub async fn import_bundle(
+    context: &Context,
+    bundle: &str,
+    dry_run: bool,
+) -> FieldResult<BundleImportResult> {
+    let user = require_permission(context, Permission::AuthorQuizzes)?;
+
+    if bundle.len() > MAX_IMPORT_LENGTH {
+        return Err(format!(
+            "Bundles must be at most {} bytes",
+            MAX_IMPORT_LENGTH
+        ))?;
+    }
+
+    let bundle = match Bundle::from_json(bundle) {
+        Ok(bundle) => bundle,
+        Err(errors) => {
+            return Ok(BundleImportResult {
+                errors,
+                quiz_templates: vec![],
+            })
+        }
+    };
+
+    let models = bundle.to_models(&user.id);
+
+    if !dry_run {
+        insert_all(context, &models).await?;
+    }
+
+    Ok(BundleImportResult {
+        errors: vec![],
+        quiz_templates: models
+            .iter()
+            .map(|(quiz_template, questions)| BundleQuizTemplate {
+                name: quiz_template.name.clone(),
+                question_count: questions.len() as i32,
+                quiz_template: (!dry_run).then(|| QuizTemplate::from_model(quiz_template)),
+            })
+            .collect(),
+    })
+}
+
+#[derive(GraphQLObject)]
+pub struct CsvImportResult {
+    /// Whether the changes were saved. Never on a dry run or when any row has an error.
+    applied: bool,
+    created: i32,
+    deleted: i32,
+    errors: Vec<ImportError>,
+    unchanged: i32,
+    updated: i32,
+}
+
+impl CsvImportResult {
+    fn from_errors(errors: &[formats::ImportError]) -> Self {
+        Self {
+            applied: false,
+            created: 0,
+            deleted: 0,
+            errors: errors.iter().map(ImportError::from_model).collect(),
+            unchanged: 0,
+            updated: 0,
+        }
+    }
+}
+
+/// Writes of a CSV import done so far, kept so that they can be undone.
+#[derive(Default)]
+struct CsvWrites<'a> {
+    created: Vec<&'a models::Question>,
+    deleted: Vec<&'a models::Question>,
+    /// The versions before the update.
+    updated: Vec<&'a models::Question>,
+}
+
+async fn write_csv<'a>(
+    context: &Context,
+    diff: &'a formats::csv::Diff,
+    existing: &HashMap<&str, &'a models::Question>,
+    writes: &mut CsvWrites<'a>,
+) -> Result<(), ModelError> {
+    for question in &diff.created {
+        context.questions.create(question).await?;
+        writes.created.push(question);
+    }
+
+    for question in &diff.updated {
+        context
+            .questions
+            .update_by_id(&question.id, question)
+            .await?;
+        writes.updated.push(existing[question.id.as_str()]);
+    }
+
+    for question in &diff.deleted {
+        context.questions.delete_by_id(&question.id).await?;
+        writes.deleted.push(question);
+    }
+
+    Ok(())
+}
+
+/// Undoes `writes`, returning the ids of the questions that could not be put back.
+async fn undo_csv(context: &Context, writes: &CsvWrites<'_>) -> Vec<Value> {
+    let mut left_behind = vec![];
+
+    for question in &writes.created {
+        if let Err(e) = context.questions.delete_by_id(&question.id).await {
+            eprintln!("Error removing imported question {}: {:?}", question.id, e);
+
+            left_behind.push(Value::scalar(question.id.clone()));
+        }
+    }
+
+    for question in &writes.updated {
+        if let Err(e) = context.questions.update_by_id(&question.id, question).await {
+            eprintln!("Error restoring question {}: {:?}", question.id, e);
+
+            left_behind.push(Value::scalar(question.id.clone()));
+        }
+    }
+
+    for question in &writes.deleted {
+        if let Err(e) = context.questions.create(question).await {
+            eprintln!("Error restoring question {}: {:?}", question.id, e);
+
+            left_behind.push(Value::scalar(question.id.clone()));
+        }
+    }
+
+    left_behind
+}
+
+/// Applies a CSV diff, all or nothing as far as it can in the same way as [`insert_all`]: a
+/// failed write is compensated by undoing the writes before it, and questions that could not be
+/// put back are listed under `leftBehind`. Attachments of deleted questions are only removed
+/// once every write succeeded, as they cannot be restored.
+async fn apply_csv(
+    context: &Context,
+    quiz_template_id: &str,
+    existing: &[models::Question],
+    diff: &formats::csv::Diff,
+) -> FieldResult<()> {
+    let existing = existing
+        .iter()
+        .map(|question| (question.id.as_str(), question))
+        .collect::<HashMap<_, _>>();
+
+    let mut writes = CsvWrites::default();
+
+    if let Err(e) = write_csv(context, diff, &existing, &mut writes).await {
+        let left_behind = undo_csv(context, &writes).await;
+
+        if left_behind.is_empty() {
+            return Err(e)?;
+        }
+
+        // Questions were left added or removed, so count them again.
+        if let Ok(count) = context
+            .questions
+            .count_by_quiz_template_id(quiz_template_id)
+            .await
+        {
+            let _ = context
+                .quiz_templates
+                .set_question_count(quiz_template_id, count)
+                .await;
+        }
+
+        return Err(FieldError::new(
+            format!("{}. Some questions could not be restored", e),
+            graphql_value!({ "leftBehind": (Value::list(left_behind)) }),
+        ));
+    }
+
+    context
+        .quiz_templates
+        .add_to_question_count(
+            quiz_template_id,
+            diff.created.len() as i64 - diff.deleted.len() as i64,
+        )
+        .await?;
+
+    for question in &diff.deleted {
+        if let Err(e) = context.attachments.delete_all(&question.attachments).await {
+            eprintln!(
+                "Error deleting attachments of question {}: {:?}",
+                question.id, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub struct ImportMutation;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl ImportMutation {
+    /// Creates a quiz template from the contents of a Moodle GIFT file. Without a `name` the
+    /// first category in the file is used.
+    async fn gift(
+        &self,
+        context: &Context,
+        contents: String,
+        name: Option<String>,
+    ) -> FieldResult<ImportResult> {
+        if contents.len() > MAX_IMPORT_LENGTH {
+            return Err(format!("Files must be at most {} bytes", MAX_IMPORT_LENGTH))?;
+        }
+
+        save(context, formats::gift::parse(&contents), name).await
+    }
+
+    /// Creates a quiz template from a base64 encoded IMS QTI 2.1 package. Without a `name` the
+    /// title in the manifest is used. Items with unsupported interactions are reported.
+    async fn qti(
+        &self,
+        context: &Context,
+        package: String,
+        name: Option<String>,
+    ) -> FieldResult<ImportResult> {
+        if package.len() > MAX_IMPORT_LENGTH {
+            return Err(format!(
+                "Packages must be at most {} bytes",
+                MAX_IMPORT_LENGTH
+            ))?;
+        }
+
+        let package = BASE64_STANDARD
+            .decode(package.trim())
+            .map_err(|_| "Package must be base64 encoded")?;
+
+        save(context, formats::qti::parse(&package)?, name).await
+    }
+
+    /// Creates, updates and optionally deletes the questions of a quiz template to match a CSV
+    /// file made by `quizTemplate.exportCsv`. Rows are matched to questions by id and rows
+    /// without one are new. Nothing changes if any row has an error. A write that fails undoes
+    /// the ones before it, and questions that could not be restored are listed in the error
+    /// under `leftBehind`.
+    async fn csv(
+        &self,
+        context: &Context,
+        quiz_template_id: ID,
+        contents: String,
+        delete_missing: Option<bool>,
+        dry_run: Option<bool>,
+    ) -> FieldResult<CsvImportResult> {
+        let quiz_template = require_quiz_template_owner(context, &quiz_template_id).await?;
+
+        if contents.len() > MAX_IMPORT_LENGTH {
+            return Err(format!("Files must be at most {} bytes", MAX_IMPORT_LENGTH))?;
+        }
+
+        let (rows, errors) = match formats::csv::parse(&contents) {
+            Ok(parsed) => parsed,
+            Err(error) => return Ok(CsvImportResult::from_errors(&[error])),
+        };
+
+        if !errors.is_empty() {
+            return Ok(CsvImportResult::from_errors(&errors));
+        }
+
+        let existing = context
+            .questions
+            .read_by_quiz_template_id(&quiz_template.id)
+            .await?;
+
+        let diff = match formats::csv::diff(
+            &quiz_template.id,
+            &existing,
+            rows,
+            delete_missing.unwrap_or(false),
+        ) {
+            Ok(diff) => diff,
+            Err(errors) => return Ok(CsvImportResult::from_errors(&errors)),
+        };
+
+        let applied = !dry_run.unwrap_or(false);
+
+        if applied {
+            context
+                .quiz_templates
+                .touch_questions(&quiz_template.id)
+                .await?;
+
+            apply_csv(context, &quiz_template.id, &existing, &diff).await?;
+        }
+
+        Ok(CsvImportResult {
+            applied,
+            created: diff.created.len() as i32,
+            deleted: diff.deleted.len() as i32,
+            errors: vec![],
+            unchanged: diff.unchanged as i32,
+            updated: diff.updated.len() as i32,
+        })
+    }
+}
@@ -1,15 +1,60 @@
+mod admin;
+mod attempt;
+mod game;
+mod import;
+mod pagination;
 mod question;
 mod quiz_template;
+mod search;
+mod study;
 
-use juniper::{graphql_object, EmptySubscription, GraphQLObject, RootNode, ID};
+use admin::{AdminMutation, AdminQuery};
+use attempt::{AttemptMutation, AttemptQuery};
+use game::{GameEventStream, GameMutation, GameQuery};
+use import::ImportMutation;
+use juniper::{
+    graphql_object, graphql_subscription, FieldResult, GraphQLEnum, GraphQLObject, RootNode, ID,
+};
 use question::{QuestionMutation, QuestionQuery};
 use quiz_template::{QuizTemplateMutation, QuizTemplateQuery};
+use search::SearchHit;
+use study::{StudyMutation, StudyQuery};
 
-use crate::{context::Context, models};
+use crate::{
+    authorization::{require_permission, Permission},
+    context::Context,
+    models,
+};
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum Role {
+    Admin,
+    Author,
+    Learner,
+}
+
+impl Role {
+    fn from_model(model: &models::Role) -> Self {
+        match model {
+            models::Role::Admin => Self::Admin,
+            models::Role::Author => Self::Author,
+            models::Role::Learner => Self::Learner,
+        }
+    }
+
+    fn to_model(self) -> models::Role {
+        match self {
+            Self::Admin => models::Role::Admin,
+            Self::Author => models::Role::Author,
+            Self::Learner => models::Role::Learner,
+        }
+    }
+}
 
 #[derive(GraphQLObject)]
 pub struct User {
     id: ID,
+    roles: Vec<Role>,
     username: String,
 }
 
@@ -17,17 +62,35 @@ impl User {
     fn from_model(model: &models::User) -> Self {
         Self {
             id: model.id.clone().into(),
+            roles: model.roles.iter().map(Role::from_model).collect(),
             username: model.username.clone(),
         }
     }
 }
 
-
 pub struct Mutation;
 
 #[graphql_object]
 #[graphql(context = Context)]
 impl Mutation {
+    fn admin(&self, context: &Context) -> FieldResult<AdminMutation> {
+        require_permission(context, Permission::ManageUsers)?;
+
+        Ok(AdminMutation)
+    }
+
+    fn attempt(&self) -> AttemptMutation {
+        AttemptMutation
+    }
+
+    fn game(&self) -> GameMutation {
+        GameMutation
+    }
+
+    fn import(&self) -> ImportMutation {
+        ImportMutation
+    }
+
     fn question(&self) -> QuestionMutation {
         QuestionMutation
     }
@@ -35,6 +98,10 @@ impl Mutation {
     fn quiz_template(&self) -> QuizTemplateMutation {
         QuizTemplateMutation
     }
+
+    fn study(&self) -> StudyMutation {
+        StudyMutation
+    }
 }
 
 pub struct Query;
@@ -42,10 +109,24 @@ pub struct Query;
 #[graphql_object]
 #[graphql(context = Context)]
 impl Query {
+    fn admin(&self, context: &Context) -> FieldResult<AdminQuery> {
+        require_permission(context, Permission::ManageUsers)?;
+
+        Ok(AdminQuery)
+    }
+
     fn api_version(&self) -> &'static str {
         "1.0"
     }
 
+    fn attempt(&self) -> AttemptQuery {
+        AttemptQuery
+    }
+
+    fn game(&self) -> GameQuery {
+        GameQuery
+    }
+
     fn question(&self) -> QuestionQuery {
         QuestionQuery
     }
@@ -54,9 +135,28 @@ impl Query {
         QuizTemplateQuery
     }
 
+    async fn search(&self, context: &Context, query: String) -> FieldResult<Vec<SearchHit>> {
+        search::search(context, &query).await
+    }
+
+    fn study(&self) -> StudyQuery {
+        StudyQuery
+    }
+
     fn user(&self, context: &Context) -> Option<User> {
         context.user.as_ref().map(User::from_model)
     }
 }
 
-pub type Schema = RootNode<'static, Query, Mutation, EmptySubscription<Context>>;
+pub struct Subscription;
+
+#[graphql_subscription]
+#[graphql(context = Context)]
+impl Subscription {
+    /// Question, answer reveal and leaderboard events of a live game you host or play in.
+    async fn game(&self, context: &Context, code: String) -> FieldResult<GameEventStream> {
+        game::subscribe(context, &code).await
+    }
+}
+
+pub type Schema = RootNode<'static, Query, Mutation, Subscription>;
@@ -0,0 +1,37 @@
+use juniper::{GraphQLEnum, GraphQLObject};
+
+use crate::models::{self, Page};
+
+#[derive(GraphQLObject)]
+pub struct PageInfo {
+    end_cursor: Option<String>,
+    has_next_page: bool,
+    has_previous_page: bool,
+    start_cursor: Option<String>,
+}
+
+impl PageInfo {
+    pub fn from_page<Model>(page: &Page<Model>) -> Self {
+        Self {
+            end_cursor: page.end_cursor(),
+            has_next_page: page.has_next_page,
+            has_previous_page: page.has_previous_page,
+            start_cursor: page.start_cursor(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub fn to_model(self) -> models::SortDirection {
+        match self {
+            Self::Ascending => models::SortDirection::Ascending,
+            Self::Descending => models::SortDirection::Descending,
+        }
+    }
+}
@@ -1,59 +1,913 @@
-use juniper::{graphql_object, FieldResult, GraphQLInputObject, GraphQLObject, ID};
-use mongodb::bson::oid::{self, ObjectId};
+use juniper::{
+    graphql_object, FieldResult, GraphQLEnum, GraphQLInputObject, GraphQLObject, GraphQLUnion,
+    Nullable, ID,
+};
+use std::str::FromStr;
 
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+
+use super::pagination::{PageInfo, SortDirection};
+
+use crate::authorization::{
+    has_permission, require_question_owner, require_quiz_template_owner, Permission,
+};
+use crate::content;
 use crate::context::Context;
-use crate::models::{self, BaseModel};
+use crate::matching::MatchMode;
+use crate::models::{self, BaseModel, Filter, ModelError, Page, PageRequest, Sort};
+
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum TextMatchMode {
+    Exact,
+    CaseInsensitive,
+    Normalized,
+    Alternatives,
+    Regex,
+    Fuzzy,
+}
 
 #[derive(GraphQLObject)]
-pub struct Question {
+pub struct TextMatching {
+    accepted: Vec<String>,
+    max_distance: Option<i32>,
+    mode: TextMatchMode,
+    pattern: Option<String>,
+}
+
+impl TextMatching {
+    fn from_model(model: &MatchMode) -> Self {
+        let mut matching = Self {
+            accepted: vec![],
+            max_distance: None,
+            mode: TextMatchMode::Exact,
+            pattern: None,
+        };
+
+        match model {
+            MatchMode::Exact => {}
+            MatchMode::CaseInsensitive => matching.mode = TextMatchMode::CaseInsensitive,
+            MatchMode::Normalized => matching.mode = TextMatchMode::Normalized,
+            MatchMode::Alternatives { accepted } => {
+                matching.accepted = accepted.clone();
+                matching.mode = TextMatchMode::Alternatives;
+            }
+            MatchMode::Regex { pattern } => {
+                matching.mode = TextMatchMode::Regex;
+                matching.pattern = Some(pattern.clone());
+            }
+            MatchMode::Fuzzy { max_distance } => {
+                matching.max_distance = Some(*max_distance as i32);
+                matching.mode = TextMatchMode::Fuzzy;
+            }
+        }
+
+        matching
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct FreeTextKind {
     answer: String,
+    matching: TextMatching,
+}
+
+#[derive(GraphQLObject)]
+pub struct MultipleChoiceKind {
+    correct_option: i32,
+    options: Vec<String>,
+}
+
+#[derive(GraphQLObject)]
+pub struct MultipleSelectKind {
+    correct_options: Vec<i32>,
+    options: Vec<String>,
+}
+
+#[derive(GraphQLObject)]
+pub struct TrueFalseKind {
+    correct: bool,
+}
+
+#[derive(GraphQLObject)]
+pub struct NumericKind {
+    tolerance: f64,
+    value: f64,
+}
+
+#[derive(GraphQLObject)]
+pub struct OrderingKind {
+    /// Items in their correct order.
+    items: Vec<String>,
+}
+
+#[derive(GraphQLUnion)]
+pub enum QuestionKind {
+    FreeText(FreeTextKind),
+    MultipleChoice(MultipleChoiceKind),
+    MultipleSelect(MultipleSelectKind),
+    TrueFalse(TrueFalseKind),
+    Numeric(NumericKind),
+    Ordering(OrderingKind),
+}
+
+impl QuestionKind {
+    fn from_model(model: &models::Question) -> Self {
+        match &model.kind {
+            models::QuestionKind::FreeText { matching } => Self::FreeText(FreeTextKind {
+                answer: model.answer.clone(),
+                matching: TextMatching::from_model(matching),
+            }),
+            models::QuestionKind::MultipleChoice {
+                correct_option,
+                options,
+            } => Self::MultipleChoice(MultipleChoiceKind {
+                correct_option: *correct_option as i32,
+                options: options.clone(),
+            }),
+            models::QuestionKind::MultipleSelect {
+                correct_options,
+                options,
+            } => Self::MultipleSelect(MultipleSelectKind {
+                correct_options: correct_options.iter().map(|o| *o as i32).collect(),
+                options: options.clone(),
+            }),
+            models::QuestionKind::TrueFalse { correct } => {
+                Self::TrueFalse(TrueFalseKind { correct: *correct })
+            }
+            models::QuestionKind::Numeric { tolerance, value } => Self::Numeric(NumericKind {
+                tolerance: *tolerance,
+                value: *value,
+            }),
+            models::QuestionKind::Ordering { items } => Self::Ordering(OrderingKind {
+                items: items.clone(),
+            }),
+        }
+    }
+}
+
+/// How a question or answer is written. `MARKDOWN` supports `$inline$` and `$$display$$`
+/// math but not raw HTML.
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum ContentFormat {
+    Plain,
+    Markdown,
+}
+
+impl ContentFormat {
+    fn from_model(model: content::ContentFormat) -> Self {
+        match model {
+            content::ContentFormat::Plain => Self::Plain,
+            content::ContentFormat::Markdown => Self::Markdown,
+        }
+    }
+
+    fn to_model(format: Option<Self>) -> content::ContentFormat {
+        match format {
+            None | Some(Self::Plain) => content::ContentFormat::Plain,
+            Some(Self::Markdown) => content::ContentFormat::Markdown,
+        }
+    }
+}
+
+/// An image or sound clip uploaded to `/questions/<id>/attachments`.
+#[derive(GraphQLObject)]
+pub struct Attachment {
+    content_type: String,
+    filename: String,
     id: ID,
-    question: String,
+    /// Size in bytes.
+    size: i32,
+    /// Where the file can be downloaded by any signed in user.
+    url: String,
+}
+
+impl Attachment {
+    pub fn from_model(model: &models::Attachment) -> Self {
+        Self {
+            content_type: model.content_type.clone(),
+            filename: model.filename.clone(),
+            id: model.id.clone().into(),
+            size: model.size as i32,
+            url: format!("/attachments/{}", model.id),
+        }
+    }
+}
+
+#[derive(GraphQLObject)]
+pub struct Hint {
+    /// Fraction of the question's score deducted when the hint is revealed, from 0 to 1.
+    penalty: f64,
+    text: String,
+}
+
+impl Hint {
+    fn from_model(model: &models::Hint) -> Self {
+        Self {
+            penalty: model.penalty,
+            text: model.text.clone(),
+        }
+    }
+}
+
+/// The shape of a question without the data needed to grade it.
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum QuestionType {
+    FreeText,
+    MultipleChoice,
+    MultipleSelect,
+    TrueFalse,
+    Numeric,
+    Ordering,
+}
+
+impl QuestionType {
+    fn from_model(model: &models::QuestionKind) -> Self {
+        match model {
+            models::QuestionKind::FreeText { .. } => Self::FreeText,
+            models::QuestionKind::MultipleChoice { .. } => Self::MultipleChoice,
+            models::QuestionKind::MultipleSelect { .. } => Self::MultipleSelect,
+            models::QuestionKind::TrueFalse { .. } => Self::TrueFalse,
+            models::QuestionKind::Numeric { .. } => Self::Numeric,
+            models::QuestionKind::Ordering { .. } => Self::Ordering,
+        }
+    }
+}
+
+/// How much of a question's points a partly right response to a multiple select or ordering
+/// question earns. Other kinds are always all or nothing.
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum PartialCredit {
+    AllOrNothing,
+    /// The share of options marked correctly, or of items in their correct place.
+    Proportional,
+    /// Right choices or places minus wrong ones, as a share of the right ones. Never below 0.
+    ProportionalWithPenalty,
+}
+
+impl PartialCredit {
+    fn from_model(model: models::PartialCredit) -> Self {
+        match model {
+            models::PartialCredit::AllOrNothing => Self::AllOrNothing,
+            models::PartialCredit::Proportional => Self::Proportional,
+            models::PartialCredit::ProportionalWithPenalty => Self::ProportionalWithPenalty,
+        }
+    }
+
+    fn to_model(policy: Option<Self>) -> models::PartialCredit {
+        match policy {
+            None | Some(Self::AllOrNothing) => models::PartialCredit::AllOrNothing,
+            Some(Self::Proportional) => models::PartialCredit::Proportional,
+            Some(Self::ProportionalWithPenalty) => models::PartialCredit::ProportionalWithPenalty,
+        }
+    }
+}
+
+/// Who may see the answer, explanation, hints and grading data of a question.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Reveal {
+    /// The owner of the quiz template and anyone who can view all content.
+    ToOwner,
+    /// On a study card, once the learner has chosen to reveal it.
+    OnCard { revealed: bool },
+    /// As in an attempt: the explanation once the question is answered and the rest once the
+    /// attempt is graded.
+    InAttempt { answered: bool, graded: bool },
+}
+
+impl Reveal {
+    /// Whether the answer, hints and grading data show. `owner` only matters for `ToOwner`.
+    fn answer(self, owner: bool) -> bool {
+        match self {
+            Self::ToOwner => owner,
+            Self::OnCard { revealed } => revealed,
+            Self::InAttempt { graded, .. } => graded,
+        }
+    }
+
+    fn explanation(self, owner: bool) -> bool {
+        match self {
+            Self::InAttempt { answered, .. } => answered,
+            _ => self.answer(owner),
+        }
+    }
+}
+
+/// A question as anyone may see it. Fields that would give the answer away resolve to nothing
+/// unless the caller owns the quiz template, revealed it on a study card, or it is part of an
+/// attempt that has been graded.
+pub struct Question {
+    model: models::Question,
+    reveal: Reveal,
 }
 
 impl Question {
     pub fn from_model(model: &models::Question) -> Self {
         Self {
-            answer: model.answer.clone(),
-            id: model.id.to_string().into(),
-            question: model.question.clone(),
+            model: model.clone(),
+            reveal: Reveal::ToOwner,
+        }
+    }
+
+    /// The question on a study card, with its answer once the learner reveals it.
+    pub fn on_card(model: &models::Question, revealed: bool) -> Self {
+        Self {
+            model: model.clone(),
+            reveal: Reveal::OnCard { revealed },
+        }
+    }
+
+    /// The question as shown in an attempt. Revealed hints are listed on the attempt question
+    /// instead.
+    pub fn for_attempt(attempt: &models::Attempt, model: &models::Question) -> Self {
+        let graded = attempt.result.is_some();
+
+        Self {
+            model: model.clone(),
+            reveal: Reveal::InAttempt {
+                answered: graded || attempt.response_for(&model.id).is_some(),
+                graded,
+            },
+        }
+    }
+
+    /// Whether the answer resolves for a caller who does not own the quiz template.
+    #[cfg(test)]
+    pub fn shows_answer_to_learners(&self) -> bool {
+        self.reveal.answer(false)
+    }
+
+    async fn is_owner(&self, context: &Context) -> FieldResult<bool> {
+        let user = match &context.user {
+            Some(user) => user,
+            None => return Ok(false),
+        };
+
+        if has_permission(user, Permission::ViewAllContent) {
+            return Ok(true);
+        }
+
+        let quiz_template = context
+            .quiz_templates_by_id
+            .load(self.model.quiz_template_id.clone())
+            .await?;
+
+        Ok(quiz_template.is_some_and(|quiz_template| quiz_template.user_id == user.id))
+    }
+
+    /// Ownership is only looked up when the reveal rule depends on it.
+    async fn owner(&self, context: &Context) -> FieldResult<bool> {
+        match self.reveal {
+            Reveal::ToOwner => self.is_owner(context).await,
+            _ => Ok(false),
+        }
+    }
+
+    async fn shows_answer(&self, context: &Context) -> FieldResult<bool> {
+        Ok(self.reveal.answer(self.owner(context).await?))
+    }
+
+    async fn shows_explanation(&self, context: &Context) -> FieldResult<bool> {
+        Ok(self.reveal.explanation(self.owner(context).await?))
+    }
+}
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl Question {
+    /// Source of the answer, in `answerFormat`.
+    async fn answer(&self, context: &Context) -> FieldResult<Option<String>> {
+        Ok(self
+            .shows_answer(context)
+            .await?
+            .then(|| self.model.answer.clone()))
+    }
+
+    fn answer_format(&self) -> ContentFormat {
+        ContentFormat::from_model(self.model.answer_format)
+    }
+
+    /// The answer rendered to sanitised HTML.
+    async fn answer_html(&self, context: &Context) -> FieldResult<Option<String>> {
+        Ok(self
+            .shows_answer(context)
+            .await?
+            .then(|| self.model.answer_format.render(&self.model.answer)))
+    }
+
+    fn attachments(&self) -> Vec<Attachment> {
+        self.model
+            .attachments
+            .iter()
+            .map(Attachment::from_model)
+            .collect()
+    }
+
+    /// Why the answer is right, in `answerFormat`. Shown in an attempt once the question is
+    /// answered.
+    async fn explanation(&self, context: &Context) -> FieldResult<Option<String>> {
+        match self.shows_explanation(context).await? {
+            true => Ok(self.model.explanation.clone()),
+            false => Ok(None),
+        }
+    }
+
+    async fn explanation_html(&self, context: &Context) -> FieldResult<Option<String>> {
+        match self.shows_explanation(context).await? {
+            true => Ok(self
+                .model
+                .explanation
+                .as_ref()
+                .map(|explanation| self.model.answer_format.render(explanation))),
+            false => Ok(None),
+        }
+    }
+
+    async fn hints(&self, context: &Context) -> FieldResult<Vec<Hint>> {
+        match self.shows_answer(context).await? {
+            true => Ok(self.model.hints.iter().map(Hint::from_model).collect()),
+            false => Ok(vec![]),
+        }
+    }
+
+    fn id(&self) -> ID {
+        self.model.id.clone().into()
+    }
+
+    /// The kind with its grading data, shown with the answer.
+    async fn kind(&self, context: &Context) -> FieldResult<Option<QuestionKind>> {
+        Ok(self
+            .shows_answer(context)
+            .await?
+            .then(|| QuestionKind::from_model(&self.model)))
+    }
+
+    fn partial_credit(&self) -> PartialCredit {
+        PartialCredit::from_model(self.model.partial_credit)
+    }
+
+    /// What a fully correct response is worth.
+    fn points(&self) -> f64 {
+        self.model.points
+    }
+
+    /// The options of a choice question as stored, or the items of an ordering question in
+    /// alphabetical order so that they do not give the answer away.
+    fn options(&self) -> Vec<String> {
+        match &self.model.kind {
+            models::QuestionKind::MultipleChoice { options, .. }
+            | models::QuestionKind::MultipleSelect { options, .. } => options.clone(),
+            models::QuestionKind::Ordering { items } => {
+                let mut items = items.clone();
+
+                items.sort();
+
+                items
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Source of the question, in `questionFormat`.
+    fn question(&self) -> &str {
+        &self.model.question
+    }
+
+    fn question_format(&self) -> ContentFormat {
+        ContentFormat::from_model(self.model.question_format)
+    }
+
+    /// The question rendered to sanitised HTML.
+    fn question_html(&self) -> String {
+        self.model.question_format.render(&self.model.question)
+    }
+
+    fn r#type(&self) -> QuestionType {
+        QuestionType::from_model(&self.model.kind)
+    }
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuestionEdge {
+    cursor: String,
+    node: Question,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuestionConnection {
+    edges: Vec<QuestionEdge>,
+    page_info: PageInfo,
+}
+
+impl QuestionConnection {
+    pub fn from_page(page: &Page<models::Question>) -> Self {
+        Self {
+            edges: page
+                .edges
+                .iter()
+                .map(|(cursor, model)| QuestionEdge {
+                    cursor: cursor.clone(),
+                    node: Question::from_model(model),
+                })
+                .collect(),
+            page_info: PageInfo::from_page(page),
+        }
+    }
+}
+
+/// Every field that is set must match.
+#[derive(Default, GraphQLInputObject)]
+pub struct QuestionFilter {
+    answer_contains: Option<String>,
+    created_after: Option<DateTime>,
+    created_before: Option<DateTime>,
+    question_contains: Option<String>,
+    quiz_template_id: Option<ID>,
+}
+
+impl QuestionFilter {
+    /// Matching on answers would let anyone probe for them, so `answerContains` only matches
+    /// questions of the caller's own quiz templates unless they can view all content.
+    pub async fn to_filter(&self, context: &Context) -> FieldResult<Filter> {
+        let mut filter = Filter::new();
+
+        if let Some(text) = &self.answer_contains {
+            filter = filter.contains("answer", text)?;
+
+            match &context.user {
+                Some(user) if has_permission(user, Permission::ViewAllContent) => {}
+                user => {
+                    let own_ids = match user {
+                        Some(user) => context
+                            .quiz_templates
+                            .read_by_filter(doc! {
+                                "user_id": ObjectId::from_str(&user.id)?,
+                            })
+                            .await?
+                            .iter()
+                            .map(|quiz_template| ObjectId::from_str(&quiz_template.id))
+                            .collect::<Result<Vec<_>, _>>()?,
+                        None => vec![],
+                    };
+
+                    filter = filter.id_in("quiz_template_id", own_ids);
+                }
+            }
+        }
+
+        if let Some(time) = self.created_after {
+            filter = filter.created_after(time);
+        }
+
+        if let Some(time) = self.created_before {
+            filter = filter.created_before(time);
+        }
+
+        if let Some(text) = &self.question_contains {
+            filter = filter.contains("question", text)?;
         }
+
+        if let Some(id) = &self.quiz_template_id {
+            filter = filter.id_equals("quiz_template_id", id)?;
+        }
+
+        Ok(filter)
     }
 }
 
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum QuestionSortField {
+    CreatedAt,
+    /// Place in the quiz template, as set by `reorder`.
+    Position,
+    Question,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct QuestionSort {
+    direction: Option<SortDirection>,
+    field: QuestionSortField,
+}
+
+impl QuestionSort {
+    /// The order of the questions of one quiz template.
+    pub fn position() -> Sort {
+        Sort {
+            direction: models::SortDirection::Ascending,
+            field: "position".to_string(),
+        }
+    }
+
+    pub fn to_model(&self) -> Sort {
+        let field = match self.field {
+            QuestionSortField::CreatedAt => "_id",
+            QuestionSortField::Position => "position",
+            QuestionSortField::Question => "question",
+        };
+
+        Sort {
+            direction: self
+                .direction
+                .unwrap_or(SortDirection::Ascending)
+                .to_model(),
+            field: field.to_string(),
+        }
+    }
+}
+
+pub fn to_index(value: i32) -> Result<usize, ModelError> {
+    usize::try_from(value).map_err(|_| ModelError::from("Indexes must not be negative"))
+}
+
+/// `accepted` is required by `ALTERNATIVES`, `pattern` by `REGEX` and `maxDistance` by
+/// `FUZZY`.
+#[derive(GraphQLInputObject)]
+struct TextMatchingInput {
+    accepted: Option<Vec<String>>,
+    max_distance: Option<i32>,
+    mode: TextMatchMode,
+    pattern: Option<String>,
+}
+
+impl TextMatchingInput {
+    fn to_model(&self) -> Result<MatchMode, ModelError> {
+        Ok(match self.mode {
+            TextMatchMode::Exact => MatchMode::Exact,
+            TextMatchMode::CaseInsensitive => MatchMode::CaseInsensitive,
+            TextMatchMode::Normalized => MatchMode::Normalized,
+            TextMatchMode::Alternatives => MatchMode::Alternatives {
+                accepted: self
+                    .accepted
+                    .clone()
+                    .ok_or("Accepted alternatives are required")?,
+            },
+            TextMatchMode::Regex => MatchMode::Regex {
+                pattern: self.pattern.clone().ok_or("A pattern is required")?,
+            },
+            TextMatchMode::Fuzzy => MatchMode::Fuzzy {
+                max_distance: to_index(self.max_distance.ok_or("A maximum distance is required")?)?,
+            },
+        })
+    }
+}
+
+#[derive(GraphQLInputObject)]
+struct FreeTextInput {
+    matching: TextMatchingInput,
+}
+
+#[derive(GraphQLInputObject)]
+struct MultipleChoiceInput {
+    correct_option: i32,
+    options: Vec<String>,
+}
+
+#[derive(GraphQLInputObject)]
+struct MultipleSelectInput {
+    correct_options: Vec<i32>,
+    options: Vec<String>,
+}
+
+#[derive(GraphQLInputObject)]
+struct TrueFalseInput {
+    correct: bool,
+}
+
+#[derive(GraphQLInputObject)]
+struct NumericInput {
+    tolerance: f64,
+    value: f64,
+}
+
+#[derive(GraphQLInputObject)]
+struct OrderingInput {
+    /// Items in their correct order.
+    items: Vec<String>,
+}
+
+/// Set at most one field. Leaving every field empty makes a free-text question with exact
+/// matching.
+#[derive(GraphQLInputObject)]
+struct QuestionKindInput {
+    free_text: Option<FreeTextInput>,
+    multiple_choice: Option<MultipleChoiceInput>,
+    multiple_select: Option<MultipleSelectInput>,
+    numeric: Option<NumericInput>,
+    ordering: Option<OrderingInput>,
+    true_false: Option<TrueFalseInput>,
+}
+
+impl QuestionKindInput {
+    fn to_model(&self) -> Result<models::QuestionKind, ModelError> {
+        let mut kinds = vec![];
+
+        if let Some(input) = &self.free_text {
+            kinds.push(models::QuestionKind::FreeText {
+                matching: input.matching.to_model()?,
+            });
+        }
+
+        if let Some(input) = &self.multiple_choice {
+            kinds.push(models::QuestionKind::MultipleChoice {
+                correct_option: to_index(input.correct_option)?,
+                options: input.options.clone(),
+            });
+        }
+
+        if let Some(input) = &self.multiple_select {
+            kinds.push(models::QuestionKind::MultipleSelect {
+                correct_options: input
+                    .correct_options
+                    .iter()
+                    .map(|o| to_index(*o))
+                    .collect::<Result<_, _>>()?,
+                options: input.options.clone(),
+            });
+        }
+
+        if let Some(input) = &self.numeric {
+            kinds.push(models::QuestionKind::Numeric {
+                tolerance: input.tolerance,
+                value: input.value,
+            });
+        }
+
+        if let Some(input) = &self.ordering {
+            kinds.push(models::QuestionKind::Ordering {
+                items: input.items.clone(),
+            });
+        }
+
+        if let Some(input) = &self.true_false {
+            kinds.push(models::QuestionKind::TrueFalse {
+                correct: input.correct,
+            });
+        }
+
+        if kinds.len() > 1 {
+            return Err("Only one question kind may be set")?;
+        }
+
+        let kind = kinds.pop().unwrap_or_default();
+
+        kind.validate()?;
+
+        Ok(kind)
+    }
+}
+
+fn kind_to_model(kind: &Option<QuestionKindInput>) -> Result<models::QuestionKind, ModelError> {
+    kind.as_ref().map_or(
+        Ok(models::QuestionKind::default()),
+        QuestionKindInput::to_model,
+    )
+}
+
+/// Checks the question, answer and explanation against their formats, and the hints and
+/// points.
+fn validate_content(question: &models::Question) -> Result<(), ModelError> {
+    question
+        .question_format
+        .validate(&question.question)
+        .map_err(|e| format!("Question: {}", e))?;
+
+    question
+        .answer_format
+        .validate(&question.answer)
+        .map_err(|e| format!("Answer: {}", e))?;
+
+    if let Some(explanation) = &question.explanation {
+        question
+            .answer_format
+            .validate(explanation)
+            .map_err(|e| format!("Explanation: {}", e))?;
+    }
+
+    models::Hint::validate_all(&question.hints)?;
+    models::validate_points(question.points)?;
+
+    Ok(())
+}
+
+/// A hint penalty defaults to nothing.
+#[derive(GraphQLInputObject)]
+struct HintInput {
+    penalty: Option<f64>,
+    text: String,
+}
+
+fn hints_to_model(hints: &[HintInput]) -> Vec<models::Hint> {
+    hints
+        .iter()
+        .map(|hint| models::Hint {
+            penalty: hint.penalty.unwrap_or(0.0),
+            text: hint.text.clone(),
+        })
+        .collect()
+}
+
+/// Formats default to `PLAIN`, points to 1 and partial credit to `ALL_OR_NOTHING`. The
+/// explanation is written in the answer's format.
 #[derive(GraphQLInputObject)]
 struct CreateQuestion {
     answer: String,
+    answer_format: Option<ContentFormat>,
+    explanation: Option<String>,
+    hints: Option<Vec<HintInput>>,
+    kind: Option<QuestionKindInput>,
+    partial_credit: Option<PartialCredit>,
+    points: Option<f64>,
     question: String,
+    question_format: Option<ContentFormat>,
     quiz_template_id: String,
 }
 
 impl CreateQuestion {
-    fn to_model(&self) -> Result<models::Question, oid::Error> {
-        Ok(models::Question {
+    fn to_model(&self, position: u32) -> Result<models::Question, ModelError> {
+        let question = models::Question {
             answer: self.answer.clone(),
+            answer_format: ContentFormat::to_model(self.answer_format),
+            attachments: vec![],
+            explanation: self
+                .explanation
+                .clone()
+                .filter(|explanation| !explanation.trim().is_empty()),
+            hints: hints_to_model(self.hints.as_deref().unwrap_or_default()),
             id: ObjectId::new().to_string(),
+            kind: kind_to_model(&self.kind)?,
+            partial_credit: PartialCredit::to_model(self.partial_credit),
+            points: self.points.unwrap_or_else(models::default_points),
+            position,
             question: self.question.clone(),
+            question_format: ContentFormat::to_model(self.question_format),
             quiz_template_id: self.quiz_template_id.clone(),
-        })
+        };
+
+        validate_content(&question)?;
+
+        Ok(question)
     }
 }
 
+/// Optional fields left out keep their stored values. Set the explanation to `null` or the
+/// hints to `null` or `[]` to remove them. The explanation is written in the answer's format.
 #[derive(GraphQLInputObject)]
 struct EditQuestion {
     answer: String,
+    answer_format: Option<ContentFormat>,
+    explanation: Nullable<String>,
+    hints: Nullable<Vec<HintInput>>,
+    kind: Option<QuestionKindInput>,
+    partial_credit: Option<PartialCredit>,
+    points: Option<f64>,
     question: String,
+    question_format: Option<ContentFormat>,
     quiz_template_id: String,
 }
 
 impl EditQuestion {
-    fn to_model(&self, id: &str) -> models::Question {
-        models::Question {
+    fn to_model(&self, existing: &models::Question) -> Result<models::Question, ModelError> {
+        let question = models::Question {
             answer: self.answer.clone(),
-            id: id.to_string(),
+            answer_format: self.answer_format.map_or(existing.answer_format, |format| {
+                ContentFormat::to_model(Some(format))
+            }),
+            explanation: match &self.explanation {
+                Nullable::ImplicitNull => existing.explanation.clone(),
+                Nullable::ExplicitNull => None,
+                Nullable::Some(explanation) => {
+                    Some(explanation.clone()).filter(|explanation| !explanation.trim().is_empty())
+                }
+            },
+            hints: match &self.hints {
+                Nullable::ImplicitNull => existing.hints.clone(),
+                Nullable::ExplicitNull => vec![],
+                Nullable::Some(hints) => hints_to_model(hints),
+            },
+            kind: match &self.kind {
+                Some(kind) => kind.to_model()?,
+                None => existing.kind.clone(),
+            },
+            partial_credit: self
+                .partial_credit
+                .map_or(existing.partial_credit, |policy| {
+                    PartialCredit::to_model(Some(policy))
+                }),
+            points: self.points.unwrap_or(existing.points),
             question: self.question.clone(),
+            question_format: self
+                .question_format
+                .map_or(existing.question_format, |format| {
+                    ContentFormat::to_model(Some(format))
+                }),
             quiz_template_id: self.quiz_template_id.clone(),
-        }
+            ..existing.clone()
+        };
+
+        validate_content(&question)?;
+
+        Ok(question)
     }
 }
 
@@ -63,31 +917,143 @@ pub struct QuestionMutation;
 #[graphql(context = Context)]
 impl QuestionMutation {
     async fn create(&self, context: &Context, input: CreateQuestion) -> FieldResult<Question> {
-        let input_model = input.to_model()?;
+        require_quiz_template_owner(context, &input.quiz_template_id).await?;
+
+        context
+            .quiz_templates
+            .touch_questions(&input.quiz_template_id)
+            .await?;
+
+        let position = context
+            .questions
+            .next_position(&input.quiz_template_id)
+            .await?;
+
+        let input_model = input.to_model(position)?;
 
         let model = context.questions.create(&input_model).await?;
 
+        context
+            .quiz_templates
+            .add_to_question_count(&model.quiz_template_id, 1)
+            .await?;
+
         Ok(Question::from_model(&model))
     }
 
     async fn delete_by_id(&self, context: &Context, id: ID) -> FieldResult<bool> {
-        let result = context.questions.delete_by_id(&id.to_string()).await?;
+        let question = require_question_owner(context, &id.to_string()).await?;
+
+        context
+            .quiz_templates
+            .touch_questions(&question.quiz_template_id)
+            .await?;
+
+        context
+            .attachments
+            .delete_all(&question.attachments)
+            .await?;
+
+        let result = context.questions.delete_by_id(&question.id).await?;
+
+        if result {
+            context
+                .quiz_templates
+                .add_to_question_count(&question.quiz_template_id, -1)
+                .await?;
+        }
 
         Ok(result)
     }
 
-    async fn edit(
+    /// Deletes an attachment and its file.
+    async fn remove_attachment(
         &self,
         context: &Context,
         id: ID,
-        input: EditQuestion,
+        attachment_id: ID,
     ) -> FieldResult<bool> {
-        let input_model = input.to_model(&id.to_string());
+        let question = require_question_owner(context, &id.to_string()).await?;
+
+        let attachment = match question
+            .attachments
+            .iter()
+            .find(|attachment| attachment.id == attachment_id.to_string())
+        {
+            Some(attachment) => attachment,
+            None => return Ok(false),
+        };
+
+        context
+            .attachments
+            .delete_all(std::slice::from_ref(attachment))
+            .await?;
+
+        let result = context
+            .questions
+            .remove_attachment(&question.id, &attachment.id)
+            .await?;
+
+        Ok(result)
+    }
+
+    async fn edit(&self, context: &Context, id: ID, input: EditQuestion) -> FieldResult<bool> {
+        let question = require_question_owner(context, &id.to_string()).await?;
+
+        if input.quiz_template_id != question.quiz_template_id {
+            require_quiz_template_owner(context, &input.quiz_template_id).await?;
+        }
+
+        let input_model = input.to_model(&question)?;
+
+        if input.quiz_template_id != question.quiz_template_id {
+            for quiz_template_id in [&question.quiz_template_id, &input.quiz_template_id] {
+                context
+                    .quiz_templates
+                    .touch_questions(quiz_template_id)
+                    .await?;
+            }
+        }
+
+        let result = context
+            .questions
+            .update_by_id(&question.id, &input_model)
+            .await?;
+
+        if result && input.quiz_template_id != question.quiz_template_id {
+            context
+                .questions
+                .move_to_end(&question.id, &input.quiz_template_id)
+                .await?;
+
+            for (quiz_template_id, change) in [
+                (&question.quiz_template_id, -1),
+                (&input.quiz_template_id, 1),
+            ] {
+                context
+                    .quiz_templates
+                    .add_to_question_count(quiz_template_id, change)
+                    .await?;
+            }
+        }
 
-        let result = context.questions.update_by_id(&id.to_string(), &input_model).await?;
-        
         Ok(result)
     }
+
+    /// Puts the questions of a quiz template in the order of `ids`, which must list each of
+    /// its questions exactly once.
+    async fn reorder(&self, context: &Context, template_id: ID, ids: Vec<ID>) -> FieldResult<bool> {
+        let quiz_template = require_quiz_template_owner(context, &template_id).await?;
+
+        let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        context
+            .questions
+            .reorder(&context.quiz_templates, &quiz_template, &ids)
+            .await?;
+
+        Ok(true)
+    }
 }
 
 pub struct QuestionQuery;
@@ -95,18 +1061,77 @@ pub struct QuestionQuery;
 #[graphql_object]
 #[graphql(context = Context)]
 impl QuestionQuery {
-    async fn all(&self, context: &Context) -> FieldResult<Vec<Question>> {
-        let models = context.questions.read_all().await?;
+    #[allow(clippy::too_many_arguments)]
+    async fn all(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        filter: Option<QuestionFilter>,
+        sort: Option<QuestionSort>,
+    ) -> FieldResult<QuestionConnection> {
+        let page_request = PageRequest::new(first, after, last, before)?
+            .with_sort(sort.map(|sort| sort.to_model()).unwrap_or_default());
+
+        let filter = filter.unwrap_or_default().to_filter(context).await?;
+
+        let page = context.questions.read_page(filter, &page_request).await?;
 
-        Ok(models.iter().map(Question::from_model).collect())
+        Ok(QuestionConnection::from_page(&page))
     }
 
     async fn by_id(&self, context: &Context, id: ID) -> FieldResult<Option<Question>> {
         let model = context.questions.read_by_id(&id.to_string()).await?;
 
-        Ok(match model {
-            Some(model) => Some(Question::from_model(&model)),
-            None => None,
-        })
+        Ok(model.as_ref().map(Question::from_model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reveal_to_owner() {
+        assert!(Reveal::ToOwner.answer(true));
+        assert!(Reveal::ToOwner.explanation(true));
+        assert!(!Reveal::ToOwner.answer(false));
+        assert!(!Reveal::ToOwner.explanation(false));
+    }
+
+    #[test]
+    fn test_reveal_on_card() {
+        let hidden = Reveal::OnCard { revealed: false };
+        let revealed = Reveal::OnCard { revealed: true };
+
+        assert!(!hidden.answer(true));
+        assert!(!hidden.explanation(true));
+        assert!(revealed.answer(false));
+        assert!(revealed.explanation(false));
+    }
+
+    #[test]
+    fn test_reveal_in_attempt() {
+        let unanswered = Reveal::InAttempt {
+            answered: false,
+            graded: false,
+        };
+        let answered = Reveal::InAttempt {
+            answered: true,
+            graded: false,
+        };
+        let graded = Reveal::InAttempt {
+            answered: true,
+            graded: true,
+        };
+
+        assert!(!unanswered.answer(true));
+        assert!(!unanswered.explanation(true));
+        assert!(!answered.answer(true));
+        assert!(answered.explanation(false));
+        assert!(graded.answer(false));
+        assert!(graded.explanation(false));
     }
 }
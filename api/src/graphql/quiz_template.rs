@@ -1,25 +1,42 @@
 use std::str::FromStr;
 
-use juniper::{graphql_object, FieldResult, GraphQLInputObject, ID};
-use mongodb::bson::doc;
+use base64::prelude::*;
+use juniper::{
+    graphql_object, FieldResult, GraphQLEnum, GraphQLInputObject, GraphQLObject, Nullable, ID,
+};
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, DateTime};
 
-use super::question::Question;
+use super::import::{import_bundle, BundleImportResult};
+use super::pagination::{PageInfo, SortDirection};
+use super::question::{QuestionConnection, QuestionFilter, QuestionSort};
+use super::User;
 
+use crate::authorization::{
+    read_exportable, require_permission, require_quiz_template_owner, Permission,
+};
 use crate::context::Context;
-use crate::models::{self, BaseModel};
+use crate::formats::{self, bundle::Bundle};
+use crate::models::{self, BaseModel, Filter, ModelError, Page, PageRequest, Sort};
 
-struct QuizTemplate {
+pub struct QuizTemplate {
     id: ID,
     name: String,
+    question_time_limit_seconds: Option<u32>,
+    scoring: models::Scoring,
+    time_limit_seconds: Option<u32>,
+    user_id: String,
 }
 
 impl QuizTemplate {
-    fn from_model(model: &models::QuizTemplate) -> Self {
-        println!("{:?}", model);
+    pub fn from_model(model: &models::QuizTemplate) -> Self {
         Self {
             id: model.id.to_string().into(),
             name: model.name.clone(),
+            question_time_limit_seconds: model.question_time_limit_seconds,
+            scoring: model.scoring.clone(),
+            time_limit_seconds: model.time_limit_seconds,
+            user_id: model.user_id.clone(),
         }
     }
 }
@@ -35,49 +52,270 @@ impl QuizTemplate {
         self.name.as_str()
     }
 
-    async fn questions(&self, context: &Context) -> FieldResult<Vec<Question>> {
-        let id = ObjectId::from_str(&self.id.to_string())?;
+    /// Seconds allowed for each question, counted from when it is opened.
+    fn question_time_limit_seconds(&self) -> Option<i32> {
+        self.question_time_limit_seconds
+            .map(|seconds| seconds as i32)
+    }
 
-        let results = context
-            .questions
-            .read_by_filter(doc! {
-                "quiz_template_id": id,
-            })
-            .await?;
+    /// Seconds allowed for a whole attempt.
+    fn time_limit_seconds(&self) -> Option<i32> {
+        self.time_limit_seconds.map(|seconds| seconds as i32)
+    }
+
+    /// Percentage of the available points needed to pass an attempt.
+    fn pass_mark(&self) -> Option<f64> {
+        self.scoring.pass_mark
+    }
+
+    /// Decimal places that attempt scores are rounded to.
+    fn score_decimals(&self) -> i32 {
+        self.scoring.score_decimals as i32
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn questions(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        filter: Option<QuestionFilter>,
+        sort: Option<QuestionSort>,
+    ) -> FieldResult<QuestionConnection> {
+        let page_request = PageRequest::new(first, after, last, before)?.with_sort(
+            sort.map(|sort| sort.to_model())
+                .unwrap_or_else(QuestionSort::position),
+        );
+
+        // First pages of plain listings go through the loader so that a list of templates costs
+        // one query.
+        if filter.is_none()
+            && page_request.is_first_page()
+            && page_request.sort == QuestionSort::position()
+        {
+            let questions = context
+                .first_questions_by_quiz_template_id
+                .load(self.id.to_string())
+                .await?;
 
-        Ok(results.iter().map(Question::from_model).collect())
+            let page = page_request.page_loaded(questions)?;
+
+            return Ok(QuestionConnection::from_page(&page));
+        }
+
+        let filter = filter
+            .unwrap_or_default()
+            .to_filter(context)
+            .await?
+            .id_equals("quiz_template_id", &self.id)?;
+
+        let page = context.questions.read_page(filter, &page_request).await?;
+
+        Ok(QuestionConnection::from_page(&page))
+    }
+
+    async fn owner(&self, context: &Context) -> FieldResult<Option<User>> {
+        let model = context.users_by_id.load(self.user_id.clone()).await?;
+
+        Ok(model.as_ref().map(User::from_model))
     }
 }
 
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuizTemplateEdge {
+    cursor: String,
+    node: QuizTemplate,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuizTemplateConnection {
+    edges: Vec<QuizTemplateEdge>,
+    page_info: PageInfo,
+}
+
+impl QuizTemplateConnection {
+    fn from_page(page: &Page<models::QuizTemplate>) -> Self {
+        Self {
+            edges: page
+                .edges
+                .iter()
+                .map(|(cursor, model)| QuizTemplateEdge {
+                    cursor: cursor.clone(),
+                    node: QuizTemplate::from_model(model),
+                })
+                .collect(),
+            page_info: PageInfo::from_page(page),
+        }
+    }
+}
+
+/// Every field that is set must match.
+#[derive(Default, GraphQLInputObject)]
+struct QuizTemplateFilter {
+    created_after: Option<DateTime>,
+    created_before: Option<DateTime>,
+    has_questions: Option<bool>,
+    name_contains: Option<String>,
+    owner: Option<ID>,
+}
+
+impl QuizTemplateFilter {
+    fn to_filter(&self) -> FieldResult<Filter> {
+        let mut filter = Filter::new();
+
+        if let Some(time) = self.created_after {
+            filter = filter.created_after(time);
+        }
+
+        if let Some(time) = self.created_before {
+            filter = filter.created_before(time);
+        }
+
+        if let Some(has_questions) = self.has_questions {
+            filter = if has_questions {
+                filter.at_least("question_count", 1)
+            } else {
+                filter.at_most("question_count", 0)
+            };
+        }
+
+        if let Some(text) = &self.name_contains {
+            filter = filter.contains("name", text)?;
+        }
+
+        if let Some(owner) = &self.owner {
+            filter = filter.id_equals("user_id", owner)?;
+        }
+
+        Ok(filter)
+    }
+}
+
+#[derive(Clone, Copy, GraphQLEnum)]
+enum QuizTemplateSortField {
+    CreatedAt,
+    Name,
+}
+
+#[derive(GraphQLInputObject)]
+struct QuizTemplateSort {
+    direction: Option<SortDirection>,
+    field: QuizTemplateSortField,
+}
+
+impl QuizTemplateSort {
+    fn to_model(&self) -> Sort {
+        let field = match self.field {
+            QuizTemplateSortField::CreatedAt => "_id",
+            QuizTemplateSortField::Name => "name",
+        };
+
+        Sort {
+            direction: self
+                .direction
+                .unwrap_or(SortDirection::Ascending)
+                .to_model(),
+            field: field.to_string(),
+        }
+    }
+}
+
+fn to_seconds(value: i32) -> Result<u32, ModelError> {
+    u32::try_from(value)
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .ok_or_else(|| ModelError::from("Time limits must be positive"))
+}
+
+fn to_scoring(pass_mark: Option<f64>, score_decimals: i32) -> Result<models::Scoring, ModelError> {
+    let scoring = models::Scoring {
+        pass_mark,
+        score_decimals: u32::try_from(score_decimals)
+            .map_err(|_| ModelError::from("Score decimals cannot be negative"))?,
+    };
+
+    scoring.validate()?;
+
+    Ok(scoring)
+}
+
 #[derive(GraphQLInputObject)]
 struct CreateQuizTemplate {
     name: String,
-    user_id: String,
+    /// Percentage needed to pass. Attempts are neither passed nor failed without one.
+    pass_mark: Option<f64>,
+    question_time_limit_seconds: Option<i32>,
+    /// Decimal places that scores are rounded to. Defaults to 2.
+    score_decimals: Option<i32>,
+    time_limit_seconds: Option<i32>,
 }
 
 impl CreateQuizTemplate {
-    fn to_model(&self) -> models::QuizTemplate {
-        models::QuizTemplate {
+    fn to_model(&self, user_id: &str) -> Result<models::QuizTemplate, ModelError> {
+        Ok(models::QuizTemplate {
             id: ObjectId::new().to_string(),
             name: self.name.clone(),
-            user_id: self.user_id.clone(),
-        }
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds: self
+                .question_time_limit_seconds
+                .map(to_seconds)
+                .transpose()?,
+            scoring: to_scoring(
+                self.pass_mark,
+                self.score_decimals
+                    .unwrap_or(models::Scoring::default().score_decimals as i32),
+            )?,
+            time_limit_seconds: self.time_limit_seconds.map(to_seconds).transpose()?,
+            user_id: user_id.to_string(),
+        })
     }
 }
 
+/// Time limits and scoring settings left out are kept; set time limits or the pass mark to
+/// `null` to remove them.
 #[derive(GraphQLInputObject)]
 struct EditQuizTemplate {
     name: String,
-    user_id: String,
+    pass_mark: Nullable<f64>,
+    question_time_limit_seconds: Nullable<i32>,
+    score_decimals: Option<i32>,
+    time_limit_seconds: Nullable<i32>,
 }
 
 impl EditQuizTemplate {
-    fn to_model(&self, id: &str) -> models::QuizTemplate {
-        models::QuizTemplate {
-            id: id.to_string(),
+    fn to_model(
+        &self,
+        existing: &models::QuizTemplate,
+    ) -> Result<models::QuizTemplate, ModelError> {
+        let edit = |value: &Nullable<i32>, existing: Option<u32>| match value {
+            Nullable::ImplicitNull => Ok(existing),
+            Nullable::ExplicitNull => Ok(None),
+            Nullable::Some(value) => to_seconds(*value).map(Some),
+        };
+
+        Ok(models::QuizTemplate {
             name: self.name.clone(),
-            user_id: self.user_id.clone(),
-        }
+            question_time_limit_seconds: edit(
+                &self.question_time_limit_seconds,
+                existing.question_time_limit_seconds,
+            )?,
+            scoring: to_scoring(
+                match self.pass_mark {
+                    Nullable::ImplicitNull => existing.scoring.pass_mark,
+                    Nullable::ExplicitNull => None,
+                    Nullable::Some(pass_mark) => Some(pass_mark),
+                },
+                self.score_decimals
+                    .unwrap_or(existing.scoring.score_decimals as i32),
+            )?,
+            time_limit_seconds: edit(&self.time_limit_seconds, existing.time_limit_seconds)?,
+            ..existing.clone()
+        })
     }
 }
 
@@ -91,7 +329,9 @@ impl QuizTemplateMutation {
         context: &Context,
         input: CreateQuizTemplate,
     ) -> FieldResult<QuizTemplate> {
-        let input_model = input.to_model();
+        let user = require_permission(context, Permission::AuthorQuizzes)?;
+
+        let input_model = input.to_model(&user.id)?;
 
         let model = context.quiz_templates.create(&input_model).await?;
 
@@ -99,24 +339,53 @@ impl QuizTemplateMutation {
     }
 
     async fn delete_by_id(&self, context: &Context, id: ID) -> FieldResult<bool> {
+        let quiz_template = require_quiz_template_owner(context, &id.to_string()).await?;
+
+        for question in context
+            .questions
+            .read_by_quiz_template_id(&quiz_template.id)
+            .await?
+        {
+            context
+                .attachments
+                .delete_all(&question.attachments)
+                .await?;
+        }
+
         context
             .questions
             .delete_by_filter(doc! {
-                "quiz_template_id": &id.to_string(),
+                "quiz_template_id": ObjectId::from_str(&quiz_template.id)?,
             })
             .await?;
 
-        let result = context.quiz_templates.delete_by_id(&id.to_string()).await?;
+        let result = context
+            .quiz_templates
+            .delete_by_id(&quiz_template.id)
+            .await?;
 
         Ok(result)
     }
 
+    /// Recreates the quiz templates of a bundle made by `export`, with new ids, under the
+    /// signed in user. Nothing is created if any part of the bundle is invalid, or on a dry run.
+    async fn import(
+        &self,
+        context: &Context,
+        bundle: String,
+        dry_run: Option<bool>,
+    ) -> FieldResult<BundleImportResult> {
+        import_bundle(context, &bundle, dry_run.unwrap_or(false)).await
+    }
+
     async fn edit(&self, context: &Context, id: ID, input: EditQuizTemplate) -> FieldResult<bool> {
-        let input_model = input.to_model(&id.to_string());
+        let quiz_template = require_quiz_template_owner(context, &id.to_string()).await?;
+
+        let input_model = input.to_model(&quiz_template)?;
 
         let result = context
             .quiz_templates
-            .update_by_id(&id.to_string(), &input_model)
+            .update_by_id(&quiz_template.id, &input_model)
             .await?;
 
         Ok(result)
@@ -128,29 +397,87 @@ pub struct QuizTemplateQuery;
 #[graphql_object]
 #[graphql(context = Context)]
 impl QuizTemplateQuery {
-    async fn all(&self, context: &Context) -> FieldResult<Vec<QuizTemplate>> {
-        let models = context.quiz_templates.read_all().await?;
+    #[allow(clippy::too_many_arguments)]
+    async fn all(
+        &self,
+        context: &Context,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        filter: Option<QuizTemplateFilter>,
+        sort: Option<QuizTemplateSort>,
+    ) -> FieldResult<QuizTemplateConnection> {
+        let page_request = PageRequest::new(first, after, last, before)?
+            .with_sort(sort.map(|sort| sort.to_model()).unwrap_or_default());
+
+        let filter = filter.unwrap_or_default().to_filter()?;
 
-        Ok(models.iter().map(QuizTemplate::from_model).collect())
+        let page = context
+            .quiz_templates
+            .read_page(filter, &page_request)
+            .await?;
+
+        Ok(QuizTemplateConnection::from_page(&page))
     }
 
     async fn by_id(&self, context: &Context, id: ID) -> FieldResult<Option<QuizTemplate>> {
         let model = context.quiz_templates.read_by_id(&id.to_string()).await?;
 
-        Ok(match model {
-            Some(model) => Some(QuizTemplate::from_model(&model)),
-            None => None,
-        })
+        Ok(model.as_ref().map(QuizTemplate::from_model))
     }
 
-    async fn by_user_id(&self, context: &Context, user_id: ID) -> FieldResult<Vec<QuizTemplate>> {
-        let models = context
+    /// A versioned JSON bundle of the quiz templates and their questions, for `import`.
+    async fn export(&self, context: &Context, ids: Vec<ID>) -> FieldResult<String> {
+        let mut quiz_templates = vec![];
+
+        for id in ids {
+            quiz_templates.push(read_exportable(context, &id).await?);
+        }
+
+        Ok(Bundle::new(&quiz_templates).to_json()?)
+    }
+
+    /// The questions of a quiz template as CSV, for `import.csv`.
+    async fn export_csv(&self, context: &Context, id: ID) -> FieldResult<String> {
+        let (_, questions) = read_exportable(context, &id).await?;
+
+        Ok(formats::csv::export(&questions)?)
+    }
+
+    /// A base64 encoded IMS QTI 2.1 package of a quiz template, for learning management
+    /// systems and `import.qti`.
+    async fn export_qti(&self, context: &Context, id: ID) -> FieldResult<String> {
+        let (quiz_template, questions) = read_exportable(context, &id).await?;
+
+        Ok(BASE64_STANDARD.encode(formats::qti::export(&quiz_template, &questions)?))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn by_user_id(
+        &self,
+        context: &Context,
+        user_id: ID,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        filter: Option<QuizTemplateFilter>,
+        sort: Option<QuizTemplateSort>,
+    ) -> FieldResult<QuizTemplateConnection> {
+        let page_request = PageRequest::new(first, after, last, before)?
+            .with_sort(sort.map(|sort| sort.to_model()).unwrap_or_default());
+
+        let filter = filter
+            .unwrap_or_default()
+            .to_filter()?
+            .id_equals("user_id", &user_id)?;
+
+        let page = context
             .quiz_templates
-            .read_by_filter(doc! {
-                "user_id": ObjectId::from_str(&user_id.to_string())?,
-            })
+            .read_page(filter, &page_request)
             .await?;
 
-        Ok(models.iter().map(QuizTemplate::from_model).collect())
+        Ok(QuizTemplateConnection::from_page(&page))
     }
 }
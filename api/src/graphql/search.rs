@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use juniper::{FieldResult, GraphQLObject, GraphQLUnion};
+use mongodb::bson::{doc, oid::ObjectId};
+
+use super::{question::Question, quiz_template::QuizTemplate};
+
+use crate::authorization::{has_permission, require_user, Permission};
+use crate::context::Context;
+use crate::models::{self, BaseModel, Filter, ModelError};
+
+/// Most results returned from each collection.
+const SEARCH_LIMIT: i64 = 25;
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuizTemplateHit {
+    quiz_template: QuizTemplate,
+    /// Relevance of the match. Higher is better.
+    score: f64,
+}
+
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct QuestionHit {
+    question: Box<Question>,
+    quiz_template: QuizTemplate,
+    /// Relevance of the match. Higher is better.
+    score: f64,
+}
+
+#[derive(GraphQLUnion)]
+#[graphql(context = Context)]
+pub enum SearchHit {
+    Question(QuestionHit),
+    QuizTemplate(QuizTemplateHit),
+}
+
+impl SearchHit {
+    fn score(&self) -> f64 {
+        match self {
+            Self::Question(hit) => hit.score,
+            Self::QuizTemplate(hit) => hit.score,
+        }
+    }
+}
+
+/// Filters for the quiz templates and questions `user` may find. `own_ids` are the ids of the
+/// user's quiz templates.
+fn scope(user: &models::User, own_ids: Vec<ObjectId>) -> Result<(Filter, Filter), ModelError> {
+    if has_permission(user, Permission::ViewAllContent) {
+        return Ok((Filter::new(), Filter::new()));
+    }
+
+    Ok((
+        Filter::new().id_equals("user_id", &user.id)?,
+        Filter::new().id_in("quiz_template_id", own_ids),
+    ))
+}
+
+/// Searches quiz template names and question text and answers, best matches first. Callers see
+/// their own content, or everything with the `VIEW_ALL_CONTENT` permission.
+pub async fn search(context: &Context, query: &str) -> FieldResult<Vec<SearchHit>> {
+    let user = require_user(context)?;
+
+    if query.trim().is_empty() {
+        return Err("Search query must not be empty")?;
+    }
+
+    let own_ids = if has_permission(user, Permission::ViewAllContent) {
+        vec![]
+    } else {
+        context
+            .quiz_templates
+            .read_by_filter(doc! {
+                "user_id": ObjectId::from_str(&user.id)?,
+            })
+            .await?
+            .iter()
+            .map(|quiz_template| ObjectId::from_str(&quiz_template.id))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let (quiz_template_filter, question_filter) = scope(user, own_ids)?;
+
+    let quiz_templates = context
+        .quiz_templates
+        .read_by_text(query, quiz_template_filter, SEARCH_LIMIT)
+        .await?;
+
+    let questions = context
+        .questions
+        .read_by_text(query, question_filter, SEARCH_LIMIT)
+        .await?;
+
+    let parent_ids = questions
+        .iter()
+        .map(|(_, question)| ObjectId::from_str(&question.quiz_template_id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let parents = context
+        .quiz_templates
+        .read_by_filter(doc! {
+            "_id": { "$in": parent_ids },
+        })
+        .await?
+        .into_iter()
+        .map(|quiz_template| (quiz_template.id.clone(), quiz_template))
+        .collect::<HashMap<String, models::QuizTemplate>>();
+
+    let mut hits = quiz_templates
+        .iter()
+        .map(|(score, quiz_template)| {
+            SearchHit::QuizTemplate(QuizTemplateHit {
+                quiz_template: QuizTemplate::from_model(quiz_template),
+                score: *score,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    hits.extend(questions.iter().filter_map(|(score, question)| {
+        let parent = parents.get(&question.quiz_template_id)?;
+
+        Some(SearchHit::Question(QuestionHit {
+            question: Box::new(Question::from_model(question)),
+            quiz_template: QuizTemplate::from_model(parent),
+            score: *score,
+        }))
+    }));
+
+    hits.sort_by(|a, b| b.score().total_cmp(&a.score()));
+
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Role;
+
+    fn user(roles: Vec<Role>) -> models::User {
+        models::User {
+            id: ObjectId::new().to_string(),
+            roles,
+            username: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_scope() {
+        let author = user(vec![Role::Author]);
+        let own_id = ObjectId::new();
+
+        let (quiz_templates, questions) = scope(&author, vec![own_id]).unwrap();
+
+        assert_eq!(
+            doc! { "user_id": ObjectId::from_str(&author.id).unwrap() },
+            quiz_templates.build()
+        );
+        assert_eq!(
+            doc! { "quiz_template_id": { "$in": [own_id] } },
+            questions.build()
+        );
+
+        let (quiz_templates, questions) = scope(&user(vec![Role::Admin]), vec![]).unwrap();
+
+        assert!(quiz_templates.build().is_empty());
+        assert!(questions.build().is_empty());
+    }
+}
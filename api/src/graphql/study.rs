@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use juniper::{graphql_object, FieldResult, GraphQLObject, ID};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+
+use super::question::Question;
+
+use crate::authorization::{require_permission, AuthorizationError, Permission};
+use crate::context::Context;
+use crate::models::{self, end_of_day, BaseModel};
+
+const DEFAULT_DUE_LIMIT: i32 = 20;
+
+const MAX_DUE_LIMIT: i32 = 100;
+
+/// A question together with the signed in user's review schedule for it. The answer stays
+/// hidden until the card is revealed or graded.
+#[derive(GraphQLObject)]
+#[graphql(context = Context)]
+pub struct StudyCard {
+    due_at: DateTime,
+    ease_factor: f64,
+    interval_days: i32,
+    /// Whether the card has never been graded.
+    is_new: bool,
+    question: Question,
+    quiz_template_id: ID,
+    /// Successful reviews in a row.
+    repetitions: i32,
+}
+
+impl StudyCard {
+    fn from_model(question: &models::Question, review: &models::Review, revealed: bool) -> Self {
+        Self {
+            due_at: review.due_at,
+            ease_factor: review.ease_factor,
+            interval_days: review.interval_days as i32,
+            is_new: review.is_new(),
+            question: Question::on_card(question, revealed),
+            quiz_template_id: question.quiz_template_id.clone().into(),
+            repetitions: review.repetitions as i32,
+        }
+    }
+}
+
+/// Refuses quiz templates that do not exist. Any learner may study any quiz template, as the
+/// answer on each card stays hidden until the card is revealed.
+async fn require_studyable(context: &Context, quiz_template_id: &str) -> FieldResult<()> {
+    context
+        .quiz_templates_by_id
+        .load(quiz_template_id.to_string())
+        .await?
+        .ok_or(AuthorizationError::NotFound)?;
+
+    Ok(())
+}
+
+/// Reads a question the user may study with their review of it, if they have one.
+async fn read_card(
+    context: &Context,
+    user: &models::User,
+    question_id: &ID,
+) -> FieldResult<(models::Question, Option<models::Review>)> {
+    let question = context
+        .questions
+        .read_by_id(&question_id.to_string())
+        .await?
+        .ok_or(AuthorizationError::NotFound)?;
+
+    require_studyable(context, &question.quiz_template_id).await?;
+
+    let review = context
+        .reviews
+        .read_by_user_id(&user.id, std::slice::from_ref(&question.id))
+        .await?
+        .pop();
+
+    Ok((question, review))
+}
+
+pub struct StudyMutation;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl StudyMutation {
+    /// Records how well a card was recalled, from 0 (blackout) to 5 (perfect), and schedules
+    /// its next review.
+    async fn grade(
+        &self,
+        context: &Context,
+        question_id: ID,
+        quality: i32,
+    ) -> FieldResult<StudyCard> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let quality = u8::try_from(quality)
+            .map_err(|_| format!("Quality must be between 0 and {}", models::MAX_QUALITY))?;
+
+        let (question, existing) = read_card(context, user, &question_id).await?;
+
+        let mut review = existing
+            .clone()
+            .unwrap_or_else(|| models::Review::new(&user.id, &question));
+
+        review.grade(quality, DateTime::now())?;
+
+        match existing {
+            Some(_) => {
+                context.reviews.update_by_id(&review.id, &review).await?;
+            }
+            None => {
+                review = context.reviews.create(&review).await?;
+            }
+        }
+
+        Ok(StudyCard::from_model(&question, &review, true))
+    }
+
+    /// Shows the answer of a card before it is graded.
+    async fn reveal(&self, context: &Context, question_id: ID) -> FieldResult<StudyCard> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let (question, review) = read_card(context, user, &question_id).await?;
+
+        let review = review.unwrap_or_else(|| models::Review::new(&user.id, &question));
+
+        Ok(StudyCard::from_model(&question, &review, true))
+    }
+}
+
+pub struct StudyQuery;
+
+#[graphql_object]
+#[graphql(context = Context)]
+impl StudyQuery {
+    /// Cards from the given quiz templates that are due by the end of today (UTC), most overdue
+    /// first, with their answers hidden. Cards never studied before are due straight away.
+    async fn due(
+        &self,
+        context: &Context,
+        quiz_template_ids: Vec<ID>,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<StudyCard>> {
+        let user = require_permission(context, Permission::TakeQuizzes)?;
+
+        let limit = limit.unwrap_or(DEFAULT_DUE_LIMIT);
+
+        if !(1..=MAX_DUE_LIMIT).contains(&limit) {
+            return Err(format!("Limit must be between 1 and {}", MAX_DUE_LIMIT))?;
+        }
+
+        for id in &quiz_template_ids {
+            require_studyable(context, id).await?;
+        }
+
+        let quiz_template_ids = quiz_template_ids
+            .iter()
+            .map(|id| ObjectId::from_str(id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let questions = context
+            .questions
+            .read_by_filter(doc! {
+                "quiz_template_id": { "$in": quiz_template_ids },
+            })
+            .await?;
+
+        let question_ids = questions
+            .iter()
+            .map(|question| question.id.clone())
+            .collect::<Vec<_>>();
+
+        let mut reviews = context
+            .reviews
+            .read_by_user_id(&user.id, &question_ids)
+            .await?
+            .into_iter()
+            .map(|review| (review.question_id.clone(), review))
+            .collect::<HashMap<_, _>>();
+
+        let due_before = end_of_day(DateTime::now());
+
+        let mut cards = questions
+            .iter()
+            .map(|question| {
+                let review = reviews
+                    .remove(&question.id)
+                    .unwrap_or_else(|| models::Review::new(&user.id, question));
+
+                (question, review)
+            })
+            .filter(|(_, review)| review.due_at < due_before)
+            .collect::<Vec<_>>();
+
+        cards.sort_by_key(|(question, review)| (review.due_at, question.id.clone()));
+
+        Ok(cards
+            .iter()
+            .take(limit as usize)
+            .map(|(question, review)| StudyCard::from_model(question, review, false))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_of_another_users_template() {
+        let question = models::Question::fixture(
+            &ObjectId::new().to_string(),
+            "Capital of France?",
+            "Paris",
+            models::QuestionKind::default(),
+        );
+
+        let review = models::Review::new(&ObjectId::new().to_string(), &question);
+
+        let hidden = StudyCard::from_model(&question, &review, false);
+        let revealed = StudyCard::from_model(&question, &review, true);
+
+        assert!(!hidden.question.shows_answer_to_learners());
+        assert!(revealed.question.shows_answer_to_learners());
+    }
+}
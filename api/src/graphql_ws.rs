@@ -0,0 +1,387 @@
+use std::{collections::HashMap, io, pin::Pin, sync::Arc};
+
+use juniper::{
+    http::GraphQLRequest, DefaultScalarValue, ExecutionError, GraphQLError, Object, Value,
+};
+use rocket::{
+    data::{IoHandler, IoStream},
+    futures::{
+        channel::mpsc::{unbounded, UnboundedSender},
+        future::{abortable, AbortHandle},
+        stream::{select_all, FuturesUnordered},
+        SinkExt, StreamExt,
+    },
+    http::Status,
+    request::{FromRequest, Outcome},
+    response::{self, Responder},
+    tokio::select,
+    Request, Response,
+};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{frame::coding::CloseCode, CloseFrame, Role},
+        Message,
+    },
+    WebSocketStream,
+};
+
+use crate::{context::Context, graphql::Schema};
+
+/// The `graphql-transport-ws` protocol spoken by GraphiQL, the playground and most clients.
+const PROTOCOL: &str = "graphql-transport-ws";
+
+/// Subscriptions one socket may run at once.
+const MAX_SUBSCRIPTIONS: usize = 20;
+
+/// Close code the protocol uses when a subscribe reuses the id of a running subscription.
+const SUBSCRIBER_EXISTS: u16 = 4409;
+
+/// Close code the protocol uses when a subscribe arrives before the connection was acknowledged.
+const UNAUTHORIZED: u16 = 4401;
+
+/// Close code the protocol uses when a client sends a second `connection_init`.
+const TOO_MANY_INITIALISATION_REQUESTS: u16 = 4429;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+enum ClientMessage {
+    ConnectionInit,
+    Ping,
+    Pong,
+    Subscribe { id: String, payload: GraphQLRequest },
+    Complete { id: String },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+enum ServerMessage {
+    ConnectionAck,
+    Pong,
+    Next {
+        id: String,
+        payload: serde_json::Value,
+    },
+    Error {
+        id: String,
+        payload: serde_json::Value,
+    },
+    Complete {
+        id: String,
+    },
+}
+
+/// Origins besides the server's own that may open subscription sockets.
+pub struct AllowedOrigins(pub Vec<String>);
+
+/// Sockets are authenticated by the session cookie, which browsers send whichever site opened
+/// the socket, so a browser's `Origin` must be the server itself or one of `allowed`. Clients
+/// that send no `Origin` are not browsers and cannot borrow anyone's cookie.
+fn is_allowed_origin(origin: Option<&str>, host: Option<&str>, allowed: &[String]) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+
+    let same_host = origin
+        .split_once("://")
+        .is_some_and(|(_, origin_host)| Some(origin_host) == host);
+
+    same_host || allowed.iter().any(|allowed| allowed == origin)
+}
+
+/// Request guard for a websocket handshake.
+pub struct WebSocketUpgrade {
+    key: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WebSocketUpgrade {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        let is_upgrade = headers
+            .get_one("Upgrade")
+            .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+
+        let allowed = request
+            .rocket()
+            .state::<AllowedOrigins>()
+            .map(|origins| origins.0.as_slice())
+            .unwrap_or_default();
+
+        if !is_allowed_origin(headers.get_one("Origin"), headers.get_one("Host"), allowed) {
+            return Outcome::Error((Status::Forbidden, ()));
+        }
+
+        match headers.get_one("Sec-WebSocket-Key") {
+            Some(key) if is_upgrade => Outcome::Success(Self {
+                key: key.to_string(),
+            }),
+            _ => Outcome::Error((Status::BadRequest, ())),
+        }
+    }
+}
+
+/// Serves GraphQL subscriptions over a websocket until the client goes away.
+pub struct SubscriptionSocket {
+    context: Context,
+    schema: Arc<Schema>,
+    upgrade: WebSocketUpgrade,
+}
+
+impl SubscriptionSocket {
+    pub fn new(context: Context, schema: Arc<Schema>, upgrade: WebSocketUpgrade) -> Self {
+        Self {
+            context,
+            schema,
+            upgrade,
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for SubscriptionSocket {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let accept = derive_accept_key(self.upgrade.key.as_bytes());
+
+        Response::build()
+            .raw_header("Sec-WebSocket-Accept", accept)
+            .raw_header("Sec-WebSocket-Protocol", PROTOCOL)
+            .upgrade("websocket", self)
+            .ok()
+    }
+}
+
+fn to_json<T: Serialize>(value: T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+fn errors_payload(errors: &[ExecutionError<DefaultScalarValue>]) -> serde_json::Value {
+    to_json(errors)
+}
+
+enum Output {
+    Next(String, serde_json::Value),
+    Error(String, serde_json::Value),
+    Complete(String),
+}
+
+/// Runs one subscription, sending a `next` for every event of every root field.
+async fn run(
+    id: String,
+    request: GraphQLRequest,
+    schema: &Schema,
+    context: &Context,
+    outputs: UnboundedSender<Output>,
+) {
+    let (value, errors) = match juniper::http::resolve_into_stream(&request, schema, context).await
+    {
+        Ok(result) => result,
+        Err(error) => {
+            let error: GraphQLError = error;
+
+            let _ = outputs.unbounded_send(Output::Error(id, to_json([error.to_string()])));
+
+            return;
+        }
+    };
+
+    if !errors.is_empty() {
+        let _ = outputs.unbounded_send(Output::Error(id, errors_payload(&errors)));
+
+        return;
+    }
+
+    let fields = match value {
+        Value::Object(object) => object.into_iter().collect::<Vec<_>>(),
+        _ => vec![],
+    };
+
+    let streams = fields.into_iter().filter_map(|(name, value)| match value {
+        Value::Scalar(stream) => Some(stream.map(move |item| (name.clone(), item))),
+        _ => None,
+    });
+
+    let mut events = select_all(streams);
+
+    while let Some((name, item)) = events.next().await {
+        let payload = match item {
+            Ok(value) => {
+                let mut data = Object::with_capacity(1);
+
+                data.add_field(name, value);
+
+                serde_json::json!({ "data": to_json(Value::Object(data)) })
+            }
+            Err(error) => serde_json::json!({
+                "data": null,
+                "errors": errors_payload(&[error]),
+            }),
+        };
+
+        if outputs
+            .unbounded_send(Output::Next(id.clone(), payload))
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    let _ = outputs.unbounded_send(Output::Complete(id));
+}
+
+fn encode(message: ServerMessage) -> Message {
+    Message::Text(serde_json::to_string(&message).unwrap_or_default())
+}
+
+#[rocket::async_trait]
+impl IoHandler for SubscriptionSocket {
+    async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+        let Self {
+            context, schema, ..
+        } = *Pin::into_inner(self);
+
+        let mut socket = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+
+        let (sender, mut outputs) = unbounded();
+
+        let mut running = FuturesUnordered::new();
+        let mut handles: HashMap<String, AbortHandle> = HashMap::new();
+
+        let mut acknowledged = false;
+
+        loop {
+            let reply = select! {
+                message = socket.next() => {
+                    let text = match message {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => break,
+                        Some(Ok(_)) => continue,
+                        Some(Err(error)) => {
+                            eprintln!("Websocket error: {:?}", error);
+
+                            break;
+                        }
+                    };
+
+                    match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::ConnectionInit) if acknowledged => {
+                            Some(Message::Close(Some(CloseFrame {
+                                code: CloseCode::from(TOO_MANY_INITIALISATION_REQUESTS),
+                                reason: "Too many initialisation requests".into(),
+                            })))
+                        }
+                        Ok(ClientMessage::ConnectionInit) => {
+                            acknowledged = true;
+
+                            Some(encode(ServerMessage::ConnectionAck))
+                        }
+                        Ok(ClientMessage::Ping) => Some(encode(ServerMessage::Pong)),
+                        Ok(ClientMessage::Pong) => None,
+                        Ok(ClientMessage::Subscribe { .. }) if !acknowledged => {
+                            Some(Message::Close(Some(CloseFrame {
+                                code: CloseCode::from(UNAUTHORIZED),
+                                reason: "Unauthorized".into(),
+                            })))
+                        }
+                        Ok(ClientMessage::Subscribe { id, .. }) if handles.contains_key(&id) => {
+                            Some(Message::Close(Some(CloseFrame {
+                                code: CloseCode::from(SUBSCRIBER_EXISTS),
+                                reason: format!("Subscriber for {} already exists", id).into(),
+                            })))
+                        }
+                        Ok(ClientMessage::Subscribe { id, .. })
+                            if handles.len() >= MAX_SUBSCRIPTIONS =>
+                        {
+                            Some(encode(ServerMessage::Error {
+                                id,
+                                payload: to_json(["Too many subscriptions on this socket"]),
+                            }))
+                        }
+                        Ok(ClientMessage::Subscribe { id, payload }) => {
+                            let (task, handle) = abortable(run(
+                                id.clone(),
+                                payload,
+                                &schema,
+                                &context,
+                                sender.clone(),
+                            ));
+
+                            handles.insert(id, handle);
+
+                            running.push(task);
+
+                            None
+                        }
+                        Ok(ClientMessage::Complete { id }) => {
+                            if let Some(handle) = handles.remove(&id) {
+                                handle.abort();
+                            }
+
+                            None
+                        }
+                        Err(_) => break,
+                    }
+                }
+                Some(output) = outputs.next() => match output {
+                    Output::Next(id, payload) => Some(encode(ServerMessage::Next { id, payload })),
+                    Output::Error(id, payload) => {
+                        handles.remove(&id);
+
+                        Some(encode(ServerMessage::Error { id, payload }))
+                    }
+                    Output::Complete(id) => {
+                        handles.remove(&id);
+
+                        Some(encode(ServerMessage::Complete { id }))
+                    }
+                },
+                Some(_) = running.next(), if !running.is_empty() => None,
+            };
+
+            if let Some(reply) = reply {
+                let closing = reply.is_close();
+
+                if socket.send(reply).await.is_err() || closing {
+                    break;
+                }
+            }
+        }
+
+        for handle in handles.values() {
+            handle.abort();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_origin() {
+        let allowed = vec!["http://localhost:5173".to_string()];
+
+        assert!(is_allowed_origin(None, Some("api:8000"), &allowed));
+        assert!(is_allowed_origin(
+            Some("https://quiz.example"),
+            Some("quiz.example"),
+            &allowed
+        ));
+        assert!(is_allowed_origin(
+            Some("http://localhost:5173"),
+            Some("api:8000"),
+            &allowed
+        ));
+        assert!(!is_allowed_origin(
+            Some("https://evil.example"),
+            Some("quiz.example"),
+            &allowed
+        ));
+        assert!(!is_allowed_origin(Some("null"), Some("quiz.example"), &[]));
+    }
+}
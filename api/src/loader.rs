@@ -0,0 +1,213 @@
+use std::{collections::HashMap, hash::Hash, mem, str::FromStr, sync::Mutex};
+
+use mongodb::bson::{doc, oid::ObjectId};
+use rocket::tokio::{sync::Mutex as AsyncMutex, task::yield_now};
+
+use crate::models::{
+    BaseModel, ModelError, Question, Questions, QuizTemplate, QuizTemplates, User, Users,
+    MAX_PAGE_SIZE,
+};
+
+/// Loads the values for many keys with a single query.
+pub trait BatchFn {
+    type Key: Clone + Eq + Hash;
+    type Value: Clone + Default;
+
+    /// Keys with nothing stored may be left out of the result; they load as the default value.
+    async fn load(&self, keys: &[Self::Key])
+        -> Result<HashMap<Self::Key, Self::Value>, ModelError>;
+}
+
+/// Request scoped batching and caching in front of a [`BatchFn`].
+///
+/// Resolvers for the items of a list are polled together. Each call to [`Loader::load`]
+/// queues its key and yields once, so by the time the first of them reaches the database its
+/// siblings have queued theirs and one query serves them all.
+pub struct Loader<B: BatchFn> {
+    batch_fn: B,
+    cache: Mutex<HashMap<B::Key, B::Value>>,
+    /// Held while a batch is in flight so that waiting callers find their value in the cache.
+    loading: AsyncMutex<()>,
+    pending: Mutex<Vec<B::Key>>,
+}
+
+impl<B: BatchFn> Loader<B> {
+    pub fn new(batch_fn: B) -> Self {
+        Self {
+            batch_fn,
+            cache: Mutex::new(HashMap::new()),
+            loading: AsyncMutex::new(()),
+            pending: Mutex::new(vec![]),
+        }
+    }
+
+    fn cached(&self, key: &B::Key) -> Option<B::Value> {
+        self.cache.lock().unwrap().get(key).cloned()
+    }
+
+    pub async fn load(&self, key: B::Key) -> Result<B::Value, ModelError> {
+        if let Some(value) = self.cached(&key) {
+            return Ok(value);
+        }
+
+        self.pending.lock().unwrap().push(key.clone());
+
+        yield_now().await;
+
+        let _loading = self.loading.lock().await;
+
+        if let Some(value) = self.cached(&key) {
+            return Ok(value);
+        }
+
+        let mut keys = mem::take(&mut *self.pending.lock().unwrap());
+
+        // A failed batch drops its keys, so make sure ours is always part of the next one.
+        if !keys.contains(&key) {
+            keys.push(key.clone());
+        }
+
+        let mut values = self.batch_fn.load(&keys).await?;
+
+        let mut cache = self.cache.lock().unwrap();
+
+        for key in keys {
+            let value = values.remove(&key).unwrap_or_default();
+
+            cache.insert(key, value);
+        }
+
+        Ok(cache.get(&key).cloned().unwrap_or_default())
+    }
+}
+
+/// The first questions of each quiz template in position order: one more than the largest
+/// page, so that a first page of any size can tell whether more follow. Later pages are read
+/// with a cursor instead, so large templates are never loaded whole.
+pub struct FirstQuestionsByQuizTemplateId(pub Questions);
+
+impl BatchFn for FirstQuestionsByQuizTemplateId {
+    type Key = String;
+    type Value = Vec<Question>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<Question>>, ModelError> {
+        let ids = keys
+            .iter()
+            .map(|key| ObjectId::from_str(key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let questions = self
+            .0
+            .read_first_by_quiz_template_ids(ids, MAX_PAGE_SIZE + 1)
+            .await?;
+
+        let mut values: HashMap<String, Vec<Question>> = HashMap::new();
+
+        for question in questions {
+            values
+                .entry(question.quiz_template_id.clone())
+                .or_default()
+                .push(question);
+        }
+
+        Ok(values)
+    }
+}
+
+pub struct QuizTemplatesById(pub QuizTemplates);
+
+impl BatchFn for QuizTemplatesById {
+    type Key = String;
+    type Value = Option<QuizTemplate>;
+
+    async fn load(
+        &self,
+        keys: &[String],
+    ) -> Result<HashMap<String, Option<QuizTemplate>>, ModelError> {
+        let ids = keys
+            .iter()
+            .map(|key| ObjectId::from_str(key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let quiz_templates = self
+            .0
+            .read_by_filter(doc! {
+                "_id": { "$in": ids },
+            })
+            .await?;
+
+        Ok(quiz_templates
+            .into_iter()
+            .map(|quiz_template| (quiz_template.id.clone(), Some(quiz_template)))
+            .collect())
+    }
+}
+
+pub struct UsersById(pub Users);
+
+impl BatchFn for UsersById {
+    type Key = String;
+    type Value = Option<User>;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Option<User>>, ModelError> {
+        let ids = keys
+            .iter()
+            .map(|key| ObjectId::from_str(key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let users = self
+            .0
+            .read_by_filter(doc! {
+                "_id": { "$in": ids },
+            })
+            .await?;
+
+        Ok(users
+            .into_iter()
+            .map(|user| (user.id.clone(), Some(user)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rocket::futures::future::join_all;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Squares {
+        calls: AtomicUsize,
+    }
+
+    impl BatchFn for &Squares {
+        type Key = u32;
+        type Value = u32;
+
+        async fn load(&self, keys: &[u32]) -> Result<HashMap<u32, u32>, ModelError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            Ok(keys.iter().map(|key| (*key, key * key)).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_batches_siblings() {
+        let squares = Squares::default();
+
+        let loader = Loader::new(&squares);
+
+        let values = join_all((1..=5).map(|key| loader.load(key))).await;
+
+        assert_eq!(
+            vec![1, 4, 9, 16, 25],
+            values.into_iter().map(Result::unwrap).collect::<Vec<_>>()
+        );
+        assert_eq!(1, squares.calls.load(Ordering::SeqCst));
+
+        assert_eq!(9, loader.load(3).await.unwrap());
+        assert_eq!(1, squares.calls.load(Ordering::SeqCst));
+    }
+}
@@ -2,29 +2,44 @@
 extern crate rocket;
 
 mod auth;
+mod authorization;
+mod content;
 mod context;
+mod formats;
+mod game;
 mod graphql;
+mod graphql_ws;
+mod loader;
+mod matching;
 mod models;
 mod session;
+mod sweep;
 
-use std::{env::var, fs};
+use std::{env::var, fs, str::FromStr, sync::Arc};
 
 use auth::BasicAuth;
+use authorization::{read_exportable, require_question_owner, AuthorizationError, ReadError};
 use context::Context;
 use dotenvy::dotenv;
-use graphql::{Mutation, Query, Schema};
-use juniper::EmptySubscription;
+use graphql::{Mutation, Query, Schema, Subscription};
+use graphql_ws::{AllowedOrigins, SubscriptionSocket, WebSocketUpgrade};
 use juniper_rocket::{graphiql_source, playground_source, GraphQLRequest, GraphQLResponse};
-use models::ModelError;
+use models::{ModelError, PaperOptions, Questions, QuizTemplates, Reviews, Users};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rand::{thread_rng, Rng};
 use rocket::{
     build,
+    data::{Data, ToByteUnit},
     fs::NamedFile,
-    http::{CookieJar, Status},
+    http::{ContentType, CookieJar, Header, Status},
     response::content::RawHtml,
     State,
 };
 use session::SessionStorage;
 
+/// Random paper seeds stay short enough to type in when requesting the key.
+const MAX_RANDOM_SEED: i64 = 1_000_000;
+
 #[get("/")]
 fn health_check() -> &'static str {
     "OK"
@@ -44,18 +59,308 @@ fn playground() -> RawHtml<String> {
 async fn get_graphql(
     context: Context,
     request: GraphQLRequest,
-    schema: &State<Schema>,
+    schema: &State<Arc<Schema>>,
 ) -> GraphQLResponse {
-    request.execute(&schema, &context).await
+    request.execute(schema, &context).await
 }
 
 #[post("/graphql", data = "<request>")]
 async fn post_graphql(
     context: Context,
     request: GraphQLRequest,
-    schema: &State<Schema>,
+    schema: &State<Arc<Schema>>,
 ) -> GraphQLResponse {
-    request.execute(&schema, &context).await
+    request.execute(schema, &context).await
+}
+
+#[get("/subscriptions")]
+fn subscriptions(
+    context: Context,
+    schema: &State<Arc<Schema>>,
+    upgrade: WebSocketUpgrade,
+) -> SubscriptionSocket {
+    SubscriptionSocket::new(context, schema.inner().clone(), upgrade)
+}
+
+/// A file the browser saves instead of showing.
+#[derive(Responder)]
+struct Download {
+    contents: Vec<u8>,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+impl Download {
+    fn new(contents: Vec<u8>, content_type: ContentType, filename: &str) -> Self {
+        Self {
+            contents,
+            content_type,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        }
+    }
+}
+
+fn map_export_error<E: std::fmt::Debug>(e: E) -> (Status, &'static str) {
+    eprintln!("ERROR: {:?}", e);
+
+    (Status::InternalServerError, "Unexpected error")
+}
+
+fn map_read_error(e: ReadError) -> (Status, &'static str) {
+    match e {
+        ReadError::Refused(e) => (e.status(), e.message()),
+        ReadError::Model(e) => map_export_error(e),
+    }
+}
+
+/// Anki deck of quiz templates, one deck per template: `/export/anki?ids=a&ids=b`.
+#[get("/export/anki?<ids>")]
+async fn export_anki(
+    context: Context,
+    ids: Vec<String>,
+) -> Result<Download, (Status, &'static str)> {
+    let mut unique_ids = vec![];
+
+    for id in ids {
+        if !unique_ids.contains(&id) {
+            unique_ids.push(id);
+        }
+    }
+
+    if unique_ids.is_empty() {
+        return Err((Status::BadRequest, "No quiz template ids"));
+    }
+
+    if unique_ids.len() > formats::apkg::MAX_DECKS {
+        return Err((Status::BadRequest, "Too many quiz template ids"));
+    }
+
+    let mut quiz_templates = vec![];
+
+    for id in unique_ids {
+        quiz_templates.push(
+            read_exportable(&context, &id)
+                .await
+                .map_err(map_read_error)?,
+        );
+    }
+
+    let package =
+        formats::apkg::export(&quiz_templates, DateTime::now()).map_err(map_export_error)?;
+
+    Ok(Download::new(package, ContentType::Binary, "quizzes.apkg"))
+}
+
+#[derive(FromFormField)]
+enum PrintFormat {
+    Html,
+    Pdf,
+}
+
+#[derive(Responder)]
+enum Printout {
+    Html(RawHtml<String>),
+    Pdf(Box<Download>),
+}
+
+/// A printout with the seed it was drawn from in an `X-Paper-Seed` header.
+#[derive(Responder)]
+struct SeededPrintout {
+    printout: Printout,
+    seed: Header<'static>,
+}
+
+/// A quiz template laid out for printing on paper, or its answer key with `key=true`.
+/// `variants` prints that many papers (A, B, ...) drawn from consecutive seeds, so a key
+/// requested with the same seed and options as the papers always matches them. Without a
+/// `seed` a random one is used; it is printed at the end and sent as `X-Paper-Seed`.
+#[allow(clippy::too_many_arguments)]
+#[get("/print/<id>?<variants>&<seed>&<question_count>&<shuffle_questions>&<shuffle_options>&<key>&<format>")]
+async fn print(
+    context: Context,
+    id: &str,
+    variants: Option<usize>,
+    seed: Option<i64>,
+    question_count: Option<usize>,
+    shuffle_questions: bool,
+    shuffle_options: bool,
+    key: bool,
+    format: Option<PrintFormat>,
+) -> Result<SeededPrintout, (Status, &'static str)> {
+    let (quiz_template, questions) = read_exportable(&context, id)
+        .await
+        .map_err(map_read_error)?;
+
+    let options = PaperOptions {
+        question_count,
+        shuffle_options,
+        shuffle_questions,
+    };
+
+    let seed = seed.unwrap_or_else(|| thread_rng().gen_range(0..MAX_RANDOM_SEED));
+
+    let variants = formats::print::variants(&questions, &options, seed, variants.unwrap_or(1))
+        .map_err(|_| {
+            (
+                Status::BadRequest,
+                "Invalid number of variants or questions for this quiz template",
+            )
+        })?;
+
+    let (title, blocks) = match key {
+        true => (
+            format!("{}: answer key", quiz_template.name),
+            formats::print::answer_key(&quiz_template, &questions, &variants, seed),
+        ),
+        false => (
+            quiz_template.name.clone(),
+            formats::print::paper(&quiz_template, &questions, &variants, seed),
+        ),
+    };
+
+    let printout = match format.unwrap_or(PrintFormat::Html) {
+        PrintFormat::Html => Printout::Html(RawHtml(formats::print::html(&title, &blocks))),
+        PrintFormat::Pdf => Printout::Pdf(Box::new(Download::new(
+            formats::pdf::render(&blocks),
+            ContentType::PDF,
+            match key {
+                true => "answer-key.pdf",
+                false => "quiz.pdf",
+            },
+        ))),
+    };
+
+    Ok(SeededPrintout {
+        printout,
+        seed: Header::new("X-Paper-Seed", seed.to_string()),
+    })
+}
+
+/// Uploads an image or sound clip to a question you own. The request body is the file and its
+/// `Content-Type` header the file's type. Responds with the new attachment's id.
+#[post("/questions/<id>/attachments?<filename>", data = "<data>")]
+async fn upload_attachment(
+    context: Context,
+    id: &str,
+    filename: &str,
+    content_type: &ContentType,
+    data: Data<'_>,
+) -> Result<String, (Status, String)> {
+    if ObjectId::from_str(id).is_err() {
+        return Err((Status::BadRequest, "Invalid question id".to_string()));
+    }
+
+    let question = require_question_owner(&context, id).await.map_err(|e| {
+        let (status, message) = map_read_error(e);
+
+        (status, message.to_string())
+    })?;
+
+    let unexpected = |e: ModelError| {
+        let (status, message) = map_export_error(e);
+
+        (status, message.to_string())
+    };
+
+    let too_many = || {
+        (
+            Status::Conflict,
+            format!(
+                "Questions can have at most {} attachments",
+                models::MAX_ATTACHMENTS
+            ),
+        )
+    };
+
+    if question.attachments.len() >= models::MAX_ATTACHMENTS {
+        return Err(too_many());
+    }
+
+    let contents = data
+        .open(models::MAX_ATTACHMENT_SIZE.bytes())
+        .into_bytes()
+        .await
+        .map_err(|e| unexpected(e.to_string().into()))?;
+
+    if !contents.is_complete() {
+        return Err((
+            Status::PayloadTooLarge,
+            format!(
+                "Attachments must be at most {} bytes",
+                models::MAX_ATTACHMENT_SIZE
+            ),
+        ));
+    }
+
+    let media_type = format!("{}/{}", content_type.top(), content_type.sub()).to_lowercase();
+
+    models::validate_attachment(filename, &media_type, &contents)
+        .map_err(|e| (Status::UnprocessableEntity, e.to_string()))?;
+
+    let attachment = context
+        .attachments
+        .upload(&question.id, filename, &media_type, &contents)
+        .await
+        .map_err(unexpected)?;
+
+    // Another upload may have filled the question in the meantime.
+    if !context
+        .questions
+        .add_attachment(&question.id, &attachment)
+        .await
+        .map_err(unexpected)?
+    {
+        context
+            .attachments
+            .delete_all(&[attachment])
+            .await
+            .map_err(unexpected)?;
+
+        return Err(too_many());
+    }
+
+    Ok(attachment.id)
+}
+
+/// An attachment, served only under the type it was checked against when uploaded.
+#[derive(Responder)]
+struct Media {
+    contents: Vec<u8>,
+    content_type: ContentType,
+    cache_control: Header<'static>,
+    no_sniff: Header<'static>,
+}
+
+/// Downloads an attachment. Attachments are shown to anyone signed in, like their questions.
+#[get("/attachments/<id>")]
+async fn download_attachment(context: Context, id: &str) -> Result<Media, (Status, &'static str)> {
+    if context.user.is_none() {
+        let error = AuthorizationError::Unauthenticated;
+
+        return Err((error.status(), error.message()));
+    }
+
+    if ObjectId::from_str(id).is_err() {
+        return Err((Status::BadRequest, "Invalid attachment id"));
+    }
+
+    let (content_type, contents) = context
+        .attachments
+        .download(id)
+        .await
+        .map_err(map_export_error)?
+        .ok_or((Status::NotFound, "Not found"))?;
+
+    Ok(Media {
+        contents,
+        content_type: ContentType::parse_flexible(&content_type).unwrap_or(ContentType::Binary),
+        // Attachments never change, only get deleted.
+        cache_control: Header::new("Cache-Control", "private, max-age=86400"),
+        no_sniff: Header::new("X-Content-Type-Options", "nosniff"),
+    })
 }
 
 fn map_login_error(e: ModelError) -> (Status, &'static str) {
@@ -97,12 +402,10 @@ async fn login(
     let _ = session_storage
         .create_session(&user.id, cookie_jar)
         .await
-        .map_err(|e| match e {
-            _ => {
-                eprintln!("Error creating session");
+        .map_err(|_| {
+            eprintln!("Error creating session");
 
-                (Status::InternalServerError, "Unexpected error")
-            }
+            (Status::InternalServerError, "Unexpected error")
         });
 
     Ok(())
@@ -116,12 +419,10 @@ async fn logout(
     let _ = session_storage
         .delete_session(cookie_jar)
         .await
-        .map_err(|e| match e {
-            _ => {
-                eprintln!("Error deleting session");
+        .map_err(|_| {
+            eprintln!("Error deleting session");
 
-                (Status::InternalServerError, "Unexpected error")
-            }
+            (Status::InternalServerError, "Unexpected error")
         });
 
     Ok(())
@@ -144,20 +445,48 @@ async fn signup(
         .await
         .map_err(map_login_error)?;
 
+    if admin_usernames().contains(&username) {
+        context
+            .users
+            .grant_admin(&[username])
+            .await
+            .map_err(map_export_error)?;
+    }
+
     let _ = session_storage
         .create_session(&user.id, cookie_jar)
         .await
-        .map_err(|e| match e {
-            _ => {
-                eprintln!("Error creating session");
+        .map_err(|_| {
+            eprintln!("Error creating session");
 
-                (Status::InternalServerError, "Unexpected error")
-            }
+            (Status::InternalServerError, "Unexpected error")
         });
 
     Ok(())
 }
 
+/// Accounts that are made admins when they sign up and whenever the server starts, from the
+/// comma separated `ADMIN_USERNAMES`. This is how the first admin is created.
+fn admin_usernames() -> Vec<String> {
+    var("ADMIN_USERNAMES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|username| username.trim().to_string())
+        .filter(|username| !username.is_empty())
+        .collect()
+}
+
+/// Origins of the web app when it is served from another host than the API, from the comma
+/// separated `ALLOWED_ORIGINS`, e.g. `http://localhost:5173`.
+fn allowed_origins() -> Vec<String> {
+    var("ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
 fn mongodb_connection_uri() -> String {
     match var("MONGODB_CONNECTION_STRING") {
         Ok(value) => {
@@ -223,16 +552,42 @@ async fn rocket() -> _ {
         .await
         .unwrap();
 
+    let db = db_client.default_database().unwrap();
+
+    QuizTemplates::new(&db).create_indexes().await.unwrap();
+    Questions::new(&db).create_indexes().await.unwrap();
+    Questions::new(&db).fill_missing_positions().await.unwrap();
+    QuizTemplates::new(&db)
+        .fill_missing_question_counts(&Questions::new(&db))
+        .await
+        .unwrap();
+    Reviews::new(&db).create_indexes().await.unwrap();
+    Users::new(&db)
+        .grant_admin(&admin_usernames())
+        .await
+        .unwrap();
+
     let session_client = redis::Client::open(var("REDIS_CONNECTION_STRING").unwrap()).unwrap();
 
+    // One multiplexed connection is shared by every request; clones are cheap handles onto it.
+    let redis_connection = session_client
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+
     build()
         // .attach(cors)
+        .attach(sweep::fairing())
+        .manage(AllowedOrigins(allowed_origins()))
         .manage(session_client)
-        .manage(db_client.default_database().unwrap())
-        .manage(Schema::new(Query, Mutation, EmptySubscription::new()))
+        .manage(redis_connection)
+        .manage(db)
+        .manage(Arc::new(Schema::new(Query, Mutation, Subscription)))
         .mount(
             "/",
             routes![
+                download_attachment,
+                export_anki,
                 get_graphql,
                 graphiql,
                 health_check,
@@ -240,7 +595,10 @@ async fn rocket() -> _ {
                 logout,
                 playground,
                 post_graphql,
+                print,
                 signup,
+                subscriptions,
+                upload_attachment,
                 login_page,
             ],
         )
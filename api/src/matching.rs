@@ -0,0 +1,222 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on the compiled size of author supplied patterns.
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+/// How a free-text response is compared against the stored answer.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "mode")]
+pub enum MatchMode {
+    /// Identical once leading and trailing whitespace is trimmed.
+    #[default]
+    Exact,
+    CaseInsensitive,
+    /// Case, punctuation and runs of whitespace are ignored.
+    Normalized,
+    /// The answer or any of `accepted`, compared as in [`MatchMode::Normalized`].
+    Alternatives {
+        accepted: Vec<String>,
+    },
+    /// The whole response must match `pattern`.
+    Regex {
+        pattern: String,
+    },
+    /// Normalized response within `max_distance` edits of the normalized answer.
+    Fuzzy {
+        max_distance: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchVerdict {
+    /// How close the response was to an accepted answer, from 0 to 1. Near-misses score
+    /// high without matching.
+    pub confidence: f64,
+    pub matched: bool,
+}
+
+impl MatchVerdict {
+    fn new(matched: bool, confidence: f64) -> Self {
+        Self {
+            confidence,
+            matched,
+        }
+    }
+}
+
+impl MatchMode {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Alternatives { accepted } if accepted.iter().all(|a| normalize(a).is_empty()) => {
+                Err("At least one accepted alternative is required".to_string())
+            }
+            Self::Regex { pattern } => compile(pattern).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check(&self, answer: &str, response: &str) -> MatchVerdict {
+        match self {
+            Self::Exact => {
+                let (answer, response) = (answer.trim(), response.trim());
+
+                MatchVerdict::new(answer == response, similarity(answer, response))
+            }
+            Self::CaseInsensitive => {
+                let (answer, response) =
+                    (answer.trim().to_lowercase(), response.trim().to_lowercase());
+
+                MatchVerdict::new(answer == response, similarity(&answer, &response))
+            }
+            Self::Normalized => {
+                let (answer, response) = (normalize(answer), normalize(response));
+
+                MatchVerdict::new(answer == response, similarity(&answer, &response))
+            }
+            Self::Alternatives { accepted } => {
+                let response = normalize(response);
+
+                let confidence = std::iter::once(answer)
+                    .chain(accepted.iter().map(String::as_str))
+                    .map(|candidate| similarity(&normalize(candidate), &response))
+                    .fold(0.0, f64::max);
+
+                MatchVerdict::new(confidence == 1.0, confidence)
+            }
+            Self::Regex { pattern } => {
+                let matched = compile(pattern).is_ok_and(|regex| regex.is_match(response.trim()));
+
+                MatchVerdict::new(matched, if matched { 1.0 } else { 0.0 })
+            }
+            Self::Fuzzy { max_distance } => {
+                let (answer, response) = (normalize(answer), normalize(response));
+
+                let distance = edit_distance(&answer, &response);
+
+                MatchVerdict::new(distance <= *max_distance, similarity(&answer, &response))
+            }
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(&format!("^(?:{})$", pattern))
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Lowercases, drops punctuation and collapses whitespace.
+pub fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_ascii_punctuation())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Levenshtein distance counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+fn similarity(a: &str, b: &str) -> f64 {
+    let length = a.chars().count().max(b.chars().count());
+
+    if length == 0 {
+        return 1.0;
+    }
+
+    1.0 - edit_distance(a, b) as f64 / length as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(0, edit_distance("", ""));
+        assert_eq!(6, edit_distance("kitten", ""));
+        assert_eq!(3, edit_distance("kitten", "sitting"));
+        assert_eq!(1, edit_distance("café", "cafe"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("hello world", normalize("  Hello,   World! "));
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(MatchMode::Exact.check("Paris", " Paris ").matched);
+        assert!(!MatchMode::Exact.check("Paris", "paris").matched);
+
+        assert!(MatchMode::CaseInsensitive.check("Paris", "PARIS").matched);
+        assert!(!MatchMode::CaseInsensitive.check("Paris", "Paris.").matched);
+
+        assert!(
+            MatchMode::Normalized
+                .check("The Beatles", "the   beatles!")
+                .matched
+        );
+
+        let alternatives = MatchMode::Alternatives {
+            accepted: vec!["USA".to_string(), "United States of America".to_string()],
+        };
+
+        assert!(alternatives.check("United States", "u.s.a").matched);
+        assert!(alternatives.check("United States", "united states").matched);
+        assert!(!alternatives.check("United States", "Canada").matched);
+
+        let regex = MatchMode::Regex {
+            pattern: r"(?i)colou?r".to_string(),
+        };
+
+        assert!(regex.check("", "Color").matched);
+        assert!(!regex.check("", "colors").matched);
+
+        let fuzzy = MatchMode::Fuzzy { max_distance: 2 };
+
+        let near_miss = fuzzy.check("Mississippi", "Misisipi");
+
+        assert!(!near_miss.matched);
+        assert!(near_miss.confidence > 0.7);
+        assert!(fuzzy.check("Mississippi", "Missisippi").matched);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(MatchMode::Regex {
+            pattern: "(".to_string()
+        }
+        .validate()
+        .is_err());
+
+        assert!(MatchMode::Alternatives { accepted: vec![] }
+            .validate()
+            .is_err());
+
+        assert!(MatchMode::Fuzzy { max_distance: 1 }.validate().is_ok());
+    }
+}
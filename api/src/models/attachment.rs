@@ -0,0 +1,256 @@
+//! Images and sound clips attached to questions.
+//!
+//! File contents live in the `attachments` GridFS bucket and each question keeps an
+//! [`Attachment`] reference per file. Only a few raster image and audio types are accepted, and
+//! an upload must start with the signature of the type it claims to be, so a file is never
+//! served under a type it does not have. SVG is refused because it can carry scripts.
+
+use std::str::FromStr;
+
+use mongodb::{
+    bson::{
+        doc,
+        oid::ObjectId,
+        serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
+    },
+    error::{ErrorKind, GridFsErrorKind},
+    gridfs::GridFsBucket,
+    options::GridFsBucketOptions,
+    Database,
+};
+use rocket::futures::{AsyncReadExt, AsyncWriteExt};
+use serde::{Deserialize, Serialize};
+
+use super::error::ModelError;
+
+const ATTACHMENTS_BUCKET: &str = "attachments";
+
+/// Most attachments one question can have.
+pub const MAX_ATTACHMENTS: usize = 8;
+
+const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+const MAX_AUDIO_SIZE: usize = 10 * 1024 * 1024;
+
+const MAX_FILENAME_LENGTH: usize = 255;
+
+/// Largest upload of any type, in bytes.
+pub const MAX_ATTACHMENT_SIZE: usize = MAX_AUDIO_SIZE;
+
+/// Accepted MIME types with the largest size allowed for each.
+const ALLOWED_TYPES: [(&str, usize); 8] = [
+    ("audio/mpeg", MAX_AUDIO_SIZE),
+    ("audio/ogg", MAX_AUDIO_SIZE),
+    ("audio/wav", MAX_AUDIO_SIZE),
+    ("audio/webm", MAX_AUDIO_SIZE),
+    ("image/gif", MAX_IMAGE_SIZE),
+    ("image/jpeg", MAX_IMAGE_SIZE),
+    ("image/png", MAX_IMAGE_SIZE),
+    ("image/webp", MAX_IMAGE_SIZE),
+];
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Attachment {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub id: String,
+    pub content_type: String,
+    pub filename: String,
+    pub size: i64,
+}
+
+/// The accepted type whose signature `contents` starts with.
+fn sniff(contents: &[u8]) -> Option<&'static str> {
+    let riff = |format: &[u8]| contents.starts_with(b"RIFF") && contents.get(8..12) == Some(format);
+
+    match contents {
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Some("image/png"),
+        [0xff, 0xd8, 0xff, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        _ if riff(b"WEBP") => Some("image/webp"),
+        _ if riff(b"WAVE") => Some("audio/wav"),
+        [b'O', b'g', b'g', b'S', ..] => Some("audio/ogg"),
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => Some("audio/webm"),
+        // An ID3 tag or an MPEG audio frame header.
+        [b'I', b'D', b'3', ..] => Some("audio/mpeg"),
+        [0xff, second, ..] if second & 0xe0 == 0xe0 => Some("audio/mpeg"),
+        _ => None,
+    }
+}
+
+/// Checks that `contents` may be stored as `content_type` under `filename`.
+pub fn validate(filename: &str, content_type: &str, contents: &[u8]) -> Result<(), ModelError> {
+    if filename.is_empty()
+        || filename.len() > MAX_FILENAME_LENGTH
+        || filename
+            .chars()
+            .any(|c| c.is_control() || matches!(c, '"' | '/' | '\\'))
+    {
+        Err(format!(
+            "Filenames must be 1 to {} bytes without quotes, slashes or control characters",
+            MAX_FILENAME_LENGTH
+        ))?;
+    }
+
+    let (_, max_size) = ALLOWED_TYPES
+        .iter()
+        .find(|(allowed, _)| *allowed == content_type)
+        .ok_or_else(|| format!("Attachments of type {} are not allowed", content_type))?;
+
+    if contents.is_empty() {
+        Err("Attachment is empty")?;
+    }
+
+    if contents.len() > *max_size {
+        Err(format!(
+            "Attachments of type {} must be at most {} bytes",
+            content_type, max_size
+        ))?;
+    }
+
+    if sniff(contents) != Some(content_type) {
+        Err(format!("Attachment is not a valid {} file", content_type))?;
+    }
+
+    Ok(())
+}
+
+pub struct Attachments {
+    bucket: GridFsBucket,
+}
+
+impl Attachments {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            bucket: db.gridfs_bucket(
+                GridFsBucketOptions::builder()
+                    .bucket_name(ATTACHMENTS_BUCKET.to_string())
+                    .build(),
+            ),
+        }
+    }
+
+    /// Validates and stores a file for a question. The caller adds the returned reference to
+    /// the question.
+    pub async fn upload(
+        &self,
+        question_id: &str,
+        filename: &str,
+        content_type: &str,
+        contents: &[u8],
+    ) -> Result<Attachment, ModelError> {
+        validate(filename, content_type, contents)?;
+
+        let id = ObjectId::new();
+
+        let mut stream = self
+            .bucket
+            .open_upload_stream(filename)
+            .id(id.into())
+            .metadata(doc! {
+                "content_type": content_type,
+                "question_id": ObjectId::from_str(question_id)?,
+            })
+            .await?;
+
+        if let Err(e) = stream.write_all(contents).await {
+            stream.abort().await?;
+
+            Err(e.to_string())?;
+        }
+
+        stream.close().await.map_err(|e| e.to_string())?;
+
+        Ok(Attachment {
+            id: id.to_string(),
+            content_type: content_type.to_string(),
+            filename: filename.to_string(),
+            size: contents.len() as i64,
+        })
+    }
+
+    /// The content type and contents of a file, or `None` when there is no such file.
+    pub async fn download(&self, id: &str) -> Result<Option<(String, Vec<u8>)>, ModelError> {
+        let id = ObjectId::from_str(id)?;
+
+        let file = match self.bucket.find_one(doc! { "_id": id }).await? {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        let content_type = file
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get_str("content_type").ok())
+            .ok_or("Attachment has no content type")?
+            .to_string();
+
+        let mut contents = Vec::with_capacity(file.length as usize);
+
+        self.bucket
+            .open_download_stream(id.into())
+            .await?
+            .read_to_end(&mut contents)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some((content_type, contents)))
+    }
+
+    /// Deletes the files of `attachments`. Files that are already gone are skipped.
+    pub async fn delete_all(&self, attachments: &[Attachment]) -> Result<(), ModelError> {
+        for attachment in attachments {
+            match self
+                .bucket
+                .delete(ObjectId::from_str(&attachment.id)?.into())
+                .await
+            {
+                Ok(()) => {}
+                Err(e)
+                    if matches!(
+                        *e.kind,
+                        ErrorKind::GridFs(GridFsErrorKind::FileNotFound { .. })
+                    ) => {}
+                Err(e) => Err(e)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(Some("image/png"), sniff(PNG));
+        assert_eq!(Some("image/gif"), sniff(b"GIF89a\x01\0"));
+        assert_eq!(Some("image/webp"), sniff(b"RIFF\0\0\0\0WEBPVP8 "));
+        assert_eq!(Some("audio/wav"), sniff(b"RIFF\0\0\0\0WAVEfmt "));
+        assert_eq!(Some("audio/mpeg"), sniff(b"ID3\x04\0"));
+        assert_eq!(Some("audio/mpeg"), sniff(&[0xff, 0xfb, 0x90]));
+        assert_eq!(None, sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"));
+        assert_eq!(None, sniff(b"RIFF"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("heart.png", "image/png", PNG).is_ok());
+        assert!(validate("heart.svg", "image/svg+xml", b"<svg/>").is_err());
+        assert!(validate("heart.jpg", "image/jpeg", PNG).is_err());
+        assert!(validate("heart.png", "image/png", b"").is_err());
+        assert!(validate("", "image/png", PNG).is_err());
+        assert!(validate("a\"b.png", "image/png", PNG).is_err());
+        assert!(validate("../heart.png", "image/png", PNG).is_err());
+
+        let mut large = PNG.to_vec();
+        large.resize(MAX_IMAGE_SIZE + 1, 0);
+
+        assert!(validate("heart.png", "image/png", &large).is_err());
+    }
+}
@@ -0,0 +1,672 @@
+use std::str::FromStr;
+
+use mongodb::{
+    bson::{
+        doc,
+        oid::ObjectId,
+        serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
+        to_bson, DateTime, Document,
+    },
+    Collection, Database,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    base_model::BaseModel,
+    error::ModelError,
+    option_ids::new_option_key,
+    paper::Paper,
+    question::{Question, Response},
+    quiz_template::QuizTemplate,
+    scoring::{default_points, Scoring},
+};
+
+const ATTEMPTS_COLLECTION: &str = "attempts";
+
+/// Late answers within this window are still accepted, to allow for network latency.
+pub const GRACE_MILLIS: i64 = 5_000;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptStatus {
+    InProgress,
+    Submitted,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AttemptAnswer {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub question_id: String,
+    pub response: Response,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OpenedQuestion {
+    pub opened_at: DateTime,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub question_id: String,
+}
+
+/// One hint shown to the learner. The hints of a question are revealed in order, so the
+/// number of records for a question says how many of its hints were shown.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RevealedHint {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub question_id: String,
+    pub revealed_at: DateTime,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct QuestionResult {
+    /// How close the response came to an accepted answer, from 0 to 1.
+    pub confidence: f64,
+    pub correct: bool,
+    /// Share of the question's points earned before hint penalties, from 0 to 1.
+    #[serde(default)]
+    pub credit: f64,
+    #[serde(default)]
+    pub hints_revealed: usize,
+    /// The question's points.
+    #[serde(default = "default_points")]
+    pub max_score: f64,
+    /// Share of the question's points deducted for the hints revealed.
+    #[serde(default)]
+    pub penalty: f64,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub question_id: String,
+    pub response: Option<Response>,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AttemptResult {
+    pub max_score: f64,
+    /// Whether the score reached the template's pass mark. Empty without a pass mark.
+    #[serde(default)]
+    pub passed: Option<bool>,
+    /// The score as a percentage of `max_score`.
+    #[serde(default)]
+    pub percentage: f64,
+    pub question_results: Vec<QuestionResult>,
+    pub score: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Attempt {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        rename = "_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub id: String,
+    pub answers: Vec<AttemptAnswer>,
+    /// When the whole attempt runs out of time. Untimed attempts have none.
+    #[serde(default)]
+    pub deadline_at: Option<DateTime>,
+    /// Questions shown so far, when the template limits time per question.
+    #[serde(default)]
+    pub opened_questions: Vec<OpenedQuestion>,
+    /// Keys the [`OptionIds`](super::OptionIds) the learner sees. Never sent to clients.
+    #[serde(default)]
+    pub option_key: String,
+    /// Attempts without a paper are given every question of the template.
+    #[serde(default)]
+    pub paper: Option<Paper>,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub quiz_template_id: String,
+    /// Copied from the template when the attempt starts so that edits do not affect it.
+    #[serde(default)]
+    pub question_time_limit_seconds: Option<u32>,
+    pub result: Option<AttemptResult>,
+    #[serde(default)]
+    pub revealed_hints: Vec<RevealedHint>,
+    /// Copied from the template when the attempt starts, like the time limits.
+    #[serde(default)]
+    pub scoring: Scoring,
+    pub started_at: DateTime,
+    pub status: AttemptStatus,
+    pub submitted_at: Option<DateTime>,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub user_id: String,
+}
+
+impl Attempt {
+    pub fn new(quiz_template: &QuizTemplate, user_id: &str) -> Self {
+        let started_at = DateTime::now();
+
+        Self {
+            id: ObjectId::new().to_string(),
+            answers: vec![],
+            deadline_at: quiz_template
+                .time_limit_seconds
+                .map(|seconds| add_seconds(started_at, seconds)),
+            opened_questions: vec![],
+            option_key: new_option_key(),
+            paper: None,
+            quiz_template_id: quiz_template.id.clone(),
+            question_time_limit_seconds: quiz_template.question_time_limit_seconds,
+            result: None,
+            revealed_hints: vec![],
+            scoring: quiz_template.scoring.clone(),
+            started_at,
+            status: AttemptStatus::InProgress,
+            submitted_at: None,
+            user_id: user_id.to_string(),
+        }
+    }
+
+    /// Whether the attempt is still open but out of time, grace window included.
+    pub fn is_expired(&self, now: DateTime) -> bool {
+        self.status == AttemptStatus::InProgress
+            && self
+                .deadline_at
+                .is_some_and(|deadline| is_past(deadline, now))
+    }
+
+    pub fn opened_at(&self, question_id: &str) -> Option<DateTime> {
+        self.opened_questions
+            .iter()
+            .find(|opened| opened.question_id == question_id)
+            .map(|opened| opened.opened_at)
+    }
+
+    /// Starts the clock on a question the first time it is shown.
+    pub fn open_question(&mut self, question_id: &str, now: DateTime) {
+        if self.opened_at(question_id).is_none() {
+            self.opened_questions.push(OpenedQuestion {
+                opened_at: now,
+                question_id: question_id.to_string(),
+            });
+        }
+    }
+
+    /// When answers to a question stop being accepted. Never later than the attempt deadline.
+    pub fn question_deadline(&self, question_id: &str) -> Option<DateTime> {
+        let question_deadline = self
+            .question_time_limit_seconds
+            .zip(self.opened_at(question_id))
+            .map(|(seconds, opened_at)| add_seconds(opened_at, seconds));
+
+        match (question_deadline, self.deadline_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Refuses answers that arrive after the deadline or to questions that were never opened
+    /// when time is limited per question.
+    pub fn check_time(&self, question_id: &str, now: DateTime) -> Result<(), ModelError> {
+        if self.question_time_limit_seconds.is_some() && self.opened_at(question_id).is_none() {
+            return Err("Question must be opened before it is answered")?;
+        }
+
+        if self
+            .question_deadline(question_id)
+            .is_some_and(|deadline| is_past(deadline, now))
+        {
+            return Err("Time is up for this question")?;
+        }
+
+        Ok(())
+    }
+
+    /// Refuses to change the answer to a question whose explanation is already shown, which
+    /// happens as soon as it is answered.
+    pub fn check_answer_change(&self, question: &Question) -> Result<(), ModelError> {
+        if question.explanation.is_some() && self.response_for(&question.id).is_some() {
+            return Err("The explanation has been shown, so the answer is final")?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the response to a question, replacing any earlier response to it.
+    pub fn record_answer(&mut self, question_id: &str, response: Response) {
+        match self
+            .answers
+            .iter_mut()
+            .find(|answer| answer.question_id == question_id)
+        {
+            Some(answer) => answer.response = response,
+            None => self.answers.push(AttemptAnswer {
+                question_id: question_id.to_string(),
+                response,
+            }),
+        }
+    }
+
+    pub fn hints_revealed(&self, question_id: &str) -> usize {
+        self.revealed_hints
+            .iter()
+            .filter(|revealed| revealed.question_id == question_id)
+            .count()
+    }
+
+    /// Reveals the next hint of a question and returns its index.
+    pub fn reveal_hint(&mut self, question: &Question, now: DateTime) -> Result<usize, ModelError> {
+        let next = self.hints_revealed(&question.id);
+
+        if next >= question.hints.len() {
+            return Err("There are no more hints for this question")?;
+        }
+
+        self.revealed_hints.push(RevealedHint {
+            question_id: question.id.clone(),
+            revealed_at: now,
+        });
+
+        Ok(next)
+    }
+
+    pub fn response_for(&self, question_id: &str) -> Option<&Response> {
+        self.answers
+            .iter()
+            .find(|answer| answer.question_id == question_id)
+            .map(|answer| &answer.response)
+    }
+
+    /// The questions of the template given in this attempt, in the order they were shown.
+    pub fn questions<'q>(&self, questions: &'q [Question]) -> Vec<&'q Question> {
+        match &self.paper {
+            Some(paper) => paper.questions(questions),
+            None => questions.iter().collect(),
+        }
+    }
+
+    /// Grades every question given in the attempt, picked from the questions of the template.
+    /// Unanswered questions count as incorrect. Each question earns its points times its credit
+    /// less the penalty of the hints revealed, never below zero. Scores are rounded as the
+    /// template's scoring says.
+    pub fn grade(&self, questions: &[Question]) -> AttemptResult {
+        let questions = self.questions(questions);
+
+        let question_results = questions
+            .iter()
+            .map(|question| {
+                let response = self.response_for(&question.id);
+                let verdict = response.map(|response| question.check(response));
+                let credit = response.map_or(0.0, |response| question.credit(response));
+                let hints_revealed = self.hints_revealed(&question.id);
+                let penalty = question.hint_penalty(hints_revealed);
+
+                QuestionResult {
+                    confidence: verdict.map_or(0.0, |verdict| verdict.confidence),
+                    correct: verdict.is_some_and(|verdict| verdict.matched),
+                    credit,
+                    hints_revealed,
+                    max_score: question.points,
+                    penalty,
+                    question_id: question.id.clone(),
+                    response: response.cloned(),
+                    score: self
+                        .scoring
+                        .round(question.points * (credit - penalty).max(0.0)),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let max_score = questions
+            .iter()
+            .map(|question| question.points)
+            .sum::<f64>();
+        let score = question_results
+            .iter()
+            .map(|result| result.score)
+            .sum::<f64>();
+
+        let percentage = match max_score > 0.0 {
+            true => self.scoring.round(score / max_score * 100.0),
+            false => 0.0,
+        };
+
+        AttemptResult {
+            max_score: self.scoring.round(max_score),
+            passed: self.scoring.passed(percentage),
+            percentage,
+            question_results,
+            score: self.scoring.round(score),
+        }
+    }
+
+    pub fn submit(&mut self, questions: &[Question]) {
+        self.result = Some(self.grade(questions));
+        self.status = AttemptStatus::Submitted;
+        self.submitted_at = Some(DateTime::now());
+    }
+}
+
+pub struct Attempts {
+    collection: Collection<Attempt>,
+}
+
+impl Attempts {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<Attempt>(ATTEMPTS_COLLECTION),
+        }
+    }
+
+    /// Open attempts whose deadline and grace window have both passed.
+    pub async fn read_expired(&self, now: DateTime) -> Result<Vec<Attempt>, ModelError> {
+        self.read_by_filter(doc! {
+            "deadline_at": {
+                "$lt": DateTime::from_millis(now.timestamp_millis() - GRACE_MILLIS),
+            },
+            "status": to_bson(&AttemptStatus::InProgress)?,
+        })
+        .await
+    }
+
+    /// Writes back an attempt read while it was in progress, unless it has been submitted since.
+    /// Returns whether the attempt was still in progress; nothing is written when it was not.
+    pub async fn update_in_progress(&self, attempt: &Attempt) -> Result<bool, ModelError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": ObjectId::from_str(&attempt.id)?,
+                    "status": to_bson(&AttemptStatus::InProgress)?,
+                },
+                doc! {
+                    "$set": self.update_doc(attempt)?,
+                },
+            )
+            .await?;
+
+        Ok(result.matched_count == 1)
+    }
+
+    pub async fn read_by_user_id(
+        &self,
+        user_id: &str,
+        quiz_template_id: Option<&str>,
+    ) -> Result<Vec<Attempt>, ModelError> {
+        let mut filter = doc! {
+            "user_id": ObjectId::from_str(user_id)?,
+        };
+
+        if let Some(quiz_template_id) = quiz_template_id {
+            filter.insert("quiz_template_id", ObjectId::from_str(quiz_template_id)?);
+        }
+
+        self.read_by_filter(filter).await
+    }
+}
+
+fn add_seconds(time: DateTime, seconds: u32) -> DateTime {
+    DateTime::from_millis(time.timestamp_millis() + i64::from(seconds) * 1000)
+}
+
+fn is_past(deadline: DateTime, now: DateTime) -> bool {
+    now.timestamp_millis() > deadline.timestamp_millis() + GRACE_MILLIS
+}
+
+impl BaseModel<Attempt> for Attempts {
+    fn collection(&self) -> Collection<Attempt> {
+        self.collection.clone()
+    }
+
+    fn update_doc(&self, model: &Attempt) -> Result<Document, ModelError> {
+        Ok(doc! {
+            "answers": to_bson(&model.answers)?,
+            "opened_questions": to_bson(&model.opened_questions)?,
+            "result": to_bson(&model.result)?,
+            "revealed_hints": to_bson(&model.revealed_hints)?,
+            "status": to_bson(&model.status)?,
+            "submitted_at": model.submitted_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::question::{Hint, QuestionKind};
+    use crate::models::scoring::{PartialCredit, Scoring};
+
+    fn quiz_template(
+        time_limit_seconds: Option<u32>,
+        question_time_limit_seconds: Option<u32>,
+    ) -> QuizTemplate {
+        QuizTemplate {
+            id: ObjectId::new().to_string(),
+            name: "Capitals".to_string(),
+            order_version: 0,
+            question_count: 0,
+            question_time_limit_seconds,
+            scoring: Scoring::default(),
+            time_limit_seconds,
+            user_id: ObjectId::new().to_string(),
+        }
+    }
+
+    fn question(answer: &str) -> Question {
+        Question::fixture(
+            &ObjectId::new().to_string(),
+            "?",
+            answer,
+            QuestionKind::default(),
+        )
+    }
+
+    #[test]
+    fn test_record_answer_replaces_previous_response() {
+        let mut attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+        let question_id = ObjectId::new().to_string();
+
+        attempt.record_answer(&question_id, Response::Text("first".to_string()));
+        attempt.record_answer(&question_id, Response::Text("second".to_string()));
+
+        assert_eq!(1, attempt.answers.len());
+        assert_eq!(
+            Some(&Response::Text("second".to_string())),
+            attempt.response_for(&question_id)
+        );
+    }
+
+    #[test]
+    fn test_answer_is_final_once_the_explanation_is_shown() {
+        let mut attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+
+        let plain = question("Paris");
+        let explained = Question {
+            explanation: Some("Paris has been the capital since 987.".to_string()),
+            ..question("Paris")
+        };
+
+        assert!(attempt.check_answer_change(&explained).is_ok());
+
+        attempt.record_answer(&plain.id, Response::Text("Paris".to_string()));
+        attempt.record_answer(&explained.id, Response::Text("Paris".to_string()));
+
+        assert!(attempt.check_answer_change(&plain).is_ok());
+        assert!(attempt.check_answer_change(&explained).is_err());
+    }
+
+    #[test]
+    fn test_submit() {
+        let questions = vec![question("Paris"), question("Berlin"), question("Rome")];
+
+        let mut attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+
+        attempt.record_answer(&questions[0].id, Response::Text(" Paris ".to_string()));
+        attempt.record_answer(&questions[1].id, Response::Text("Madrid".to_string()));
+
+        attempt.submit(&questions);
+
+        let result = attempt.result.unwrap();
+
+        assert_eq!(AttemptStatus::Submitted, attempt.status);
+        assert!(attempt.submitted_at.is_some());
+        assert_eq!(3.0, result.max_score);
+        assert_eq!(1.0, result.score);
+        assert_eq!(
+            vec![true, false, false],
+            result
+                .question_results
+                .iter()
+                .map(|result| result.correct)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, result.question_results[2].response);
+    }
+
+    #[test]
+    fn test_submit_grades_paper_questions() {
+        let questions = vec![question("Paris"), question("Berlin"), question("Rome")];
+
+        let mut attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+
+        attempt.paper = Some(Paper {
+            options: Default::default(),
+            question_ids: vec![questions[2].id.clone(), questions[0].id.clone()],
+            seed: 1,
+        });
+
+        attempt.record_answer(&questions[2].id, Response::Text("Rome".to_string()));
+
+        attempt.submit(&questions);
+
+        let result = attempt.result.unwrap();
+
+        assert_eq!(2.0, result.max_score);
+        assert_eq!(1.0, result.score);
+        assert_eq!(
+            vec![questions[2].id.clone(), questions[0].id.clone()],
+            result
+                .question_results
+                .iter()
+                .map(|result| result.question_id.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_revealed_hints_are_deducted() {
+        let mut questions = vec![question("Paris"), question("Berlin")];
+
+        for question in &mut questions {
+            question.hints = vec![
+                Hint {
+                    penalty: 0.25,
+                    text: "A capital".to_string(),
+                },
+                Hint {
+                    penalty: 0.5,
+                    text: "Starts with the answer's first letter".to_string(),
+                },
+            ];
+        }
+
+        let mut attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+        let now = DateTime::now();
+
+        assert_eq!(0, attempt.reveal_hint(&questions[0], now).unwrap());
+        assert_eq!(1, attempt.reveal_hint(&questions[0], now).unwrap());
+        assert!(attempt.reveal_hint(&questions[0], now).is_err());
+        assert_eq!(0, attempt.reveal_hint(&questions[1], now).unwrap());
+
+        attempt.record_answer(&questions[0].id, Response::Text("Paris".to_string()));
+        attempt.record_answer(&questions[1].id, Response::Text("Bonn".to_string()));
+
+        attempt.submit(&questions);
+
+        let result = attempt.result.unwrap();
+
+        assert_eq!(0.25, result.score);
+        assert_eq!(2, result.question_results[0].hints_revealed);
+        assert_eq!(0.75, result.question_results[0].penalty);
+        assert_eq!(0.0, result.question_results[1].score);
+    }
+
+    #[test]
+    fn test_points_partial_credit_and_pass_mark() {
+        let mut questions = vec![question("Paris"), question("")];
+
+        questions[0].points = 2.0;
+        questions[1].kind = QuestionKind::MultipleSelect {
+            correct_options: vec![0, 1],
+            options: vec!["2".to_string(), "3".to_string(), "4".to_string()],
+        };
+        questions[1].partial_credit = PartialCredit::Proportional;
+
+        let mut quiz_template = quiz_template(None, None);
+
+        quiz_template.scoring = Scoring {
+            pass_mark: Some(80.0),
+            score_decimals: 1,
+        };
+
+        let mut attempt = Attempt::new(&quiz_template, &ObjectId::new().to_string());
+
+        attempt.record_answer(&questions[0].id, Response::Text("Paris".to_string()));
+        attempt.record_answer(&questions[1].id, Response::Choices(vec![0]));
+
+        attempt.submit(&questions);
+
+        let result = attempt.result.unwrap();
+
+        assert_eq!(3.0, result.max_score);
+        assert_eq!(2.7, result.score);
+        assert_eq!(90.0, result.percentage);
+        assert_eq!(Some(true), result.passed);
+        assert_eq!(2.0, result.question_results[0].max_score);
+        assert!(!result.question_results[1].correct);
+        assert_eq!(0.7, result.question_results[1].score);
+    }
+
+    #[test]
+    fn test_time_limits() {
+        let mut attempt = Attempt::new(
+            &quiz_template(Some(60), Some(10)),
+            &ObjectId::new().to_string(),
+        );
+        let started_at = attempt.started_at.timestamp_millis();
+        let at = |seconds: i64| DateTime::from_millis(started_at + seconds * 1000);
+        let question_id = ObjectId::new().to_string();
+
+        assert!(attempt.check_time(&question_id, at(1)).is_err());
+
+        attempt.open_question(&question_id, at(55));
+        attempt.open_question(&question_id, at(58));
+
+        assert_eq!(Some(at(60)), attempt.question_deadline(&question_id));
+        assert!(attempt.check_time(&question_id, at(64)).is_ok());
+        assert!(attempt.check_time(&question_id, at(66)).is_err());
+
+        assert!(!attempt.is_expired(at(65)));
+        assert!(attempt.is_expired(at(66)));
+
+        attempt.status = AttemptStatus::Submitted;
+        assert!(!attempt.is_expired(at(66)));
+    }
+
+    #[test]
+    fn test_untimed_attempt_never_expires() {
+        let attempt = Attempt::new(&quiz_template(None, None), &ObjectId::new().to_string());
+        let question_id = ObjectId::new().to_string();
+        let later = DateTime::from_millis(attempt.started_at.timestamp_millis() + 86_400_000);
+
+        assert_eq!(None, attempt.deadline_at);
+        assert!(!attempt.is_expired(later));
+        assert!(attempt.check_time(&question_id, later).is_ok());
+    }
+}
@@ -1,13 +1,20 @@
 use std::str::FromStr;
 
 use mongodb::{
-    bson::{doc, oid::ObjectId, Document},
+    bson::{doc, from_document, oid::ObjectId, Document},
     Collection,
 };
 use rocket::futures::TryStreamExt;
 use serde::{de::DeserializeOwned, Serialize};
 
-use super::error::ModelError;
+use super::{
+    error::ModelError,
+    filter::Filter,
+    pagination::{Page, PageRequest},
+};
+
+/// Field the relevance of text search results is projected into.
+const TEXT_SCORE: &str = "_text_score";
 
 pub trait BaseModel<Model>
 where
@@ -57,6 +64,24 @@ where
         Ok(models)
     }
 
+    /// Reads one page of the documents matching `filter`, in the order the request sorts by.
+    async fn read_page(
+        &self,
+        filter: Filter,
+        page_request: &PageRequest,
+    ) -> Result<Page<Model>, ModelError> {
+        let models = self
+            .collection()
+            .find(page_request.filter(filter.build()))
+            .sort(page_request.sort_document())
+            .limit(page_request.read_limit())
+            .await?
+            .try_collect()
+            .await?;
+
+        page_request.page(models)
+    }
+
     async fn read_by_id(&self, id: &str) -> Result<Option<Model>, ModelError> {
         let model = self
             .collection()
@@ -68,6 +93,38 @@ where
         Ok(model)
     }
 
+    /// Reads up to `limit` documents matching `search` and `filter`, best matches first, each
+    /// with its relevance score. The collection needs a text index.
+    async fn read_by_text(
+        &self,
+        search: &str,
+        filter: Filter,
+        limit: i64,
+    ) -> Result<Vec<(f64, Model)>, ModelError> {
+        let documents: Vec<Document> = self
+            .collection()
+            .clone_with_type::<Document>()
+            .find(filter.text(search).build())
+            .projection(doc! { TEXT_SCORE: { "$meta": "textScore" } })
+            .sort(doc! { TEXT_SCORE: { "$meta": "textScore" } })
+            .limit(limit)
+            .await?
+            .try_collect()
+            .await?;
+
+        documents
+            .into_iter()
+            .map(|mut document| {
+                let score = document
+                    .remove(TEXT_SCORE)
+                    .and_then(|score| score.as_f64())
+                    .unwrap_or_default();
+
+                Ok((score, from_document(document)?))
+            })
+            .collect()
+    }
+
     async fn update_by_id(&self, id: &str, updated_model: &Model) -> Result<bool, ModelError> {
         let result = self
             .collection()
@@ -102,10 +159,10 @@ mod tests {
 
     use super::*;
 
-    const TEST_CREATE_COLLECTION_NAME: &'static str = "create_super_heroes";
-    const TEST_DELETE_COLLECTION_NAME: &'static str = "delete_super_heroes";
-    const TEST_READ_COLLECTION_NAME: &'static str = "read_super_heroes";
-    const TEST_UPDATE_COLLECTION_NAME: &'static str = "update_super_heroes";
+    const TEST_CREATE_COLLECTION_NAME: &str = "create_super_heroes";
+    const TEST_DELETE_COLLECTION_NAME: &str = "delete_super_heroes";
+    const TEST_READ_COLLECTION_NAME: &str = "read_super_heroes";
+    const TEST_UPDATE_COLLECTION_NAME: &str = "update_super_heroes";
 
     #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
     struct SuperHero {
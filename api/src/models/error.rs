@@ -1,23 +1,27 @@
 use std::fmt::Display;
 
 use argon2::password_hash;
-use mongodb::bson::oid;
+use mongodb::bson::{de, oid, ser};
 
 #[derive(Clone, Debug)]
 pub enum ModelError {
     AuthenticationFailed,
+    BsonDeserializationError(de::Error),
+    BsonSerializationError(ser::Error),
     Generic(String),
     InsertError,
     MongoError(mongodb::error::Error),
     OidError(oid::Error),
     PasswordHashError(password_hash::Error),
-    UserDoesNotExist(String)
+    UserDoesNotExist(String),
 }
 
 impl Display for ModelError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ModelError::AuthenticationFailed => write!(f, "Authentication failed"),
+            ModelError::BsonDeserializationError(error) => error.fmt(f),
+            ModelError::BsonSerializationError(error) => error.fmt(f),
             ModelError::Generic(error) => write!(f, "{}", error),
             ModelError::InsertError => write!(f, "Could not insert document"),
             ModelError::MongoError(error) => error.fmt(f),
@@ -34,6 +38,18 @@ impl From<&str> for ModelError {
     }
 }
 
+impl From<de::Error> for ModelError {
+    fn from(value: de::Error) -> Self {
+        Self::BsonDeserializationError(value)
+    }
+}
+
+impl From<ser::Error> for ModelError {
+    fn from(value: ser::Error) -> Self {
+        Self::BsonSerializationError(value)
+    }
+}
+
 impl From<mongodb::error::Error> for ModelError {
     fn from(value: mongodb::error::Error) -> Self {
         Self::MongoError(value)
@@ -56,4 +72,4 @@ impl From<String> for ModelError {
     fn from(value: String) -> Self {
         Self::Generic(value)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+
+use super::error::ModelError;
+
+/// Longest search text accepted by [`Filter::contains`].
+pub const MAX_CONTAINS_LENGTH: usize = 200;
+
+/// Builds a query out of known, escaped clauses so that client input never reaches MongoDB as
+/// an operator.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    clauses: Vec<Document>,
+}
+
+/// Smallest ObjectId generated at or after `time`. ObjectIds start with their creation time in
+/// seconds, so ranges over `_id` are ranges over creation time.
+fn object_id_at(time: DateTime) -> ObjectId {
+    let seconds = time
+        .timestamp_millis()
+        .div_euclid(1000)
+        .clamp(0, u32::MAX as i64) as u32;
+
+    let mut bytes = [0; 12];
+
+    bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+
+    ObjectId::from_bytes(bytes)
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Case-insensitive substring match. `text` is matched literally.
+    pub fn contains(mut self, field: &str, text: &str) -> Result<Self, ModelError> {
+        if text.chars().count() > MAX_CONTAINS_LENGTH {
+            return Err(format!(
+                "Search text must be at most {} characters",
+                MAX_CONTAINS_LENGTH
+            ))?;
+        }
+
+        self.clauses.push(doc! {
+            field: { "$regex": regex::escape(text), "$options": "i" },
+        });
+
+        Ok(self)
+    }
+
+    /// Documents whose ObjectId creation time is within the second of `time` or later.
+    pub fn created_after(mut self, time: DateTime) -> Self {
+        self.clauses
+            .push(doc! { "_id": { "$gte": object_id_at(time) } });
+
+        self
+    }
+
+    /// Documents whose ObjectId creation time is before the second of `time`.
+    pub fn created_before(mut self, time: DateTime) -> Self {
+        self.clauses
+            .push(doc! { "_id": { "$lt": object_id_at(time) } });
+
+        self
+    }
+
+    /// Documents matching `search` in the collection's text index.
+    pub fn text(mut self, search: &str) -> Self {
+        self.clauses.push(doc! { "$text": { "$search": search } });
+
+        self
+    }
+
+    pub fn id_equals(mut self, field: &str, id: &str) -> Result<Self, ModelError> {
+        self.clauses.push(doc! { field: ObjectId::from_str(id)? });
+
+        Ok(self)
+    }
+
+    pub fn id_in(mut self, field: &str, ids: Vec<ObjectId>) -> Self {
+        self.clauses.push(doc! { field: { "$in": ids } });
+
+        self
+    }
+
+    pub fn at_least(mut self, field: &str, value: i64) -> Self {
+        self.clauses.push(doc! { field: { "$gte": value } });
+
+        self
+    }
+
+    pub fn at_most(mut self, field: &str, value: i64) -> Self {
+        self.clauses.push(doc! { field: { "$lte": value } });
+
+        self
+    }
+
+    pub fn build(mut self) -> Document {
+        match self.clauses.len() {
+            0 => doc! {},
+            1 => self.clauses.remove(0),
+            _ => doc! { "$and": self.clauses },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_is_literal() {
+        let filter = Filter::new().contains("name", "a.*(").unwrap().build();
+
+        assert_eq!(
+            doc! { "name": { "$regex": r"a\.\*\(", "$options": "i" } },
+            filter
+        );
+
+        assert!(Filter::new()
+            .contains("name", &"a".repeat(MAX_CONTAINS_LENGTH + 1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_created_range() {
+        let time = DateTime::from_millis(1_700_000_000_500);
+
+        let boundary = object_id_at(time);
+
+        assert_eq!(1_700_000_000_000, boundary.timestamp().timestamp_millis());
+
+        let filter = Filter::new()
+            .created_after(time)
+            .created_before(time)
+            .build();
+
+        assert_eq!(
+            doc! {
+                "$and": [
+                    { "_id": { "$gte": boundary } },
+                    { "_id": { "$lt": boundary } },
+                ],
+            },
+            filter
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        assert_eq!(doc! {}, Filter::new().build());
+        assert_eq!(
+            doc! { "$text": { "$search": "capital" } },
+            Filter::new().text("capital").build()
+        );
+        assert!(Filter::new().id_equals("user_id", "not an id").is_err());
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(
+            doc! { "question_count": { "$gte": 1_i64 } },
+            Filter::new().at_least("question_count", 1).build()
+        );
+        assert_eq!(
+            doc! { "question_count": { "$lte": 0_i64 } },
+            Filter::new().at_most("question_count", 0).build()
+        );
+    }
+}
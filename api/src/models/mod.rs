@@ -1,11 +1,29 @@
+mod attachment;
+mod attempt;
 mod base_model;
 mod error;
+mod filter;
+mod option_ids;
+mod pagination;
+mod paper;
 mod question;
 mod quiz_template;
+mod review;
+mod scoring;
 mod user;
 
+pub use attachment::{
+    validate as validate_attachment, Attachment, Attachments, MAX_ATTACHMENTS, MAX_ATTACHMENT_SIZE,
+};
+pub use attempt::{Attempt, AttemptResult, AttemptStatus, Attempts, QuestionResult};
 pub use base_model::BaseModel;
 pub use error::ModelError;
-pub use question::{Question, Questions};
+pub use filter::Filter;
+pub use option_ids::{new_option_key, OptionIds};
+pub use pagination::{Page, PageRequest, Sort, SortDirection, MAX_PAGE_SIZE};
+pub use paper::{Paper, PaperOptions};
+pub use question::{Hint, Question, QuestionKind, Questions, Response};
 pub use quiz_template::{QuizTemplate, QuizTemplates};
-pub use user::{User, Users};
\ No newline at end of file
+pub use review::{end_of_day, Review, Reviews, MAX_QUALITY};
+pub use scoring::{default_points, validate_points, PartialCredit, Scoring};
+pub use user::{Role, User, Users};
@@ -0,0 +1,161 @@
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+use super::{error::ModelError, question::Response};
+
+const KEY_LENGTH: usize = 32;
+
+/// A fresh secret for [`OptionIds`].
+pub fn new_option_key() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(KEY_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// The ids learners see for the options of a question instead of their stored indexes.
+///
+/// Ordering items are stored in their correct order, so their indexes would give the answer
+/// away. Ids are a permutation of the indexes keyed by a secret that never leaves the server,
+/// which lets responses keep using small integers while saying nothing about the answer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptionIds {
+    /// The id of the option at each index.
+    ids: Vec<usize>,
+}
+
+impl OptionIds {
+    pub fn new(key: &str, question_id: &str, count: usize) -> Self {
+        let mut indexes = (0..count).collect::<Vec<_>>();
+
+        indexes.sort_by_cached_key(|index| {
+            sha1_smol::Sha1::from(format!("{}:{}:{}", key, question_id, index))
+                .digest()
+                .bytes()
+        });
+
+        let mut ids = vec![0; count];
+
+        for (id, index) in indexes.into_iter().enumerate() {
+            ids[index] = id;
+        }
+
+        Self { ids }
+    }
+
+    /// Indexes past the last option, left behind when an edit removed options, are kept as
+    /// they are.
+    pub fn id(&self, index: usize) -> usize {
+        self.ids.get(index).copied().unwrap_or(index)
+    }
+
+    pub fn index(&self, id: usize) -> Result<usize, ModelError> {
+        let index = self
+            .ids
+            .iter()
+            .position(|option_id| *option_id == id)
+            .ok_or_else(|| format!("Option {} does not exist", id))?;
+
+        Ok(index)
+    }
+
+    /// Option indexes ordered by id, which is a shuffle nobody without the key can undo.
+    pub fn shuffled(&self) -> Vec<usize> {
+        let mut indexes = (0..self.ids.len()).collect::<Vec<_>>();
+
+        indexes.sort_by_key(|index| self.ids[*index]);
+
+        indexes
+    }
+
+    /// Turns a response stored with option indexes into one that refers to option ids.
+    pub fn to_ids(&self, response: &Response) -> Response {
+        match response {
+            Response::Choice(index) => Response::Choice(self.id(*index)),
+            Response::Choices(indexes) => {
+                Response::Choices(indexes.iter().map(|index| self.id(*index)).collect())
+            }
+            Response::Order(indexes) => {
+                Response::Order(indexes.iter().map(|index| self.id(*index)).collect())
+            }
+            _ => response.clone(),
+        }
+    }
+
+    /// Turns a response that refers to option ids into one with option indexes.
+    pub fn to_indexes(&self, response: &Response) -> Result<Response, ModelError> {
+        let to_indexes = |ids: &Vec<usize>| {
+            ids.iter()
+                .map(|id| self.index(*id))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(match response {
+            Response::Choice(id) => Response::Choice(self.index(*id)?),
+            Response::Choices(ids) => Response::Choices(to_indexes(ids)?),
+            Response::Order(ids) => Response::Order(to_indexes(ids)?),
+            _ => response.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_a_permutation() {
+        let ids = OptionIds::new("key", "question", 6);
+
+        let mut all = (0..6).map(|index| ids.id(index)).collect::<Vec<_>>();
+
+        all.sort_unstable();
+
+        assert_eq!((0..6).collect::<Vec<_>>(), all);
+
+        for index in 0..6 {
+            assert_eq!(index, ids.index(ids.id(index)).unwrap());
+        }
+
+        assert!(ids.index(6).is_err());
+    }
+
+    #[test]
+    fn test_ids_depend_on_key_and_question() {
+        let ids = OptionIds::new("key", "question", 8);
+
+        assert_eq!(ids, OptionIds::new("key", "question", 8));
+        assert_ne!(ids, OptionIds::new("other", "question", 8));
+        assert_ne!(ids, OptionIds::new("key", "other", 8));
+    }
+
+    #[test]
+    fn test_shuffled_lists_options_by_id() {
+        let ids = OptionIds::new("key", "question", 5);
+
+        let shuffled = ids.shuffled();
+
+        for (id, index) in shuffled.into_iter().enumerate() {
+            assert_eq!(id, ids.id(index));
+        }
+    }
+
+    #[test]
+    fn test_responses_round_trip() {
+        let ids = OptionIds::new("key", "question", 4);
+
+        let order = Response::Order(vec![0, 1, 2, 3]);
+        let shown = ids.to_ids(&order);
+
+        assert_eq!(
+            Response::Order((0..4).map(|index| ids.id(index)).collect()),
+            shown
+        );
+        assert_eq!(order, ids.to_indexes(&shown).unwrap());
+
+        let text = Response::Text("Paris".to_string());
+
+        assert_eq!(text, ids.to_ids(&text));
+        assert!(ids.to_indexes(&Response::Choice(4)).is_err());
+    }
+}
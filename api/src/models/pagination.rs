@@ -0,0 +1,517 @@
+use std::cmp::Ordering;
+
+use base64::prelude::*;
+use mongodb::bson::{doc, oid::ObjectId, to_document, Bson, Document};
+use serde::Serialize;
+
+use super::error::ModelError;
+
+/// Largest page a client may request, and the size used when it asks for none.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    fn reverse(self) -> Self {
+        match self {
+            Self::Ascending => Self::Descending,
+            Self::Descending => Self::Ascending,
+        }
+    }
+
+    fn order(self) -> i32 {
+        match self {
+            Self::Ascending => 1,
+            Self::Descending => -1,
+        }
+    }
+
+    fn operator(self) -> &'static str {
+        match self {
+            Self::Ascending => "$gt",
+            Self::Descending => "$lt",
+        }
+    }
+}
+
+/// A field to order by. Ties are broken by `_id` in the same direction, so every sort is total
+/// and can be resumed from a cursor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sort {
+    pub direction: SortDirection,
+    pub field: String,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            direction: SortDirection::Ascending,
+            field: "_id".to_string(),
+        }
+    }
+}
+
+impl Sort {
+    fn document(&self, direction: SortDirection) -> Document {
+        let mut document = doc! {};
+
+        if self.field != "_id" {
+            document.insert(&self.field, direction.order());
+        }
+
+        document.insert("_id", direction.order());
+
+        document
+    }
+
+    /// Orders two cursors ascending, for models paged in memory. Only `_id` and whole number
+    /// fields can be compared.
+    fn compare(&self, a: &Cursor, b: &Cursor) -> Result<Ordering, ModelError> {
+        if self.field == "_id" {
+            return Ok(a.id.cmp(&b.id));
+        }
+
+        let value = |cursor: &Cursor| match cursor.value {
+            Bson::Int32(value) => Ok(value as i64),
+            Bson::Int64(value) => Ok(value),
+            _ => Err(ModelError::from(
+                "Loaded pages must be sorted by _id or a whole number",
+            )),
+        };
+
+        Ok(value(a)?.cmp(&value(b)?).then(a.id.cmp(&b.id)))
+    }
+
+    /// Matches documents strictly past the cursor when walking in `direction`.
+    fn past(&self, cursor: &Cursor, direction: SortDirection) -> Document {
+        let operator = direction.operator();
+
+        if self.field == "_id" {
+            return doc! { "_id": { operator: cursor.id } };
+        }
+
+        doc! {
+            "$or": [
+                { &self.field: { operator: cursor.value.clone() } },
+                { &self.field: cursor.value.clone(), "_id": { operator: cursor.id } },
+            ],
+        }
+    }
+}
+
+/// Position of a document in a sorted listing: its sort key value and `_id`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cursor {
+    pub id: ObjectId,
+    pub value: Bson,
+}
+
+impl Cursor {
+    fn from_model<Model: Serialize>(model: &Model, sort: &Sort) -> Result<Self, ModelError> {
+        let document = to_document(model)?;
+
+        let id = document
+            .get_object_id("_id")
+            .map_err(|_| ModelError::from("Documents must have an ObjectId _id"))?;
+
+        Ok(Self {
+            id,
+            value: document.get(&sort.field).cloned().unwrap_or(Bson::Null),
+        })
+    }
+
+    pub fn decode(value: &str) -> Result<Self, ModelError> {
+        let invalid = || ModelError::from("Invalid cursor");
+
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|_| invalid())?;
+        let document = Document::from_reader(bytes.as_slice()).map_err(|_| invalid())?;
+
+        Ok(Self {
+            id: document.get_object_id("id").map_err(|_| invalid())?,
+            value: document.get("value").cloned().unwrap_or(Bson::Null),
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![];
+
+        doc! { "id": self.id, "value": self.value.clone() }
+            .to_writer(&mut bytes)
+            .expect("Cursor documents always serialize");
+
+        BASE64_URL_SAFE_NO_PAD.encode(bytes)
+    }
+}
+
+/// Relay style window over a listing. `first`/`after` page forwards and `last`/`before` page
+/// backwards.
+#[derive(Clone, Debug, Default)]
+pub struct PageRequest {
+    pub after: Option<Cursor>,
+    pub before: Option<Cursor>,
+    pub first: Option<usize>,
+    pub last: Option<usize>,
+    pub sort: Sort,
+}
+
+impl PageRequest {
+    pub fn new(
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<Self, ModelError> {
+        let size = |value: Option<i32>| -> Result<Option<usize>, ModelError> {
+            value
+                .map(|value| match usize::try_from(value) {
+                    Ok(value) if value <= MAX_PAGE_SIZE => Ok(value),
+                    _ => Err(ModelError::from(format!(
+                        "Page size must be between 0 and {}",
+                        MAX_PAGE_SIZE
+                    ))),
+                })
+                .transpose()
+        };
+
+        if first.is_some() && last.is_some() {
+            return Err("Use either first or last, not both")?;
+        }
+
+        Ok(Self {
+            after: after.as_deref().map(Cursor::decode).transpose()?,
+            before: before.as_deref().map(Cursor::decode).transpose()?,
+            first: size(first)?,
+            last: size(last)?,
+            sort: Sort::default(),
+        })
+    }
+
+    pub fn with_sort(self, sort: Sort) -> Self {
+        Self { sort, ..self }
+    }
+
+    /// Whether the request is for the start of a listing, read forwards.
+    pub fn is_first_page(&self) -> bool {
+        self.after.is_none() && self.before.is_none() && self.last.is_none()
+    }
+
+    fn is_backwards(&self) -> bool {
+        self.last.is_some()
+    }
+
+    fn limit(&self) -> usize {
+        self.first.or(self.last).unwrap_or(MAX_PAGE_SIZE)
+    }
+
+    /// Adds the cursor bounds to `filter`.
+    pub fn filter(&self, filter: Document) -> Document {
+        let mut clauses = vec![];
+
+        if !filter.is_empty() {
+            clauses.push(filter);
+        }
+
+        if let Some(after) = &self.after {
+            clauses.push(self.sort.past(after, self.sort.direction));
+        }
+
+        if let Some(before) = &self.before {
+            clauses.push(self.sort.past(before, self.sort.direction.reverse()));
+        }
+
+        match clauses.len() {
+            0 => doc! {},
+            1 => clauses.remove(0),
+            _ => doc! { "$and": clauses },
+        }
+    }
+
+    /// The order to read in. Backwards pages are read in reverse and flipped afterwards.
+    pub fn sort_document(&self) -> Document {
+        let direction = if self.is_backwards() {
+            self.sort.direction.reverse()
+        } else {
+            self.sort.direction
+        };
+
+        self.sort.document(direction)
+    }
+
+    /// How many documents to read: one more than the page so we know if another page follows.
+    pub fn read_limit(&self) -> i64 {
+        self.limit() as i64 + 1
+    }
+
+    /// Pages through every matching model already loaded in the order of an ascending sort,
+    /// for callers that batch their reads.
+    pub fn page_loaded<Model: Serialize>(
+        &self,
+        models: Vec<Model>,
+    ) -> Result<Page<Model>, ModelError> {
+        if self.sort.direction != SortDirection::Ascending {
+            return Err("Loaded pages must be sorted in ascending order")?;
+        }
+
+        let mut window = vec![];
+
+        for model in models {
+            let cursor = Cursor::from_model(&model, &self.sort)?;
+
+            let is_after = match &self.after {
+                Some(after) => self.sort.compare(&cursor, after)? == Ordering::Greater,
+                None => true,
+            };
+            let is_before = match &self.before {
+                Some(before) => self.sort.compare(&cursor, before)? == Ordering::Less,
+                None => true,
+            };
+
+            if is_after && is_before {
+                window.push(model);
+            }
+        }
+
+        if self.is_backwards() {
+            window.reverse();
+        }
+
+        window.truncate(self.read_limit() as usize);
+
+        self.page(window)
+    }
+
+    pub fn page<Model: Serialize>(
+        &self,
+        mut models: Vec<Model>,
+    ) -> Result<Page<Model>, ModelError> {
+        let has_more = models.len() > self.limit();
+
+        models.truncate(self.limit());
+
+        if self.is_backwards() {
+            models.reverse();
+        }
+
+        let edges = models
+            .into_iter()
+            .map(|model| Ok((Cursor::from_model(&model, &self.sort)?.encode(), model)))
+            .collect::<Result<Vec<_>, ModelError>>()?;
+
+        let (has_next_page, has_previous_page) = if self.is_backwards() {
+            (self.before.is_some(), has_more)
+        } else {
+            (has_more, self.after.is_some())
+        };
+
+        Ok(Page {
+            edges,
+            has_next_page,
+            has_previous_page,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Page<Model> {
+    /// Each model with the cursor that points at it.
+    pub edges: Vec<(String, Model)>,
+    pub has_next_page: bool,
+    pub has_previous_page: bool,
+}
+
+impl<Model> Page<Model> {
+    pub fn end_cursor(&self) -> Option<String> {
+        self.edges.last().map(|(cursor, _)| cursor.clone())
+    }
+
+    pub fn start_cursor(&self) -> Option<String> {
+        self.edges.first().map(|(cursor, _)| cursor.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::serde_helpers::serialize_hex_string_as_object_id;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize)]
+    struct Item {
+        #[serde(rename = "_id", serialize_with = "serialize_hex_string_as_object_id")]
+        id: String,
+        position: i32,
+    }
+
+    fn items(count: i32) -> Vec<Item> {
+        (0..count)
+            .map(|position| Item {
+                id: ObjectId::new().to_string(),
+                position,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            id: ObjectId::new(),
+            value: Bson::Int32(3),
+        };
+
+        assert_eq!(cursor, Cursor::decode(&cursor.encode()).unwrap());
+        assert!(Cursor::decode("not a cursor").is_err());
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(PageRequest::new(Some(1), None, Some(1), None).is_err());
+        assert!(PageRequest::new(Some(-1), None, None, None).is_err());
+        assert!(PageRequest::new(Some(MAX_PAGE_SIZE as i32 + 1), None, None, None).is_err());
+        assert!(PageRequest::new(None, Some("bad".to_string()), None, None).is_err());
+    }
+
+    #[test]
+    fn test_page_forwards() {
+        let request = PageRequest::new(Some(2), None, None, None).unwrap();
+
+        let page = request.page(items(3)).unwrap();
+
+        assert_eq!(2, page.edges.len());
+        assert!(page.has_next_page);
+        assert!(!page.has_previous_page);
+        assert_eq!(3, request.read_limit());
+        assert_eq!(doc! { "_id": 1 }, request.sort_document());
+    }
+
+    #[test]
+    fn test_page_backwards() {
+        let models = items(4);
+
+        let before = Cursor::from_model(&models[3], &Sort::default()).unwrap();
+
+        let request = PageRequest::new(None, None, Some(2), Some(before.encode())).unwrap();
+
+        // Backwards pages are read in reverse order.
+        let page = request
+            .page(models[..3].iter().rev().cloned().collect())
+            .unwrap();
+
+        assert_eq!(
+            vec![1, 2],
+            page.edges
+                .iter()
+                .map(|(_, item)| item.position)
+                .collect::<Vec<_>>()
+        );
+        assert!(page.has_next_page);
+        assert!(page.has_previous_page);
+        assert_eq!(doc! { "_id": -1 }, request.sort_document());
+    }
+
+    #[test]
+    fn test_page_loaded() {
+        let models = items(5);
+
+        let after = Cursor::from_model(&models[0], &Sort::default()).unwrap();
+        let before = Cursor::from_model(&models[4], &Sort::default()).unwrap();
+
+        let positions = |page: Page<Item>| {
+            page.edges
+                .iter()
+                .map(|(_, item)| item.position)
+                .collect::<Vec<_>>()
+        };
+
+        let request = PageRequest::new(Some(2), Some(after.encode()), None, None).unwrap();
+
+        assert!(!request.is_first_page());
+        assert!(PageRequest::new(Some(2), None, None, None)
+            .unwrap()
+            .is_first_page());
+
+        let page = request.page_loaded(models.clone()).unwrap();
+
+        assert!(page.has_next_page);
+        assert_eq!(vec![1, 2], positions(page));
+
+        let request = PageRequest::new(None, None, Some(2), Some(before.encode())).unwrap();
+
+        let page = request.page_loaded(models.clone()).unwrap();
+
+        assert!(page.has_previous_page);
+        assert_eq!(vec![2, 3], positions(page));
+
+        let sort = Sort {
+            direction: SortDirection::Descending,
+            field: "position".to_string(),
+        };
+
+        assert!(PageRequest::default()
+            .with_sort(sort.clone())
+            .page_loaded(models.clone())
+            .is_err());
+
+        let sort = Sort {
+            direction: SortDirection::Ascending,
+            ..sort
+        };
+
+        // Ties on the sort key keep `_id` order.
+        let mut models = models;
+
+        models[3].position = 1;
+        models.sort_by_key(|item| item.position);
+
+        let after = Cursor::from_model(&models[1], &sort).unwrap();
+
+        let request = PageRequest::new(Some(2), Some(after.encode()), None, None)
+            .unwrap()
+            .with_sort(sort);
+
+        let page = request.page_loaded(models).unwrap();
+
+        assert!(page.has_next_page);
+        assert_eq!(vec![1, 2], positions(page));
+    }
+
+    #[test]
+    fn test_filter_with_sort_key() {
+        let models = items(1);
+
+        let sort = Sort {
+            direction: SortDirection::Descending,
+            field: "position".to_string(),
+        };
+
+        let after = Cursor::from_model(&models[0], &sort).unwrap();
+
+        let request = PageRequest::new(Some(1), Some(after.encode()), None, None)
+            .unwrap()
+            .with_sort(sort);
+
+        let id = ObjectId::parse_str(&models[0].id).unwrap();
+
+        assert_eq!(
+            doc! {
+                "$and": [
+                    { "name": "x" },
+                    {
+                        "$or": [
+                            { "position": { "$lt": 0 } },
+                            { "position": 0, "_id": { "$lt": id } },
+                        ],
+                    },
+                ],
+            },
+            request.filter(doc! { "name": "x" })
+        );
+        assert_eq!(doc! { "position": -1, "_id": -1 }, request.sort_document());
+    }
+}
@@ -0,0 +1,197 @@
+use rand::{seq::index::sample, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::ModelError,
+    question::{Question, QuestionKind},
+};
+
+/// How the questions of an attempt are drawn from its quiz template.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PaperOptions {
+    /// Questions drawn from the template. Every question when `None`.
+    pub question_count: Option<usize>,
+    pub shuffle_options: bool,
+    pub shuffle_questions: bool,
+}
+
+/// The questions an attempt was given, in the order they were shown.
+///
+/// Drawing is a pure function of the template's questions, the options and the seed, so the same
+/// paper can be rebuilt later with [`Paper::draw`] as long as the pool is unchanged. The drawn ids
+/// are kept as well so an attempt stays gradable after the template is edited.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Paper {
+    pub options: PaperOptions,
+    pub question_ids: Vec<String>,
+    /// Stored as `i64` since BSON has no unsigned 64 bit integer.
+    pub seed: i64,
+}
+
+impl Paper {
+    /// Draws a paper with a fresh random seed.
+    pub fn generate(pool: &[Question], options: PaperOptions) -> Result<Self, ModelError> {
+        let seed = ChaCha20Rng::from_rng(thread_rng())
+            .map_err(|e| e.to_string())?
+            .gen::<i64>();
+
+        Self::draw(pool, options, seed)
+    }
+
+    pub fn draw(pool: &[Question], options: PaperOptions, seed: i64) -> Result<Self, ModelError> {
+        let count = options.question_count.unwrap_or(pool.len());
+
+        if count == 0 || count > pool.len() {
+            return Err(format!(
+                "Question count must be between 1 and {}",
+                pool.len()
+            ))?;
+        }
+
+        let mut pool = pool.iter().collect::<Vec<_>>();
+
+        pool.sort_by(|a, b| (a.position, &a.id).cmp(&(b.position, &b.id)));
+
+        let mut rng = ChaCha20Rng::seed_from_u64(seed as u64);
+
+        let mut indexes = sample(&mut rng, pool.len(), count).into_vec();
+
+        if options.shuffle_questions {
+            indexes.shuffle(&mut rng);
+        } else {
+            indexes.sort_unstable();
+        }
+
+        Ok(Self {
+            options,
+            question_ids: indexes
+                .into_iter()
+                .map(|index| pool[index].id.clone())
+                .collect(),
+            seed,
+        })
+    }
+
+    /// The questions of the paper in order, skipping any that no longer exist.
+    pub fn questions<'q>(&self, pool: &'q [Question]) -> Vec<&'q Question> {
+        self.question_ids
+            .iter()
+            .filter_map(|id| pool.iter().find(|question| &question.id == id))
+            .collect()
+    }
+
+    /// Option indexes in the order they are shown for the question at `position`. Ordering
+    /// items are left alone: the seed is public, so they are shuffled with
+    /// [`OptionIds::shuffled`](super::OptionIds::shuffled) instead.
+    pub fn option_order(&self, position: usize, question: &Question) -> Vec<usize> {
+        let count = question.kind.options().len();
+        let shuffle = match &question.kind {
+            QuestionKind::MultipleChoice { .. } | QuestionKind::MultipleSelect { .. } => {
+                self.options.shuffle_options
+            }
+            _ => false,
+        };
+
+        let mut order = (0..count).collect::<Vec<_>>();
+
+        if shuffle {
+            // Each position gets its own stream so that option orders do not depend on the
+            // questions before it.
+            let mut rng = ChaCha20Rng::seed_from_u64(self.seed as u64);
+
+            rng.set_stream(position as u64 + 1);
+
+            order.shuffle(&mut rng);
+        }
+
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::oid::ObjectId;
+
+    use super::*;
+
+    fn pool(size: usize) -> Vec<Question> {
+        (0..size)
+            .map(|index| {
+                Question::fixture(
+                    &ObjectId::new().to_string(),
+                    &format!("Question {index}"),
+                    "",
+                    QuestionKind::MultipleChoice {
+                        correct_option: 0,
+                        options: (0..4).map(|option| format!("{index}-{option}")).collect(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_is_reproducible() {
+        let pool = pool(150);
+
+        let options = PaperOptions {
+            question_count: Some(20),
+            shuffle_options: true,
+            shuffle_questions: true,
+        };
+
+        let paper = Paper::generate(&pool, options.clone()).unwrap();
+
+        let mut reversed = pool.clone();
+        reversed.reverse();
+
+        let rebuilt = Paper::draw(&reversed, options, paper.seed).unwrap();
+
+        assert_eq!(paper, rebuilt);
+        assert_eq!(20, paper.question_ids.len());
+        assert_eq!(
+            20,
+            paper
+                .question_ids
+                .iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        );
+
+        let questions = paper.questions(&pool);
+
+        for (position, question) in questions.iter().enumerate() {
+            let mut order = paper.option_order(position, question);
+
+            assert_eq!(order, rebuilt.option_order(position, question));
+
+            order.sort_unstable();
+            assert_eq!(vec![0, 1, 2, 3], order);
+        }
+    }
+
+    #[test]
+    fn test_draw_without_shuffling_keeps_template_order() {
+        let mut pool = pool(10);
+
+        for (index, question) in pool.iter_mut().enumerate() {
+            question.position = (9 - index) as u32;
+        }
+
+        let paper = Paper::draw(&pool, PaperOptions::default(), 7).unwrap();
+
+        assert_eq!(
+            pool.iter().rev().map(|q| q.id.clone()).collect::<Vec<_>>(),
+            paper.question_ids
+        );
+        assert_eq!(vec![0, 1, 2, 3], paper.option_order(0, &pool[9]));
+
+        let options = PaperOptions {
+            question_count: Some(11),
+            ..Default::default()
+        };
+
+        assert!(Paper::draw(&pool, options, 7).is_err());
+    }
+}
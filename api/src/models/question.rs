@@ -1,19 +1,180 @@
-use std::str::FromStr;
+use std::{collections::HashSet, str::FromStr};
 
 use mongodb::{
     bson::{
         doc,
         oid::ObjectId,
         serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
-        Document,
+        to_bson, Document,
     },
-    Collection, Database,
+    Collection, Database, IndexModel,
 };
+use rocket::futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 
-use super::base_model::BaseModel;
+use super::{
+    attachment::{Attachment, MAX_ATTACHMENTS},
+    base_model::BaseModel,
+    error::ModelError,
+    quiz_template::{QuizTemplate, QuizTemplates},
+    scoring::{default_points, PartialCredit},
+};
+use crate::content::ContentFormat;
+use crate::matching::{MatchMode, MatchVerdict};
+
+const QUESTIONS_COLLECTION: &str = "questions";
+
+/// Most hints one question can have.
+const MAX_HINTS: usize = 10;
+
+/// Small slack so that tolerances such as `0.1` survive floating point rounding.
+const NUMERIC_EPSILON: f64 = 1e-9;
+
+/// The shape of a question and the data needed to grade it. Documents stored before kinds
+/// existed have no `kind` field and load as [`QuestionKind::FreeText`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum QuestionKind {
+    FreeText {
+        #[serde(default)]
+        matching: MatchMode,
+    },
+    MultipleChoice {
+        correct_option: usize,
+        options: Vec<String>,
+    },
+    MultipleSelect {
+        correct_options: Vec<usize>,
+        options: Vec<String>,
+    },
+    TrueFalse {
+        correct: bool,
+    },
+    Numeric {
+        tolerance: f64,
+        value: f64,
+    },
+    /// `items` are stored in their correct order.
+    Ordering {
+        items: Vec<String>,
+    },
+}
+
+impl Default for QuestionKind {
+    fn default() -> Self {
+        Self::FreeText {
+            matching: MatchMode::default(),
+        }
+    }
+}
+
+impl QuestionKind {
+    /// The options or items that responses refer to. Empty for kinds without any.
+    pub fn options(&self) -> &[String] {
+        match self {
+            Self::MultipleChoice { options, .. } | Self::MultipleSelect { options, .. } => options,
+            Self::Ordering { items } => items,
+            _ => &[],
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ModelError> {
+        match self {
+            Self::FreeText { matching } => Ok(matching.validate()?),
+            Self::MultipleChoice {
+                correct_option,
+                options,
+            } => {
+                if options.len() < 2 {
+                    return Err("Multiple choice questions need at least two options")?;
+                }
+
+                if *correct_option >= options.len() {
+                    return Err("Correct option is out of range")?;
+                }
+
+                Ok(())
+            }
+            Self::MultipleSelect {
+                correct_options,
+                options,
+            } => {
+                if options.len() < 2 {
+                    return Err("Multiple select questions need at least two options")?;
+                }
 
-const QUESTIONS_COLLECTION: &'static str = "questions";
+                if correct_options
+                    .iter()
+                    .any(|option| *option >= options.len())
+                {
+                    return Err("Correct option is out of range")?;
+                }
+
+                if correct_options.iter().collect::<HashSet<_>>().len() != correct_options.len() {
+                    return Err("Correct options must be unique")?;
+                }
+
+                Ok(())
+            }
+            Self::TrueFalse { .. } => Ok(()),
+            Self::Numeric { tolerance, value } => {
+                if !value.is_finite() || !tolerance.is_finite() || *tolerance < 0.0 {
+                    return Err("Numeric value and tolerance must be finite and non-negative")?;
+                }
+
+                Ok(())
+            }
+            Self::Ordering { items } => {
+                if items.len() < 2 {
+                    return Err("Ordering questions need at least two items")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A learner's response to a question. Choices and orderings refer to option indexes.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(content = "value", rename_all = "snake_case", tag = "type")]
+pub enum Response {
+    Boolean(bool),
+    Choice(usize),
+    Choices(Vec<usize>),
+    Number(f64),
+    Order(Vec<usize>),
+    Text(String),
+}
+
+/// A hint a learner can reveal during an attempt at the cost of part of the question's score.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Hint {
+    /// Fraction of the question's score deducted when the hint is revealed, from 0 to 1.
+    pub penalty: f64,
+    pub text: String,
+}
+
+impl Hint {
+    /// Checks the hints of one question.
+    pub fn validate_all(hints: &[Hint]) -> Result<(), ModelError> {
+        if hints.len() > MAX_HINTS {
+            return Err(format!("Questions can have at most {} hints", MAX_HINTS))?;
+        }
+
+        for hint in hints {
+            if hint.text.trim().is_empty() {
+                return Err("Hints must not be empty")?;
+            }
+
+            if !(0.0..=1.0).contains(&hint.penalty) {
+                return Err("Hint penalties must be between 0 and 1")?;
+            }
+        }
+
+        Ok(())
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Question {
@@ -24,7 +185,33 @@ pub struct Question {
     )]
     pub id: String,
     pub answer: String,
+    #[serde(default)]
+    pub answer_format: ContentFormat,
+    /// Kept out of `update_doc`, so edits never drop them. Use
+    /// [`Questions::add_attachment`] and [`Questions::remove_attachment`].
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Shown once the question has been answered, in `answer_format`.
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// Revealed one at a time, in order.
+    #[serde(default)]
+    pub hints: Vec<Hint>,
+    #[serde(default)]
+    pub kind: QuestionKind,
+    #[serde(default)]
+    pub partial_credit: PartialCredit,
+    /// What a fully correct response is worth.
+    #[serde(default = "default_points")]
+    pub points: f64,
+    /// Place in the quiz template, counted from 0. Ties are broken by `_id`, so questions
+    /// stored before positions existed keep their creation order. Kept out of `update_doc`;
+    /// use [`Questions::reorder`].
+    #[serde(default)]
+    pub position: u32,
     pub question: String,
+    #[serde(default)]
+    pub question_format: ContentFormat,
     #[serde(
         deserialize_with = "deserialize_hex_string_from_object_id",
         serialize_with = "serialize_hex_string_as_object_id"
@@ -32,16 +219,337 @@ pub struct Question {
     pub quiz_template_id: String,
 }
 
+impl Question {
+    /// The part of the question's score lost by revealing its first `revealed` hints.
+    pub fn hint_penalty(&self, revealed: usize) -> f64 {
+        self.hints
+            .iter()
+            .take(revealed)
+            .map(|hint| hint.penalty)
+            .sum::<f64>()
+            .min(1.0)
+    }
+
+    /// The share of this question's points a response earns, from 0 to 1. Only multiple select
+    /// and ordering questions give partial credit, as set by `partial_credit`.
+    pub fn credit(&self, response: &Response) -> f64 {
+        if self.check(response).matched {
+            return 1.0;
+        }
+
+        match (&self.kind, response) {
+            (
+                QuestionKind::MultipleSelect {
+                    correct_options,
+                    options,
+                },
+                Response::Choices(choices),
+            ) => self
+                .partial_credit
+                .for_choices(options.len(), correct_options, choices),
+            (QuestionKind::Ordering { items }, Response::Order(order)) => {
+                self.partial_credit.for_order(items.len(), order)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The response that answers this question correctly.
+    pub fn correct_response(&self) -> Response {
+        match &self.kind {
+            QuestionKind::FreeText { .. } => Response::Text(self.answer.clone()),
+            QuestionKind::MultipleChoice { correct_option, .. } => {
+                Response::Choice(*correct_option)
+            }
+            QuestionKind::MultipleSelect {
+                correct_options, ..
+            } => Response::Choices(correct_options.clone()),
+            QuestionKind::TrueFalse { correct } => Response::Boolean(*correct),
+            QuestionKind::Numeric { value, .. } => Response::Number(*value),
+            QuestionKind::Ordering { items } => Response::Order((0..items.len()).collect()),
+        }
+    }
+
+    /// Grades a response with the rule of this question's kind. A response of the wrong
+    /// shape for the kind is never correct. Only free-text questions report near-misses.
+    pub fn check(&self, response: &Response) -> MatchVerdict {
+        let matched = match (&self.kind, response) {
+            (QuestionKind::FreeText { matching }, Response::Text(text)) => {
+                return matching.check(&self.answer, text);
+            }
+            (QuestionKind::MultipleChoice { correct_option, .. }, Response::Choice(choice)) => {
+                correct_option == choice
+            }
+            (
+                QuestionKind::MultipleSelect {
+                    correct_options, ..
+                },
+                Response::Choices(choices),
+            ) => {
+                correct_options.iter().collect::<HashSet<_>>()
+                    == choices.iter().collect::<HashSet<_>>()
+            }
+            (QuestionKind::TrueFalse { correct }, Response::Boolean(boolean)) => correct == boolean,
+            (QuestionKind::Numeric { tolerance, value }, Response::Number(number)) => {
+                (value - number).abs() <= tolerance + NUMERIC_EPSILON
+            }
+            (QuestionKind::Ordering { items }, Response::Order(order)) => {
+                order.len() == items.len()
+                    && order.iter().enumerate().all(|(index, item)| index == *item)
+            }
+            _ => false,
+        };
+
+        MatchVerdict {
+            confidence: if matched { 1.0 } else { 0.0 },
+            matched,
+        }
+    }
+}
+
 pub struct Questions {
     collection: Collection<Question>,
 }
 
+/// Checks that a new order lists each of the questions exactly once.
+fn validate_order(questions: &[Question], question_ids: &[String]) -> Result<(), ModelError> {
+    let existing = questions
+        .iter()
+        .map(|question| question.id.as_str())
+        .collect::<HashSet<_>>();
+
+    let requested = question_ids
+        .iter()
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+
+    if requested.len() != question_ids.len() {
+        Err("Each question must be listed once")?;
+    }
+
+    if requested != existing {
+        Err("The new order must list every question of the quiz template")?;
+    }
+
+    Ok(())
+}
+
 impl Questions {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection::<Question>(QUESTIONS_COLLECTION),
         }
     }
+
+    pub async fn create_indexes(&self) -> Result<(), ModelError> {
+        self.collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "answer": "text", "question": "text" })
+                    .build(),
+            )
+            .await?;
+
+        self.collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "quiz_template_id": 1, "position": 1, "_id": 1 })
+                    .build(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Gives questions stored before positions existed position 0, so that cursors over
+    /// positions match them.
+    pub async fn fill_missing_positions(&self) -> Result<(), ModelError> {
+        self.collection
+            .update_many(
+                doc! { "position": { "$exists": false } },
+                doc! { "$set": { "position": 0 } },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Every question of a quiz template in position order.
+    pub async fn read_by_quiz_template_id(
+        &self,
+        quiz_template_id: &str,
+    ) -> Result<Vec<Question>, ModelError> {
+        let questions = self
+            .collection
+            .find(doc! {
+                "quiz_template_id": ObjectId::from_str(quiz_template_id)?,
+            })
+            .sort(doc! { "position": 1, "_id": 1 })
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(questions)
+    }
+
+    /// The first `limit` questions of each quiz template in position order, read with one query.
+    pub async fn read_first_by_quiz_template_ids(
+        &self,
+        quiz_template_ids: Vec<ObjectId>,
+        limit: usize,
+    ) -> Result<Vec<Question>, ModelError> {
+        let questions = self
+            .collection
+            .aggregate(vec![
+                doc! { "$match": { "quiz_template_id": { "$in": quiz_template_ids } } },
+                doc! {
+                    "$group": {
+                        "_id": "$quiz_template_id",
+                        "questions": {
+                            "$topN": {
+                                "n": limit as i64,
+                                "sortBy": { "position": 1, "_id": 1 },
+                                "output": "$$ROOT",
+                            },
+                        },
+                    },
+                },
+                doc! { "$unwind": "$questions" },
+                doc! { "$replaceRoot": { "newRoot": "$questions" } },
+            ])
+            .with_type::<Question>()
+            .await?
+            .try_collect()
+            .await?;
+
+        Ok(questions)
+    }
+
+    /// The position after the last question of a quiz template.
+    pub async fn next_position(&self, quiz_template_id: &str) -> Result<u32, ModelError> {
+        let last = self
+            .collection
+            .find_one(doc! {
+                "quiz_template_id": ObjectId::from_str(quiz_template_id)?,
+            })
+            .sort(doc! { "position": -1 })
+            .await?;
+
+        Ok(last.map_or(0, |question| question.position + 1))
+    }
+
+    /// Moves a question to the end of a quiz template.
+    pub async fn move_to_end(
+        &self,
+        question_id: &str,
+        quiz_template_id: &str,
+    ) -> Result<bool, ModelError> {
+        let position = self.next_position(quiz_template_id).await?;
+
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": ObjectId::from_str(question_id)? },
+                doc! { "$set": { "position": position } },
+            )
+            .await?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Puts the questions of `quiz_template` in the order of `question_ids`, which must list
+    /// each of its questions exactly once. Transactions need a replica set, so the order version
+    /// read with the quiz template is claimed before any position is written: when questions
+    /// were added, removed or reordered since, the reorder fails instead of leaving them out.
+    pub async fn reorder(
+        &self,
+        quiz_templates: &QuizTemplates,
+        quiz_template: &QuizTemplate,
+        question_ids: &[String],
+    ) -> Result<(), ModelError> {
+        let quiz_template_id = quiz_template.id.as_str();
+
+        let existing = self.read_by_quiz_template_id(quiz_template_id).await?;
+
+        validate_order(&existing, question_ids)?;
+
+        if !quiz_templates
+            .claim_order_version(quiz_template_id, quiz_template.order_version)
+            .await?
+        {
+            Err("The questions of the quiz template changed while reordering them")?;
+        }
+
+        let ids = question_ids
+            .iter()
+            .map(|id| ObjectId::from_str(id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.collection
+            .update_many(
+                doc! {
+                    "_id": { "$in": ids.clone() },
+                    "quiz_template_id": ObjectId::from_str(quiz_template_id)?,
+                },
+                vec![doc! {
+                    "$set": { "position": { "$indexOfArray": [ids, "$_id"] } },
+                }],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Adds a reference to an uploaded file. Returns `false` when the question does not exist
+    /// or already has [`MAX_ATTACHMENTS`].
+    pub async fn add_attachment(
+        &self,
+        question_id: &str,
+        attachment: &Attachment,
+    ) -> Result<bool, ModelError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": ObjectId::from_str(question_id)?,
+                    format!("attachments.{}", MAX_ATTACHMENTS - 1): { "$exists": false },
+                },
+                doc! { "$push": { "attachments": to_bson(attachment)? } },
+            )
+            .await?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    pub async fn remove_attachment(
+        &self,
+        question_id: &str,
+        attachment_id: &str,
+    ) -> Result<bool, ModelError> {
+        let result = self
+            .collection
+            .update_one(
+                doc! { "_id": ObjectId::from_str(question_id)? },
+                doc! {
+                    "$pull": { "attachments": { "id": ObjectId::from_str(attachment_id)? } },
+                },
+            )
+            .await?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    pub async fn count_by_quiz_template_id(
+        &self,
+        quiz_template_id: &str,
+    ) -> Result<u64, ModelError> {
+        let count = self
+            .collection
+            .count_documents(doc! { "quiz_template_id": ObjectId::from_str(quiz_template_id)? })
+            .await?;
+
+        Ok(count)
+    }
 }
 
 impl BaseModel<Question> for Questions {
@@ -49,11 +557,255 @@ impl BaseModel<Question> for Questions {
         self.collection.clone()
     }
 
-    fn update_doc(&self, model: &Question) -> Result<Document, super::error::ModelError> {
+    fn update_doc(&self, model: &Question) -> Result<Document, ModelError> {
         Ok(doc! {
             "answer": model.answer.clone(),
+            "answer_format": to_bson(&model.answer_format)?,
+            "explanation": model.explanation.clone(),
+            "hints": to_bson(&model.hints)?,
+            "kind": to_bson(&model.kind)?,
+            "partial_credit": to_bson(&model.partial_credit)?,
+            "points": model.points,
             "question": model.question.clone(),
+            "question_format": to_bson(&model.question_format)?,
             "quiz_template_id": ObjectId::from_str(&model.quiz_template_id)?,
         })
     }
 }
+
+#[cfg(test)]
+impl Question {
+    /// A plain question worth one point, for tests.
+    pub fn fixture(
+        quiz_template_id: &str,
+        question: &str,
+        answer: &str,
+        kind: QuestionKind,
+    ) -> Self {
+        Self {
+            id: ObjectId::new().to_string(),
+            answer: answer.to_string(),
+            answer_format: ContentFormat::Plain,
+            attachments: vec![],
+            explanation: None,
+            hints: vec![],
+            kind,
+            partial_credit: PartialCredit::default(),
+            points: 1.0,
+            position: 0,
+            question: question.to_string(),
+            question_format: ContentFormat::Plain,
+            quiz_template_id: quiz_template_id.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::from_document;
+
+    use super::*;
+
+    fn question(kind: QuestionKind) -> Question {
+        Question::fixture(&ObjectId::new().to_string(), "?", "Paris", kind)
+    }
+
+    fn is_correct(question: &Question, response: &Response) -> bool {
+        question.check(response).matched
+    }
+
+    fn options() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string(), "c".to_string()]
+    }
+
+    #[test]
+    fn test_missing_kind_loads_as_free_text() {
+        let question: Question = from_document(doc! {
+            "_id": ObjectId::new(),
+            "answer": "Paris",
+            "question": "Capital of France?",
+            "quiz_template_id": ObjectId::new(),
+        })
+        .unwrap();
+
+        assert_eq!(QuestionKind::default(), question.kind);
+    }
+
+    #[test]
+    fn test_is_correct() {
+        let free_text = question(QuestionKind::default());
+
+        assert!(is_correct(
+            &free_text,
+            &Response::Text(" Paris".to_string())
+        ));
+        assert!(!is_correct(&free_text, &Response::Text("Rome".to_string())));
+        assert!(!is_correct(&free_text, &Response::Choice(0)));
+
+        let multiple_choice = question(QuestionKind::MultipleChoice {
+            correct_option: 1,
+            options: options(),
+        });
+
+        assert!(is_correct(&multiple_choice, &Response::Choice(1)));
+        assert!(!is_correct(&multiple_choice, &Response::Choice(2)));
+
+        let multiple_select = question(QuestionKind::MultipleSelect {
+            correct_options: vec![0, 2],
+            options: options(),
+        });
+
+        assert!(is_correct(&multiple_select, &Response::Choices(vec![2, 0])));
+        assert!(!is_correct(&multiple_select, &Response::Choices(vec![0])));
+        assert!(!is_correct(
+            &multiple_select,
+            &Response::Choices(vec![0, 1, 2])
+        ));
+
+        let true_false = question(QuestionKind::TrueFalse { correct: false });
+
+        assert!(is_correct(&true_false, &Response::Boolean(false)));
+        assert!(!is_correct(&true_false, &Response::Boolean(true)));
+
+        let numeric = question(QuestionKind::Numeric {
+            tolerance: 0.1,
+            value: 2.5,
+        });
+
+        assert!(is_correct(&numeric, &Response::Number(2.6)));
+        assert!(!is_correct(&numeric, &Response::Number(2.7)));
+
+        let ordering = question(QuestionKind::Ordering { items: options() });
+
+        assert!(is_correct(&ordering, &Response::Order(vec![0, 1, 2])));
+        assert!(!is_correct(&ordering, &Response::Order(vec![1, 0, 2])));
+        assert!(!is_correct(&ordering, &Response::Order(vec![0, 1])));
+    }
+
+    #[test]
+    fn test_correct_response() {
+        for kind in [
+            QuestionKind::default(),
+            QuestionKind::MultipleSelect {
+                correct_options: vec![0, 2],
+                options: options(),
+            },
+            QuestionKind::Numeric {
+                tolerance: 0.0,
+                value: 2.5,
+            },
+            QuestionKind::Ordering { items: options() },
+        ] {
+            let question = question(kind);
+
+            assert!(is_correct(&question, &question.correct_response()));
+        }
+    }
+
+    #[test]
+    fn test_credit() {
+        let mut question = question(QuestionKind::MultipleSelect {
+            correct_options: vec![0, 2],
+            options: options(),
+        });
+
+        assert_eq!(1.0, question.credit(&Response::Choices(vec![2, 0])));
+        assert_eq!(0.0, question.credit(&Response::Choices(vec![0])));
+
+        question.partial_credit = PartialCredit::Proportional;
+
+        assert_eq!(2.0 / 3.0, question.credit(&Response::Choices(vec![0])));
+        assert_eq!(0.0, question.credit(&Response::Choice(0)));
+
+        let mut free_text = self::question(QuestionKind::default());
+
+        free_text.partial_credit = PartialCredit::Proportional;
+
+        assert_eq!(0.0, free_text.credit(&Response::Text("Rome".to_string())));
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut question = question(QuestionKind::default());
+
+        question.hints = vec![
+            Hint {
+                penalty: 0.25,
+                text: "A city".to_string(),
+            },
+            Hint {
+                penalty: 0.9,
+                text: "On the Seine".to_string(),
+            },
+        ];
+
+        assert!(Hint::validate_all(&question.hints).is_ok());
+        assert_eq!(0.0, question.hint_penalty(0));
+        assert_eq!(0.25, question.hint_penalty(1));
+        assert_eq!(1.0, question.hint_penalty(2));
+
+        question.hints[0].penalty = 1.5;
+
+        assert!(Hint::validate_all(&question.hints).is_err());
+
+        question.hints[0].penalty = 0.5;
+        question.hints[1].text = " ".to_string();
+
+        assert!(Hint::validate_all(&question.hints).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(QuestionKind::MultipleChoice {
+            correct_option: 3,
+            options: options(),
+        }
+        .validate()
+        .is_err());
+
+        assert!(QuestionKind::MultipleSelect {
+            correct_options: vec![1, 1],
+            options: options(),
+        }
+        .validate()
+        .is_err());
+
+        assert!(QuestionKind::Numeric {
+            tolerance: -1.0,
+            value: 0.0,
+        }
+        .validate()
+        .is_err());
+
+        assert!(QuestionKind::Ordering { items: options() }
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_order() {
+        let questions = (0..3)
+            .map(|_| question(QuestionKind::default()))
+            .collect::<Vec<_>>();
+
+        let ids = questions
+            .iter()
+            .map(|question| question.id.clone())
+            .collect::<Vec<_>>();
+
+        let reversed = ids.iter().rev().cloned().collect::<Vec<_>>();
+
+        assert!(validate_order(&questions, &reversed).is_ok());
+
+        let repeated = vec![ids[0].clone(), ids[1].clone(), ids[1].clone()];
+
+        assert!(validate_order(&questions, &repeated).is_err());
+        assert!(validate_order(&questions, &ids[..2]).is_err());
+
+        let mut extra = ids.clone();
+
+        extra.push(ObjectId::new().to_string());
+
+        assert!(validate_order(&questions, &extra).is_err());
+    }
+}
@@ -1,16 +1,20 @@
+use std::str::FromStr;
+
 use mongodb::{
     bson::{
         doc,
+        oid::ObjectId,
         serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
-        Document,
+        to_bson, Document,
     },
-    Collection, Database,
+    Collection, Database, IndexModel,
 };
+use rocket::futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 
-use super::base_model::BaseModel;
+use super::{base_model::BaseModel, error::ModelError, question::Questions, scoring::Scoring};
 
-const QUIZ_TEMPLATES_COLLECTION: &'static str = "quiz_templates";
+const QUIZ_TEMPLATES_COLLECTION: &str = "quiz_templates";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct QuizTemplate {
@@ -21,6 +25,25 @@ pub struct QuizTemplate {
     )]
     pub id: String,
     pub name: String,
+    /// Raised whenever questions are added to, removed from or reordered in the quiz template,
+    /// so that a reorder can tell the questions it checked have changed. Kept out of
+    /// `update_doc`; use [`QuizTemplates::touch_questions`] and
+    /// [`QuizTemplates::claim_order_version`].
+    #[serde(default)]
+    pub order_version: u32,
+    /// Kept up to date by the writes that add or remove questions, so that templates can be
+    /// filtered on having questions without reading them. Kept out of `update_doc`; use
+    /// [`QuizTemplates::add_to_question_count`].
+    #[serde(default)]
+    pub question_count: u32,
+    /// Seconds allowed for each question, counted from when it is opened.
+    #[serde(default)]
+    pub question_time_limit_seconds: Option<u32>,
+    #[serde(default)]
+    pub scoring: Scoring,
+    /// Seconds allowed for the whole quiz, counted from when an attempt starts.
+    #[serde(default)]
+    pub time_limit_seconds: Option<u32>,
     #[serde(
         deserialize_with = "deserialize_hex_string_from_object_id",
         serialize_with = "serialize_hex_string_as_object_id"
@@ -37,10 +60,13 @@ impl BaseModel<QuizTemplate> for QuizTemplates {
         self.collection.clone()
     }
 
-    fn update_doc(&self, model: &QuizTemplate) -> Result<Document, super::error::ModelError> {
+    fn update_doc(&self, model: &QuizTemplate) -> Result<Document, ModelError> {
         Ok(doc! {
             "name": model.name.clone(),
-            "user_id": model.user_id.clone(),
+            "question_time_limit_seconds": model.question_time_limit_seconds,
+            "scoring": to_bson(&model.scoring)?,
+            "time_limit_seconds": model.time_limit_seconds,
+            "user_id": ObjectId::from_str(&model.user_id)?,
         })
     }
 }
@@ -51,4 +77,91 @@ impl QuizTemplates {
             collection: db.collection::<QuizTemplate>(QUIZ_TEMPLATES_COLLECTION),
         }
     }
+
+    pub async fn create_indexes(&self) -> Result<(), ModelError> {
+        self.collection
+            .create_index(IndexModel::builder().keys(doc! { "name": "text" }).build())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Raises the order version before questions are added to or removed from a quiz template.
+    pub async fn touch_questions(&self, id: &str) -> Result<(), ModelError> {
+        self.collection
+            .update_one(
+                doc! { "_id": ObjectId::from_str(id)? },
+                doc! { "$inc": { "order_version": 1 } },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Changes the question count by `change` after questions were added or removed.
+    pub async fn add_to_question_count(&self, id: &str, change: i64) -> Result<(), ModelError> {
+        self.collection
+            .update_one(
+                doc! { "_id": ObjectId::from_str(id)? },
+                doc! { "$inc": { "question_count": change } },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_question_count(&self, id: &str, count: u64) -> Result<(), ModelError> {
+        self.collection
+            .update_one(
+                doc! { "_id": ObjectId::from_str(id)? },
+                doc! { "$set": { "question_count": count as i64 } },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Counts the questions of quiz templates stored before question counts existed.
+    pub async fn fill_missing_question_counts(
+        &self,
+        questions: &Questions,
+    ) -> Result<(), ModelError> {
+        let mut missing = self
+            .collection
+            .find(doc! { "question_count": { "$exists": false } })
+            .await?;
+
+        while let Some(quiz_template) = missing.try_next().await? {
+            let count = questions
+                .count_by_quiz_template_id(&quiz_template.id)
+                .await?;
+
+            self.set_question_count(&quiz_template.id, count).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Raises the order version only if it is still `version`. Returns whether it was.
+    pub async fn claim_order_version(&self, id: &str, version: u32) -> Result<bool, ModelError> {
+        // Documents stored before versions existed have none, which reads as 0.
+        let current = if version == 0 {
+            doc! { "$in": [0, null] }
+        } else {
+            doc! { "$eq": version }
+        };
+
+        let result = self
+            .collection
+            .update_one(
+                doc! {
+                    "_id": ObjectId::from_str(id)?,
+                    "order_version": current,
+                },
+                doc! { "$inc": { "order_version": 1 } },
+            )
+            .await?;
+
+        Ok(result.modified_count == 1)
+    }
 }
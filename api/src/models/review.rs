@@ -0,0 +1,238 @@
+use std::str::FromStr;
+
+use mongodb::{
+    bson::{
+        doc,
+        oid::ObjectId,
+        serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
+        DateTime, Document,
+    },
+    options::IndexOptions,
+    Collection, Database, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{base_model::BaseModel, error::ModelError, question::Question};
+
+const REVIEWS_COLLECTION: &str = "reviews";
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+const INITIAL_EASE_FACTOR: f64 = 2.5;
+
+const MIN_EASE_FACTOR: f64 = 1.3;
+
+/// Highest recall grade. Grades below 3 count as forgotten.
+pub const MAX_QUALITY: u8 = 5;
+
+/// One user's spaced repetition state for one question.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Review {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        rename = "_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub id: String,
+    pub due_at: DateTime,
+    pub ease_factor: f64,
+    pub interval_days: u32,
+    pub last_reviewed_at: Option<DateTime>,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub question_id: String,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub quiz_template_id: String,
+    /// Successful reviews in a row.
+    pub repetitions: u32,
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        serialize_with = "serialize_hex_string_as_object_id"
+    )]
+    pub user_id: String,
+}
+
+impl Review {
+    /// State of a card that has never been studied. It is due straight away.
+    pub fn new(user_id: &str, question: &Question) -> Self {
+        Self {
+            id: ObjectId::new().to_string(),
+            due_at: DateTime::now(),
+            ease_factor: INITIAL_EASE_FACTOR,
+            interval_days: 0,
+            last_reviewed_at: None,
+            question_id: question.id.clone(),
+            quiz_template_id: question.quiz_template_id.clone(),
+            repetitions: 0,
+            user_id: user_id.to_string(),
+        }
+    }
+
+    pub fn is_new(&self) -> bool {
+        self.last_reviewed_at.is_none()
+    }
+
+    /// Schedules the next review with SM-2 from a recall grade between 0 and 5.
+    pub fn grade(&mut self, quality: u8, now: DateTime) -> Result<(), ModelError> {
+        if quality > MAX_QUALITY {
+            return Err(format!("Quality must be between 0 and {}", MAX_QUALITY))?;
+        }
+
+        if quality < 3 {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval_days) * self.ease_factor).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+
+        let miss = f64::from(MAX_QUALITY - quality);
+
+        self.ease_factor =
+            (self.ease_factor + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE_FACTOR);
+        self.last_reviewed_at = Some(now);
+        self.due_at = DateTime::from_millis(
+            now.timestamp_millis() + i64::from(self.interval_days) * DAY_MILLIS,
+        );
+
+        Ok(())
+    }
+}
+
+/// The first moment after the UTC day containing `now`.
+pub fn end_of_day(now: DateTime) -> DateTime {
+    let millis = now.timestamp_millis();
+
+    DateTime::from_millis(millis - millis.rem_euclid(DAY_MILLIS) + DAY_MILLIS)
+}
+
+pub struct Reviews {
+    collection: Collection<Review>,
+}
+
+impl Reviews {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<Review>(REVIEWS_COLLECTION),
+        }
+    }
+
+    pub async fn create_indexes(&self) -> Result<(), ModelError> {
+        self.collection
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user_id": 1, "question_id": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn read_by_user_id(
+        &self,
+        user_id: &str,
+        question_ids: &[String],
+    ) -> Result<Vec<Review>, ModelError> {
+        let question_ids = question_ids
+            .iter()
+            .map(|id| ObjectId::from_str(id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.read_by_filter(doc! {
+            "question_id": { "$in": question_ids },
+            "user_id": ObjectId::from_str(user_id)?,
+        })
+        .await
+    }
+}
+
+impl BaseModel<Review> for Reviews {
+    fn collection(&self) -> Collection<Review> {
+        self.collection.clone()
+    }
+
+    fn update_doc(&self, model: &Review) -> Result<Document, ModelError> {
+        Ok(doc! {
+            "due_at": model.due_at,
+            "ease_factor": model.ease_factor,
+            "interval_days": model.interval_days,
+            "last_reviewed_at": model.last_reviewed_at,
+            "repetitions": model.repetitions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review() -> Review {
+        let question = Question::fixture(
+            &ObjectId::new().to_string(),
+            "Capital of France?",
+            "Paris",
+            Default::default(),
+        );
+
+        Review::new(&ObjectId::new().to_string(), &question)
+    }
+
+    #[test]
+    fn test_grade_intervals() {
+        let now = DateTime::from_millis(0);
+
+        let mut review = review();
+
+        review.grade(5, now).unwrap();
+        assert_eq!(1, review.interval_days);
+        assert_eq!(DAY_MILLIS, review.due_at.timestamp_millis());
+
+        review.grade(5, now).unwrap();
+        assert_eq!(6, review.interval_days);
+
+        review.grade(4, now).unwrap();
+        assert_eq!(16, review.interval_days);
+        assert_eq!(3, review.repetitions);
+        assert!((review.ease_factor - 2.7).abs() < 1e-9);
+
+        review.grade(1, now).unwrap();
+        assert_eq!(1, review.interval_days);
+        assert_eq!(0, review.repetitions);
+        assert!(!review.is_new());
+    }
+
+    #[test]
+    fn test_ease_factor_floor() {
+        let mut review = review();
+
+        for _ in 0..10 {
+            review.grade(0, DateTime::now()).unwrap();
+        }
+
+        assert_eq!(MIN_EASE_FACTOR, review.ease_factor);
+        assert!(review.grade(MAX_QUALITY + 1, DateTime::now()).is_err());
+    }
+
+    #[test]
+    fn test_end_of_day() {
+        assert_eq!(
+            DAY_MILLIS,
+            end_of_day(DateTime::from_millis(DAY_MILLIS - 1)).timestamp_millis()
+        );
+        assert_eq!(
+            2 * DAY_MILLIS,
+            end_of_day(DateTime::from_millis(DAY_MILLIS)).timestamp_millis()
+        );
+    }
+}
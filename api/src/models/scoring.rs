@@ -0,0 +1,205 @@
+//! Points, partial credit and pass marks.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::ModelError;
+
+pub const MAX_SCORE_DECIMALS: u32 = 4;
+
+/// Most points one question can be worth.
+const MAX_POINTS: f64 = 1000.0;
+
+pub fn default_points() -> f64 {
+    1.0
+}
+
+pub fn validate_points(points: f64) -> Result<(), ModelError> {
+    if !points.is_finite() || points <= 0.0 || points > MAX_POINTS {
+        Err(format!("Points must be above 0 and at most {}", MAX_POINTS))?;
+    }
+
+    Ok(())
+}
+
+/// How much of a question's points a partly right response to a multiple select or ordering
+/// question earns. Other kinds are always all or nothing.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialCredit {
+    #[default]
+    AllOrNothing,
+    /// The share of options marked correctly, or of items in their correct place.
+    Proportional,
+    /// Right choices or places minus wrong ones, as a share of the right ones. Never below 0.
+    ProportionalWithPenalty,
+}
+
+fn share(part: usize, whole: usize) -> f64 {
+    match whole {
+        0 => 0.0,
+        _ => part as f64 / whole as f64,
+    }
+}
+
+impl PartialCredit {
+    /// Credit for choosing `chosen` out of `option_count` options when `correct` are right.
+    pub fn for_choices(&self, option_count: usize, correct: &[usize], chosen: &[usize]) -> f64 {
+        let correct = correct.iter().collect::<HashSet<_>>();
+        let chosen = chosen.iter().collect::<HashSet<_>>();
+
+        if chosen.iter().any(|option| **option >= option_count) {
+            return 0.0;
+        }
+
+        match self {
+            Self::AllOrNothing => share((correct == chosen) as usize, 1),
+            Self::Proportional => share(
+                (0..option_count)
+                    .filter(|option| correct.contains(option) == chosen.contains(option))
+                    .count(),
+                option_count,
+            ),
+            Self::ProportionalWithPenalty => {
+                let right = chosen.intersection(&correct).count();
+                let wrong = chosen.len() - right;
+
+                share(right.saturating_sub(wrong), correct.len())
+            }
+        }
+    }
+
+    /// Credit for putting `item_count` items in `order`, where item `n` belongs in place `n`.
+    pub fn for_order(&self, item_count: usize, order: &[usize]) -> f64 {
+        if order.len() != item_count {
+            return 0.0;
+        }
+
+        let right = order
+            .iter()
+            .enumerate()
+            .filter(|(place, item)| place == *item)
+            .count();
+
+        match self {
+            Self::AllOrNothing => share((right == item_count) as usize, 1),
+            Self::Proportional => share(right, item_count),
+            Self::ProportionalWithPenalty => {
+                share(right.saturating_sub(item_count - right), item_count)
+            }
+        }
+    }
+}
+
+/// How the attempts of a quiz template are marked.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Scoring {
+    /// Percentage needed to pass. Attempts are neither passed nor failed without one.
+    pub pass_mark: Option<f64>,
+    /// Decimal places that scores and percentages are rounded to.
+    pub score_decimals: u32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            pass_mark: None,
+            score_decimals: 2,
+        }
+    }
+}
+
+impl Scoring {
+    pub fn validate(&self) -> Result<(), ModelError> {
+        if self
+            .pass_mark
+            .is_some_and(|pass_mark| !(0.0..=100.0).contains(&pass_mark))
+        {
+            Err("Pass mark must be a percentage between 0 and 100")?;
+        }
+
+        if self.score_decimals > MAX_SCORE_DECIMALS {
+            Err(format!(
+                "Scores can be rounded to at most {} decimals",
+                MAX_SCORE_DECIMALS
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Rounds half away from zero to `score_decimals` places.
+    pub fn round(&self, value: f64) -> f64 {
+        let factor = 10f64.powi(self.score_decimals as i32);
+
+        (value * factor).round() / factor
+    }
+
+    pub fn passed(&self, percentage: f64) -> Option<bool> {
+        self.pass_mark.map(|pass_mark| percentage >= pass_mark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_choices() {
+        let credit =
+            |policy: PartialCredit, chosen: &[usize]| policy.for_choices(4, &[0, 1], chosen);
+
+        assert_eq!(1.0, credit(PartialCredit::AllOrNothing, &[1, 0]));
+        assert_eq!(0.0, credit(PartialCredit::AllOrNothing, &[0]));
+
+        assert_eq!(0.75, credit(PartialCredit::Proportional, &[0]));
+        assert_eq!(0.5, credit(PartialCredit::Proportional, &[0, 1, 2, 3]));
+        assert_eq!(0.0, credit(PartialCredit::Proportional, &[4]));
+
+        assert_eq!(0.5, credit(PartialCredit::ProportionalWithPenalty, &[0]));
+        assert_eq!(0.0, credit(PartialCredit::ProportionalWithPenalty, &[0, 2]));
+        assert_eq!(
+            0.0,
+            credit(PartialCredit::ProportionalWithPenalty, &[0, 1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_for_order() {
+        assert_eq!(0.0, PartialCredit::AllOrNothing.for_order(4, &[0, 1, 3, 2]));
+        assert_eq!(0.5, PartialCredit::Proportional.for_order(4, &[0, 1, 3, 2]));
+        assert_eq!(
+            0.0,
+            PartialCredit::ProportionalWithPenalty.for_order(4, &[0, 1, 3, 2])
+        );
+        assert_eq!(
+            0.2,
+            PartialCredit::ProportionalWithPenalty.for_order(5, &[0, 1, 2, 4, 3])
+        );
+        assert_eq!(0.0, PartialCredit::Proportional.for_order(4, &[0, 1]));
+    }
+
+    #[test]
+    fn test_scoring() {
+        let scoring = Scoring {
+            pass_mark: Some(60.0),
+            score_decimals: 1,
+        };
+
+        assert!(scoring.validate().is_ok());
+        assert_eq!(66.7, scoring.round(200.0 / 3.0));
+        assert_eq!(Some(true), scoring.passed(60.0));
+        assert_eq!(Some(false), scoring.passed(59.9));
+        assert_eq!(None, Scoring::default().passed(0.0));
+
+        assert!(Scoring {
+            pass_mark: Some(101.0),
+            score_decimals: 2,
+        }
+        .validate()
+        .is_err());
+        assert!(validate_points(0.0).is_err());
+        assert!(validate_points(2.5).is_ok());
+    }
+}
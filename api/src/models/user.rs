@@ -9,7 +9,7 @@ use mongodb::{
         doc,
         oid::ObjectId,
         serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
-        Document,
+        to_bson, Document,
     },
     Collection, Database,
 };
@@ -17,8 +17,37 @@ use serde::{Deserialize, Serialize};
 
 use super::{base_model::BaseModel, error::ModelError};
 
-const BASIC_AUTH_USERS_COLLECTION: &'static str = "basic_auth_users";
-const USERS_COLLECTION: &'static str = "users";
+const BASIC_AUTH_USERS_COLLECTION: &str = "basic_auth_users";
+const USERS_COLLECTION: &str = "users";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Author,
+    Learner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Author => "author",
+            Self::Learner => "learner",
+        }
+    }
+}
+
+/// Roles given to new accounts. Authoring is granted by an admin.
+fn default_roles() -> Vec<Role> {
+    vec![Role::Learner]
+}
+
+/// Roles of accounts stored before roles existed. Anyone could author quiz templates then, so
+/// those accounts keep doing so instead of losing access to the templates they made.
+fn legacy_roles() -> Vec<Role> {
+    vec![Role::Author, Role::Learner]
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct User {
@@ -28,14 +57,17 @@ pub struct User {
         serialize_with = "serialize_hex_string_as_object_id"
     )]
     pub id: String,
+    #[serde(default = "legacy_roles")]
+    pub roles: Vec<Role>,
     pub username: String,
 }
 
-impl Into<Document> for User {
-    fn into(self) -> Document {
+impl From<User> for Document {
+    fn from(value: User) -> Self {
         doc! {
-            "_id": self.id,
-            "username": self.username,
+            "_id": value.id,
+            "roles": value.roles.iter().map(Role::as_str).collect::<Vec<_>>(),
+            "username": value.username,
         }
     }
 }
@@ -52,6 +84,7 @@ impl BaseModel<User> for Users {
 
     fn update_doc(&self, model: &User) -> Result<Document, ModelError> {
         Ok(doc! {
+            "roles": to_bson(&model.roles)?,
             "username": model.username.clone(),
         })
     }
@@ -76,12 +109,13 @@ impl Users {
             })
             .await?;
 
-        if existing_models.len() > 0 {
+        if !existing_models.is_empty() {
             return Err(format!("Username '{}' already exists.", username))?;
         }
 
         let model = User {
             id: ObjectId::new().to_string(),
+            roles: default_roles(),
             username: username.to_string(),
         };
 
@@ -94,6 +128,29 @@ impl Users {
         Ok(model)
     }
 
+    /// Adds the admin role to the accounts with these usernames. Unknown usernames are skipped.
+    pub async fn grant_admin(&self, usernames: &[String]) -> Result<(), ModelError> {
+        if usernames.is_empty() {
+            return Ok(());
+        }
+
+        let models = self
+            .read_by_filter(doc! {
+                "username": { "$in": usernames },
+            })
+            .await?;
+
+        for mut model in models {
+            if !model.roles.contains(&Role::Admin) {
+                model.roles.push(Role::Admin);
+
+                self.update_by_id(&model.id, &model).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn login_with_basic_auth(
         &self,
         username: &str,
@@ -160,7 +217,7 @@ impl BasicAuthUsers {
         let salt = SaltString::generate(&mut OsRng);
 
         let password_hash = Argon2::default()
-            .hash_password(&password.as_bytes(), &salt)?
+            .hash_password(password.as_bytes(), &salt)?
             .to_string();
 
         self.create(&BasicAuthUser {
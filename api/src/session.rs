@@ -4,17 +4,17 @@ use rand::{seq::IteratorRandom, thread_rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
 use redis::{aio::MultiplexedConnection, AsyncCommands, RedisError};
 use rocket::{
-    http::{Cookie, CookieJar, Status},
+    http::{Cookie, CookieJar},
     outcome::try_outcome,
     request::{FromRequest, Outcome},
     Request, State,
 };
 
-const CHOICES: &'static str = "abcdefghijklmnopqrstuvwxyz0123456789";
+const CHOICES: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
 
 const FIFTEEN_DAYS: u64 = 60 * 60 * 24 * 15;
 
-const SESSION_COOKIE: &'static str = "quiz_session";
+const SESSION_COOKIE: &str = "quiz_session";
 
 pub struct SessionStorage {
     connection: MultiplexedConnection,
@@ -150,17 +150,9 @@ impl<'r> FromRequest<'r> for SessionStorage {
     type Error = ();
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let session_client: &State<redis::Client> =
-            try_outcome!(request.guard::<&State<redis::Client>>().await);
-
-        let session_connection = match session_client.get_multiplexed_async_connection().await {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Error getting redis connection: {:?}", e);
-                return Outcome::Error((Status::InternalServerError, ()));
-            }
-        };
+        let session_connection: &State<MultiplexedConnection> =
+            try_outcome!(request.guard::<&State<MultiplexedConnection>>().await);
 
-        Outcome::Success(Self::new(session_connection))
+        Outcome::Success(Self::new(session_connection.inner().clone()))
     }
 }
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use mongodb::{bson::DateTime, Database};
+use rocket::{
+    fairing::AdHoc,
+    tokio::{self, time::interval},
+};
+
+use crate::models::{Attempt, Attempts, BaseModel, ModelError, Questions};
+
+/// How often open attempts are checked for expired deadlines.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Submits the attempt if it has run out of time. Returns whether it was submitted here, which
+/// is not the case when another request submitted it first.
+pub async fn submit_if_expired(
+    attempts: &Attempts,
+    questions: &Questions,
+    attempt: &mut Attempt,
+) -> Result<bool, ModelError> {
+    if !attempt.is_expired(DateTime::now()) {
+        return Ok(false);
+    }
+
+    let template_questions = questions
+        .read_by_quiz_template_id(&attempt.quiz_template_id)
+        .await?;
+
+    attempt.submit(&template_questions);
+
+    if attempts.update_in_progress(attempt).await? {
+        return Ok(true);
+    }
+
+    // Someone else submitted it first, so theirs is the result that counts.
+    if let Some(current) = attempts.read_by_id(&attempt.id).await? {
+        *attempt = current;
+    }
+
+    Ok(false)
+}
+
+async fn sweep(attempts: &Attempts, questions: &Questions) -> Result<(), ModelError> {
+    for mut attempt in attempts.read_expired(DateTime::now()).await? {
+        submit_if_expired(attempts, questions, &mut attempt).await?;
+    }
+
+    Ok(())
+}
+
+/// Submits expired attempts in the background, so that results do not wait for the learner to
+/// come back.
+pub fn fairing() -> AdHoc {
+    AdHoc::on_liftoff("Attempt sweeper", |rocket| {
+        Box::pin(async move {
+            let Some(db) = rocket.state::<Database>() else {
+                eprintln!("Attempt sweeper has no database");
+
+                return;
+            };
+
+            let attempts = Attempts::new(db);
+            let questions = Questions::new(db);
+
+            tokio::spawn(async move {
+                let mut ticks = interval(SWEEP_INTERVAL);
+
+                loop {
+                    ticks.tick().await;
+
+                    if let Err(e) = sweep(&attempts, &questions).await {
+                        eprintln!("Error sweeping attempts: {:?}", e);
+                    }
+                }
+            });
+        })
+    })
+}
@@ -16,7 +16,7 @@ import {
 import { Link as RouterLink } from "react-router-dom";
 
 import { Page } from "../components/Page";
-import { QuizTemplate, User } from "../gql/graphql";
+import { QuizTemplate } from "../gql/graphql";
 import { GET_QUIZ_TEMPLATES_QUERY } from "../queries";
 import { AddQuizTemplateDialog } from "../components/quiz-template-dialogs";
 import { useError } from "../hooks/useError";
@@ -42,10 +42,9 @@ const HomePageLoading: FC = () => (
 
 type HomePageDataProps = {
   quizTemplates: Omit<QuizTemplate, "questions">[];
-  user: User;
 };
 
-const HomePageData: FC<HomePageDataProps> = ({ quizTemplates, user }) => {
+const HomePageData: FC<HomePageDataProps> = ({ quizTemplates }) => {
   const [isDialogOpen, setDialogOpen] = useState(false);
 
   const handleDialogClose = useCallback(() => {
@@ -85,7 +84,6 @@ const HomePageData: FC<HomePageDataProps> = ({ quizTemplates, user }) => {
       <AddQuizTemplateDialog
         onClose={handleDialogClose}
         open={isDialogOpen}
-        user={user}
       />
     </>
   );
@@ -106,7 +104,11 @@ export const HomePage: FC = () => {
     if (error) return <HomePageError error={error} />;
     if (loading) return <HomePageLoading />;
     if (!data) return <HomePageError error="Unexpected error: No data" />;
-    return <HomePageData quizTemplates={data.quizTemplate.byUserId} user={user} />;
+    return (
+      <HomePageData
+        quizTemplates={data.quizTemplate.byUserId.edges.map(({ node }) => node)}
+      />
+    );
   })();
 
   return (
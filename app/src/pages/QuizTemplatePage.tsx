@@ -25,7 +25,7 @@ import { useParams } from "react-router-dom";
 
 import { AddQuestionDialog, EditQuestionDialog } from "../components/question-dialogs";
 import { Page } from "../components/Page";
-import { QuizTemplate, User } from "../gql/graphql";
+import { GetQuizTemplateQuery } from "../gql/graphql";
 import { DELETE_QUESTION_MUTATION, GET_QUIZ_TEMPLATE_QUERY } from "../queries";
 import { DeleteQuizTemplateDialog, EditQuizTemplateDialog } from "../components/quiz-template-dialogs";
 import { useError } from "../hooks/useError";
@@ -56,13 +56,11 @@ type ModalKey =
   | "edit-quiz-template";
 
 type QuizTemplatePageDataProps = {
-  quizTemplate: QuizTemplate;
-  user: User;
+  quizTemplate: NonNullable<GetQuizTemplateQuery["quizTemplate"]["byId"]>;
 };
 
 const QuizTemplatePageData: FC<QuizTemplatePageDataProps> = ({
   quizTemplate,
-  user,
 }) => {
   const [openDialog, setOpenDialog] = useState<ModalKey | null>(null);
   const [editQuestionId, setEditQuestionId] = useState<string | null>(null);
@@ -129,7 +127,7 @@ const QuizTemplatePageData: FC<QuizTemplatePageDataProps> = ({
       </Card>
       <Paper>
         <List>
-          {quizTemplate.questions.map((question) => (
+          {quizTemplate.questions.edges.map(({ node: question }) => (
             <ListItem
               key={question.id}
               secondaryAction={
@@ -177,7 +175,6 @@ const QuizTemplatePageData: FC<QuizTemplatePageDataProps> = ({
         onClose={handleDialogClose}
         open={openDialog === "edit-quiz-template"}
         quizTemplate={quizTemplate}
-        user={user}
       />
     </>
   );
@@ -200,7 +197,7 @@ export const QuizTemplatePage: FC = () => {
     if (!quizTemplateId || loading) return <QuizTemplatePageLoading />;
     if (!data?.quizTemplate.byId)
       return <QuizTemplatePageError error="No data" />;
-    return <QuizTemplatePageData quizTemplate={data.quizTemplate.byId} user={user} />;
+    return <QuizTemplatePageData quizTemplate={data.quizTemplate.byId} />;
   })();
 
   return (
@@ -73,9 +73,13 @@ export const GET_QUIZ_TEMPLATE_QUERY = graphql(`
         id
         name
         questions {
-          answer
-          id
-          question
+          edges {
+            node {
+              answer
+              id
+              question
+            }
+          }
         }
       }
     }
@@ -86,8 +90,12 @@ export const GET_QUIZ_TEMPLATES_QUERY = graphql(`
   query GetQuizTemplates($userId: ID!) {
     quizTemplate {
       byUserId(userId: $userId) {
-        id
-        name
+        edges {
+          node {
+            id
+            name
+          }
+        }
       }
     }
   }